@@ -199,6 +199,13 @@ impl OpState {
     }
 }
 
+// set by KIL or an invalid instruction register state, the cpu does nothing until reset
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct CpuJam {
+    pub pc: u16,
+    pub opcode: u8,
+}
+
 //internal state of cpu
 #[derive(Debug, Clone, Copy)]
 pub struct Context
@@ -214,6 +221,7 @@ pub struct Context
     pub sp: u8,   
     pub int_vec_low: u8, 
     pub nmi_detected: bool,
+    pub jam: Option<CpuJam>,
 }
 
 impl Context
@@ -233,6 +241,7 @@ impl Context
             ops: OpState::new(),
             int_vec_low: IRQ_BRK_VEC_LOW,
             nmi_detected: false,
+            jam: None,
         }
     }
 
@@ -247,6 +256,7 @@ impl Context
         self.ops = OpState::new();
         self.int_vec_low = IRQ_BRK_VEC_LOW;
         self.nmi_detected = false;
+        self.jam = None;
     }
 }
 
@@ -0,0 +1,234 @@
+use std::fmt;
+
+/*
+    NMOS 6502 and 2a03 disassembler, unofficial opcodes use the usual names and are flagged.
+    Memory is read through peek so the caller decides what is mapped, it must not have side effects.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Imp,    // implied
+    Acc,    // accumulator
+    Imm,    // #$nn
+    Zp,     // $nn
+    Zpx,    // $nn,X
+    Zpy,    // $nn,Y
+    Abs,    // $nnnn
+    Abx,    // $nnnn,X
+    Aby,    // $nnnn,Y
+    Ind,    // ($nnnn)
+    Izx,    // ($nn,X)
+    Izy,    // ($nn),Y
+    Rel,    // branch offset
+}
+
+use Mode::*;
+
+// mnemonic and addressing mode by opcode, unofficial opcodes are prefixed with '*'
+pub const OPCODES: [(&str, Mode); 256] = [
+    // 0x00
+    ("BRK", Imp), ("ORA", Izx), ("*KIL", Imp), ("*SLO", Izx), ("*NOP", Zp), ("ORA", Zp), ("ASL", Zp), ("*SLO", Zp),
+    ("PHP", Imp), ("ORA", Imm), ("ASL", Acc), ("*ANC", Imm), ("*NOP", Abs), ("ORA", Abs), ("ASL", Abs), ("*SLO", Abs),
+    // 0x10
+    ("BPL", Rel), ("ORA", Izy), ("*KIL", Imp), ("*SLO", Izy), ("*NOP", Zpx), ("ORA", Zpx), ("ASL", Zpx), ("*SLO", Zpx),
+    ("CLC", Imp), ("ORA", Aby), ("*NOP", Imp), ("*SLO", Aby), ("*NOP", Abx), ("ORA", Abx), ("ASL", Abx), ("*SLO", Abx),
+    // 0x20
+    ("JSR", Abs), ("AND", Izx), ("*KIL", Imp), ("*RLA", Izx), ("BIT", Zp), ("AND", Zp), ("ROL", Zp), ("*RLA", Zp),
+    ("PLP", Imp), ("AND", Imm), ("ROL", Acc), ("*ANC", Imm), ("BIT", Abs), ("AND", Abs), ("ROL", Abs), ("*RLA", Abs),
+    // 0x30
+    ("BMI", Rel), ("AND", Izy), ("*KIL", Imp), ("*RLA", Izy), ("*NOP", Zpx), ("AND", Zpx), ("ROL", Zpx), ("*RLA", Zpx),
+    ("SEC", Imp), ("AND", Aby), ("*NOP", Imp), ("*RLA", Aby), ("*NOP", Abx), ("AND", Abx), ("ROL", Abx), ("*RLA", Abx),
+    // 0x40
+    ("RTI", Imp), ("EOR", Izx), ("*KIL", Imp), ("*SRE", Izx), ("*NOP", Zp), ("EOR", Zp), ("LSR", Zp), ("*SRE", Zp),
+    ("PHA", Imp), ("EOR", Imm), ("LSR", Acc), ("*ALR", Imm), ("JMP", Abs), ("EOR", Abs), ("LSR", Abs), ("*SRE", Abs),
+    // 0x50
+    ("BVC", Rel), ("EOR", Izy), ("*KIL", Imp), ("*SRE", Izy), ("*NOP", Zpx), ("EOR", Zpx), ("LSR", Zpx), ("*SRE", Zpx),
+    ("CLI", Imp), ("EOR", Aby), ("*NOP", Imp), ("*SRE", Aby), ("*NOP", Abx), ("EOR", Abx), ("LSR", Abx), ("*SRE", Abx),
+    // 0x60
+    ("RTS", Imp), ("ADC", Izx), ("*KIL", Imp), ("*RRA", Izx), ("*NOP", Zp), ("ADC", Zp), ("ROR", Zp), ("*RRA", Zp),
+    ("PLA", Imp), ("ADC", Imm), ("ROR", Acc), ("*ARR", Imm), ("JMP", Ind), ("ADC", Abs), ("ROR", Abs), ("*RRA", Abs),
+    // 0x70
+    ("BVS", Rel), ("ADC", Izy), ("*KIL", Imp), ("*RRA", Izy), ("*NOP", Zpx), ("ADC", Zpx), ("ROR", Zpx), ("*RRA", Zpx),
+    ("SEI", Imp), ("ADC", Aby), ("*NOP", Imp), ("*RRA", Aby), ("*NOP", Abx), ("ADC", Abx), ("ROR", Abx), ("*RRA", Abx),
+    // 0x80
+    ("*NOP", Imm), ("STA", Izx), ("*NOP", Imm), ("*SAX", Izx), ("STY", Zp), ("STA", Zp), ("STX", Zp), ("*SAX", Zp),
+    ("DEY", Imp), ("*NOP", Imm), ("TXA", Imp), ("*XAA", Imm), ("STY", Abs), ("STA", Abs), ("STX", Abs), ("*SAX", Abs),
+    // 0x90
+    ("BCC", Rel), ("STA", Izy), ("*KIL", Imp), ("*AHX", Izy), ("STY", Zpx), ("STA", Zpx), ("STX", Zpy), ("*SAX", Zpy),
+    ("TYA", Imp), ("STA", Aby), ("TXS", Imp), ("*TAS", Aby), ("*SHY", Abx), ("STA", Abx), ("*SHX", Aby), ("*AHX", Aby),
+    // 0xA0
+    ("LDY", Imm), ("LDA", Izx), ("LDX", Imm), ("*LAX", Izx), ("LDY", Zp), ("LDA", Zp), ("LDX", Zp), ("*LAX", Zp),
+    ("TAY", Imp), ("LDA", Imm), ("TAX", Imp), ("*LAX", Imm), ("LDY", Abs), ("LDA", Abs), ("LDX", Abs), ("*LAX", Abs),
+    // 0xB0
+    ("BCS", Rel), ("LDA", Izy), ("*KIL", Imp), ("*LAX", Izy), ("LDY", Zpx), ("LDA", Zpx), ("LDX", Zpy), ("*LAX", Zpy),
+    ("CLV", Imp), ("LDA", Aby), ("TSX", Imp), ("*LAS", Aby), ("LDY", Abx), ("LDA", Abx), ("LDX", Aby), ("*LAX", Aby),
+    // 0xC0
+    ("CPY", Imm), ("CMP", Izx), ("*NOP", Imm), ("*DCP", Izx), ("CPY", Zp), ("CMP", Zp), ("DEC", Zp), ("*DCP", Zp),
+    ("INY", Imp), ("CMP", Imm), ("DEX", Imp), ("*AXS", Imm), ("CPY", Abs), ("CMP", Abs), ("DEC", Abs), ("*DCP", Abs),
+    // 0xD0
+    ("BNE", Rel), ("CMP", Izy), ("*KIL", Imp), ("*DCP", Izy), ("*NOP", Zpx), ("CMP", Zpx), ("DEC", Zpx), ("*DCP", Zpx),
+    ("CLD", Imp), ("CMP", Aby), ("*NOP", Imp), ("*DCP", Aby), ("*NOP", Abx), ("CMP", Abx), ("DEC", Abx), ("*DCP", Abx),
+    // 0xE0
+    ("CPX", Imm), ("SBC", Izx), ("*NOP", Imm), ("*ISB", Izx), ("CPX", Zp), ("SBC", Zp), ("INC", Zp), ("*ISB", Zp),
+    ("INX", Imp), ("SBC", Imm), ("NOP", Imp), ("*SBC", Imm), ("CPX", Abs), ("SBC", Abs), ("INC", Abs), ("*ISB", Abs),
+    // 0xF0
+    ("BEQ", Rel), ("SBC", Izy), ("*KIL", Imp), ("*ISB", Izy), ("*NOP", Zpx), ("SBC", Zpx), ("INC", Zpx), ("*ISB", Zpx),
+    ("SED", Imp), ("SBC", Aby), ("*NOP", Imp), ("*ISB", Aby), ("*NOP", Abx), ("SBC", Abx), ("INC", Abx), ("*ISB", Abx),
+];
+
+pub fn operand_length(mode: Mode) -> usize {
+    match mode {
+        Imp | Acc => 0,
+        Imm | Zp | Zpx | Zpy | Izx | Izy | Rel => 1,
+        Abs | Abx | Aby | Ind => 2,
+    }
+}
+
+pub fn instruction_length(opcode: u8) -> usize {
+    1 + operand_length(OPCODES[opcode as usize].1)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Disassembly {
+    pub address: u16,
+    pub opcode: u8,
+    // one byte operands are in the low byte, the branch offset too
+    pub operand: u16,
+    pub mnemonic: &'static str,
+    pub mode: Mode,
+    pub unofficial: bool,
+}
+
+impl Disassembly {
+    // opcode and operand bytes
+    pub fn byte_len(&self) -> usize {
+        1 + operand_length(self.mode)
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        let [lo, hi] = self.operand.to_le_bytes();
+        [self.opcode, lo, hi][..self.byte_len()].to_vec()
+    }
+
+    pub fn next_address(&self) -> u16 {
+        self.address.wrapping_add(self.byte_len() as u16)
+    }
+
+    // branch target, or the address jumped to by JMP abs and JSR
+    pub fn target(&self) -> Option<u16> {
+        match self.mode {
+            Rel => Some(self.next_address().wrapping_add(self.operand as u8 as i8 as u16)),
+            Abs if self.opcode == 0x20 || self.opcode == 0x4C => Some(self.operand),
+            _ => None,
+        }
+    }
+
+    // the memory address the operand refers to, the base address for indexed and indirect modes
+    pub fn operand_address(&self) -> Option<u16> {
+        match self.mode {
+            Imp | Acc | Imm => None,
+            Zp | Zpx | Zpy | Izx | Izy => Some(self.operand & 0xFF),
+            Abs | Abx | Aby | Ind => Some(self.operand),
+            Rel => self.target(),
+        }
+    }
+
+    // the operand as written in source, branches show the target address
+    pub fn operand_text(&self) -> String {
+        let address = match self.mode {
+            Zp | Zpx | Zpy | Izx | Izy => format!("${:02X}", self.operand as u8),
+            _ => format!("${:04X}", self.operand_address().unwrap_or(0)),
+        };
+        self.format_operand(&address)
+    }
+
+    // same as operand_text with a label in place of the operand address
+    pub fn operand_text_labeled(&self, label: &str) -> String {
+        self.format_operand(label)
+    }
+
+    fn format_operand(&self, address: &str) -> String {
+        match self.mode {
+            Imp => String::new(),
+            Acc => "A".to_string(),
+            Imm => format!("#${:02X}", self.operand as u8),
+            Zp | Abs | Rel => address.to_string(),
+            Zpx | Abx => format!("{},X", address),
+            Zpy | Aby => format!("{},Y", address),
+            Ind => format!("({})", address),
+            Izx => format!("({},X)", address),
+            Izy => format!("({}),Y", address),
+        }
+    }
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mode {
+            Imp => write!(f, "{}", self.mnemonic),
+            _ => write!(f, "{} {}", self.mnemonic, self.operand_text()),
+        }
+    }
+}
+
+pub fn disassemble<F: FnMut(u16) -> u8>(address: u16, mut peek: F) -> Disassembly {
+    let opcode = peek(address);
+    let (mnemonic, mode) = OPCODES[opcode as usize];
+    let operand = match operand_length(mode) {
+        0 => 0,
+        1 => peek(address.wrapping_add(1)) as u16,
+        _ => u16::from_le_bytes([peek(address.wrapping_add(1)), peek(address.wrapping_add(2))]),
+    };
+
+    Disassembly {
+        address,
+        opcode,
+        operand,
+        mnemonic: mnemonic.trim_start_matches('*'),
+        mode,
+        unofficial: mnemonic.starts_with('*'),
+    }
+}
+
+// count instructions one after the other, starting at address
+pub fn disassemble_range<F: FnMut(u16) -> u8>(address: u16, count: usize, mut peek: F) -> Vec<Disassembly> {
+    let mut lines = Vec::with_capacity(count);
+    let mut address = address;
+    for _ in 0..count {
+        let line = disassemble(address, &mut peek);
+        address = line.next_address();
+        lines.push(line);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_disassemble() {
+        // lda ($10),y, bne -4, jmp ($02FF), *slo $1234,x, asl a
+        let program = [0xB1, 0x10, 0xD0, 0xFC, 0x6C, 0xFF, 0x02, 0x1F, 0x34, 0x12, 0x0A];
+        let lines = disassemble_range(0x8000, 5, |address| program.get(address as usize - 0x8000).copied().unwrap_or(0));
+        let text: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        assert_eq!(text, ["LDA ($10),Y", "BNE $8000", "JMP ($02FF)", "SLO $1234,X", "ASL A"]);
+
+        assert_eq!(lines[1].target(), Some(0x8000));
+        assert_eq!(lines[2].bytes(), [0x6C, 0xFF, 0x02]);
+        assert!(lines[3].unofficial && !lines[0].unofficial);
+        assert_eq!(lines[4].address, 0x800A);
+        assert_eq!(lines[0].operand_address(), Some(0x10));
+        assert_eq!(lines[0].operand_text_labeled("ptr"), "(ptr),Y");
+        assert_eq!(lines[4].operand_address(), None);
+    }
+
+    #[test]
+    fn test_instruction_length() {
+        assert_eq!(instruction_length(0x00), 1);
+        assert_eq!(instruction_length(0xA9), 2);
+        assert_eq!(instruction_length(0x20), 3);
+        assert_eq!(instruction_length(0x02), 1);
+    }
+}
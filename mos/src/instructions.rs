@@ -14,6 +14,43 @@ pub trait Instruction {
     fn execute(cpu: &mut Context);
 }
 
+// adc and sbc are the only instructions whose behaviour depends on the core
+pub trait DecimalMode {
+    type Adc: Instruction;
+    type Sbc: Instruction;
+}
+
+// 2a03, the D flag is ignored
+pub struct NoDecimal {}
+impl DecimalMode for NoDecimal {
+    type Adc = AdcNoDec;
+    type Sbc = SbcNoDec;
+}
+
+// nmos 6502, N V Z are set from the binary result
+pub struct NmosDecimal {}
+impl DecimalMode for NmosDecimal {
+    type Adc = Adc;
+    type Sbc = Sbc;
+}
+
+// 65c02, N Z are valid after the decimal adjust
+pub struct CmosDecimal {}
+impl DecimalMode for CmosDecimal {
+    type Adc = AdcCmos;
+    type Sbc = SbcCmos;
+}
+
+// unstable stores, the value is anded with the base address high byte + 1
+// and replaces the high byte of the address when the index crosses a page
+#[inline]
+fn unstable_store(cpu: &mut Context, value: u8) {
+    cpu.ops.dl = value & cpu.ops.bah.wrapping_add(1);
+    if cpu.ops.adh != cpu.ops.bah {
+        cpu.ops.adh = cpu.ops.dl;
+    }
+}
+
 //=====================================================
 // official opcodes
 //====================================================== 
@@ -446,7 +483,8 @@ impl Instruction for Sbc {
     fn execute(cpu: &mut Context) {
         if cpu.p.contains(StatusRegister::DECIMAL) {
             // decimal mode (MAME implementation)
-            let c: u8 = cpu.p.contains(StatusRegister::CARRY) as u8;
+            // borrow is the inverted carry
+            let c: u8 = !cpu.p.contains(StatusRegister::CARRY) as u8;
             cpu.p.set(StatusRegister::CARRY, false);
             cpu.p.set(StatusRegister::OVERFLOW, false);
             cpu.p.set(StatusRegister::NEGATIVE, false);
@@ -472,7 +510,7 @@ impl Instruction for Sbc {
                 cpu.p.set(StatusRegister::OVERFLOW, true);
             }
 
-            if (!(diff & 0xFF00)) > 0 { cpu.p.set(StatusRegister::CARRY, true); }
+            if (diff & 0xFF00) == 0 { cpu.p.set(StatusRegister::CARRY, true); }
             if (ah & 0x80) > 0 { ah -= 6; }
 
             cpu.a = (ah << 4) | (al & 0x0F);
@@ -657,10 +695,7 @@ impl Instruction for Atx {
 pub struct Axa {}
 impl Instruction for Axa {
     fn execute(cpu: &mut Context) {
-        cpu.ops.dl = cpu.a & cpu.x & (cpu.ops.adh.wrapping_add(1));
-
-        cpu.p.set(StatusRegister::ZERO, set_zero(cpu.a));
-        cpu.p.set(StatusRegister::NEGATIVE, set_negative(cpu.a));
+        unstable_store(cpu, cpu.a & cpu.x);
     }
 }
 
@@ -697,16 +732,6 @@ impl Instruction for Isc {
     }
 }
 
-pub struct Kil {}
-impl Instruction for Kil {
-    fn execute(cpu: &mut Context) {
-        let mut addr = u16::from(cpu.pc);
-       // halt pc, lock up cpu
-        addr -= 1;
-        cpu.pc = ProgramCounter::from(addr);
-    }
-}
-
 pub struct Lar {}
 impl Instruction for Lar {
     fn execute(cpu: &mut Context) {
@@ -750,25 +775,8 @@ impl Instruction for Rla {
 pub struct Rra {}
 impl Instruction for Rra {
     fn execute(cpu: &mut Context) {
-        let new_carry = if (cpu.ops.dl & 0x01) > 0 { true } else { false };
-
-        cpu.ops.dl = cpu.ops.dl.wrapping_div(2);
-        cpu.ops.dl |= (cpu.p.contains(StatusRegister::CARRY) as u8) << 7;
-
-        cpu.p.set(StatusRegister::CARRY, new_carry);
-        cpu.p.set(StatusRegister::ZERO, set_zero(cpu.ops.dl));
-        cpu.p.set(StatusRegister::NEGATIVE, set_negative(cpu.ops.dl));
-
-        let sum = (cpu.a as u16) + (cpu.ops.dl as u16) + (cpu.p.contains(StatusRegister::CARRY) as u16); 
-        if sum > 255 { cpu.p.set(StatusRegister::CARRY, true) } else { cpu.p.set(StatusRegister::CARRY, false) };
-
-        let result = sum as u8;
-        cpu.a = result;
-       // cpu.p.overflow =  if (signed_sum < -128) || (signed_sum > 127) { true } else { false };
-        if ((cpu.ops.dl ^ result) & (cpu.a & result) & 0x80) == 0x80 { cpu.p.set(StatusRegister::OVERFLOW, true) } else { cpu.p.set(StatusRegister::OVERFLOW, false) };
-        cpu.p.set(StatusRegister::ZERO, set_zero(cpu.a));
-        cpu.p.set(StatusRegister::NEGATIVE, set_negative(cpu.a));
-
+        Ror::execute(cpu);
+        AdcNoDec::execute(cpu);
     }
 }
 
@@ -810,23 +818,25 @@ impl Instruction for Sre {
 pub struct Sxa {}
 impl Instruction for Sxa {
     fn execute(cpu: &mut Context) {
-        cpu.ops.dl = cpu.x & (cpu.ops.adh + 1);  
+        unstable_store(cpu, cpu.x);
     }
 }
 
 pub struct Sya {}
 impl Instruction for Sya {
     fn execute(cpu: &mut Context) {
-        cpu.ops.dl = cpu.y & (cpu.ops.adh + 1);  
+        unstable_store(cpu, cpu.y);
     }
 }
 
 pub struct Xaa {}
 impl Instruction for Xaa {
     fn execute(cpu: &mut Context) {
-        cpu.a = cpu.x;
-        // TODO: A is anded with some unkown immediate value
-        cpu.a &= cpu.ops.dl;
+        // the magic constant varies between chips, $EE is the common value
+        cpu.a = (cpu.a | 0xEE) & cpu.x & cpu.ops.dl;
+
+        cpu.p.set(StatusRegister::ZERO, set_zero(cpu.a));
+        cpu.p.set(StatusRegister::NEGATIVE, set_negative(cpu.a));
     }
 }
 
@@ -834,6 +844,145 @@ pub struct Xas {}
 impl Instruction for Xas {
     fn execute(cpu: &mut Context) {
         cpu.sp = cpu.x & cpu.a;
-        cpu.ops.dl = cpu.sp & (cpu.ops.adh + 1);
+        unstable_store(cpu, cpu.sp);
+    }
+}
+
+//=====================================================
+// 65c02 opcodes
+//======================================================
+
+pub struct AdcCmos {}
+impl Instruction for AdcCmos {
+    fn execute(cpu: &mut Context) {
+        Adc::execute(cpu);
+        cpu.p.set(StatusRegister::ZERO, set_zero(cpu.a));
+        cpu.p.set(StatusRegister::NEGATIVE, set_negative(cpu.a));
+    }
+}
+
+pub struct SbcCmos {}
+impl Instruction for SbcCmos {
+    fn execute(cpu: &mut Context) {
+        Sbc::execute(cpu);
+        cpu.p.set(StatusRegister::ZERO, set_zero(cpu.a));
+        cpu.p.set(StatusRegister::NEGATIVE, set_negative(cpu.a));
+    }
+}
+
+pub struct Bra {}
+impl Instruction for Bra {
+    fn execute(cpu: &mut Context) {
+        cpu.ops.branch_taken = true;
+    }
+}
+
+// bit immediate only affects the zero flag
+pub struct BitImm {}
+impl Instruction for BitImm {
+    fn execute(cpu: &mut Context) {
+        cpu.p.set(StatusRegister::ZERO, set_zero(cpu.a & cpu.ops.dl));
+    }
+}
+
+pub struct IncAccum {}
+impl Instruction for IncAccum {
+    fn execute(cpu: &mut Context) {
+        cpu.a = cpu.a.wrapping_add(1);
+        cpu.p.set(StatusRegister::ZERO, set_zero(cpu.a));
+        cpu.p.set(StatusRegister::NEGATIVE, set_negative(cpu.a));
+    }
+}
+
+pub struct DecAccum {}
+impl Instruction for DecAccum {
+    fn execute(cpu: &mut Context) {
+        cpu.a = cpu.a.wrapping_sub(1);
+        cpu.p.set(StatusRegister::ZERO, set_zero(cpu.a));
+        cpu.p.set(StatusRegister::NEGATIVE, set_negative(cpu.a));
+    }
+}
+
+pub struct Phx {}
+impl Instruction for Phx {
+    fn execute(cpu: &mut Context) {
+        cpu.ops.dl = cpu.x;
+    }
+}
+
+pub struct Phy {}
+impl Instruction for Phy {
+    fn execute(cpu: &mut Context) {
+        cpu.ops.dl = cpu.y;
+    }
+}
+
+pub struct Plx {}
+impl Instruction for Plx {
+    fn execute(cpu: &mut Context) {
+        cpu.x = cpu.ops.dl;
+        cpu.p.set(StatusRegister::ZERO, set_zero(cpu.x));
+        cpu.p.set(StatusRegister::NEGATIVE, set_negative(cpu.x));
+    }
+}
+
+pub struct Ply {}
+impl Instruction for Ply {
+    fn execute(cpu: &mut Context) {
+        cpu.y = cpu.ops.dl;
+        cpu.p.set(StatusRegister::ZERO, set_zero(cpu.y));
+        cpu.p.set(StatusRegister::NEGATIVE, set_negative(cpu.y));
+    }
+}
+
+pub struct Stz {}
+impl Instruction for Stz {
+    fn execute(cpu: &mut Context) {
+        cpu.ops.dl = 0;
+    }
+}
+
+pub struct Trb {}
+impl Instruction for Trb {
+    fn execute(cpu: &mut Context) {
+        cpu.p.set(StatusRegister::ZERO, set_zero(cpu.a & cpu.ops.dl));
+        cpu.ops.dl &= !cpu.a;
+    }
+}
+
+pub struct Tsb {}
+impl Instruction for Tsb {
+    fn execute(cpu: &mut Context) {
+        cpu.p.set(StatusRegister::ZERO, set_zero(cpu.a & cpu.ops.dl));
+        cpu.ops.dl |= cpu.a;
+    }
+}
+
+// rockwell/wdc bit instructions, the tested zero page byte is kept in bal
+pub struct Rmb<const BIT: u8> {}
+impl<const BIT: u8> Instruction for Rmb<BIT> {
+    fn execute(cpu: &mut Context) {
+        cpu.ops.dl &= !(1 << BIT);
+    }
+}
+
+pub struct Smb<const BIT: u8> {}
+impl<const BIT: u8> Instruction for Smb<BIT> {
+    fn execute(cpu: &mut Context) {
+        cpu.ops.dl |= 1 << BIT;
+    }
+}
+
+pub struct Bbr<const BIT: u8> {}
+impl<const BIT: u8> Instruction for Bbr<BIT> {
+    fn execute(cpu: &mut Context) {
+        cpu.ops.branch_taken = (cpu.ops.bal & (1 << BIT)) == 0;
+    }
+}
+
+pub struct Bbs<const BIT: u8> {}
+impl<const BIT: u8> Instruction for Bbs<BIT> {
+    fn execute(cpu: &mut Context) {
+        cpu.ops.branch_taken = (cpu.ops.bal & (1 << BIT)) != 0;
     }
 }
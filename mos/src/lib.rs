@@ -6,6 +6,12 @@ mod instructions;
 mod operations;
 pub mod bus;
 pub mod rp2a03;
+pub mod mos6502;
+pub mod wdc65c02;
+pub mod step;
+pub mod disassembler;
+#[cfg(test)]
+mod single_step_tests;
 
 /*
 Mos 6502
@@ -79,7 +85,23 @@ impl Pinout {
 
 #[cfg(test)]
 mod tests {
-    use super::{rp2a03, Ctrl};
+    use super::{rp2a03, Ctrl, Pinout};
+    use super::bus::Bus;
+    use super::core::{Context, CpuJam};
+
+    struct Ram(Vec<u8>);
+
+    impl Bus for Ram {
+        fn read(&mut self, mut pinout: Pinout) -> Pinout {
+            pinout.data = self.0[pinout.address as usize];
+            pinout
+        }
+
+        fn write(&mut self, pinout: Pinout) -> Pinout {
+            self.0[pinout.address as usize] = pinout.data;
+            pinout
+        }
+    }
     
     #[test]
     fn it_works() {
@@ -87,4 +109,91 @@ mod tests {
         
         assert_eq!(cpu_pinout.ctrl.contains(Ctrl::RDY), true);
     }
+
+    #[test]
+    fn test_kil_jams() {
+        // nop, kil
+        let mut ram = Ram(vec![0; 0x10000]);
+        ram.0[0x0200] = 0xEA;
+        ram.0[0x0201] = 0x02;
+
+        let (mut cpu, mut pinout) = rp2a03::Rp2a03::from_context_at(Context::new(), 0x0200);
+        for _ in 0..10 {
+            pinout = cpu.tick(&mut ram, pinout);
+        }
+        assert_eq!(cpu.jammed(), Some(CpuJam { pc: 0x0201, opcode: 0x02 }));
+
+        // interrupts are ignored
+        pinout.ctrl.set(Ctrl::NMI, false);
+        pinout = cpu.tick(&mut ram, pinout);
+        assert!(!pinout.ctrl.contains(Ctrl::SYNC));
+        assert!(cpu.jammed().is_some());
+
+        let (cpu, _) = cpu.from_reset();
+        assert_eq!(cpu.jammed(), None);
+    }
+
+    fn load_subroutines() -> Ram {
+        let mut ram = Ram(vec![0; 0x10000]);
+        let code: [(u16, &[u8]); 3] = [
+            // jsr $0300, lda #$01, nop
+            (0x0200, &[0x20, 0x00, 0x03, 0xA9, 0x01, 0xEA]),
+            // ldx #$05, jsr $0310, rts
+            (0x0300, &[0xA2, 0x05, 0x20, 0x10, 0x03, 0x60]),
+            // iny, rts
+            (0x0310, &[0xC8, 0x60]),
+        ];
+        for (address, bytes) in code.iter() {
+            let start = *address as usize;
+            ram.0[start..start + bytes.len()].copy_from_slice(bytes);
+        }
+        ram
+    }
+
+    fn start_at(ram: &mut Ram, pc: u16) -> (rp2a03::Rp2a03, Pinout) {
+        let mut context = Context::new();
+        context.sp = 0xFD;
+        let (mut cpu, mut pinout) = rp2a03::Rp2a03::from_context_at(context, pc);
+        pinout = cpu.tick(ram, pinout);
+        assert!(cpu.is_at_instruction_boundary());
+        (cpu, pinout)
+    }
+
+    #[test]
+    fn test_step_over_and_out() {
+        let mut ram = load_subroutines();
+        let (mut cpu, mut pinout) = start_at(&mut ram, 0x0200);
+        pinout = cpu.step_over(&mut ram, pinout);
+        assert_eq!((pinout.address, cpu.pc(), cpu.x(), cpu.y(), cpu.sp()), (0x0203, 0x0203, 0x05, 0x01, 0xFD));
+
+        let mut ram = load_subroutines();
+        let (mut cpu, mut pinout) = start_at(&mut ram, 0x0200);
+        pinout = cpu.step_instruction(&mut ram, pinout);
+        assert_eq!(cpu.pc(), 0x0300);
+        pinout = cpu.step_instruction(&mut ram, pinout);
+        pinout = cpu.step_instruction(&mut ram, pinout);
+        assert_eq!(cpu.pc(), 0x0310);
+        // out of the inner routine back into the outer one
+        pinout = cpu.step_out(&mut ram, pinout);
+        assert_eq!(cpu.pc(), 0x0305);
+        pinout = cpu.step_out(&mut ram, pinout);
+        assert_eq!((cpu.pc(), cpu.sp()), (0x0203, 0xFD));
+        cpu.step_instruction(&mut ram, pinout);
+        assert_eq!(cpu.a(), 0x01);
+    }
+
+    #[test]
+    fn test_register_setters() {
+        let mut ram = load_subroutines();
+        let (mut cpu, mut pinout) = start_at(&mut ram, 0x0300);
+        // skip the ldx, jsr straight to the inner routine with y preset
+        cpu.set_pc(0x0310);
+        cpu.set_y(0x41);
+        assert_eq!(cpu.pc(), 0x0310);
+        pinout = cpu.tick(&mut ram, pinout);
+        assert!(pinout.ctrl.contains(Ctrl::SYNC) && pinout.address == 0x0310);
+
+        cpu.step_instruction(&mut ram, pinout);
+        assert_eq!((cpu.x(), cpu.y()), (0x00, 0x42));
+    }
 }
@@ -0,0 +1,138 @@
+use super::core::*;
+use super::instructions::{NoDecimal, NmosDecimal};
+use super::rp2a03::{context_at, reset_context, tick_with, nmos_dispatch};
+use super::Pinout;
+use super::bus::Bus;
+
+/*
+    NMOS 6502 with the same opcode table and timing as the 2a03, adc/sbc honour the D flag.
+    Decimal mode can be turned off for hosts that use a 2a03 like part.
+    N V Z after a decimal adc/sbc are set from the binary result like the real chip.
+    The undocumented RRA and ISC ignore the D flag.
+*/
+
+pub struct Mos6502 {
+    cpu: Context,
+    decimal_enabled: bool,
+}
+
+impl Mos6502 {
+    pub fn from_power_on() -> (Mos6502, Pinout) {
+        let mut cpu_context = Context::new();
+        cpu_context.ir.reset_to_rst();
+
+        let cpu = Mos6502 { cpu: cpu_context, decimal_enabled: true };
+        let cpu_pinout = Pinout::new();
+
+        (cpu, cpu_pinout)
+    }
+
+    // registers are taken from the context, the next tick fetches the opcode at pc
+    pub fn from_context_at(cpu_context: Context, pc: u16) -> (Mos6502, Pinout) {
+        let cpu = Mos6502 { cpu: context_at(cpu_context, pc), decimal_enabled: true };
+        let cpu_pinout = Pinout::new();
+
+        (cpu, cpu_pinout)
+    }
+
+    pub fn from_reset(&self) -> (Mos6502, Pinout) {
+        let cpu = Mos6502 { cpu: reset_context(&self.cpu), decimal_enabled: self.decimal_enabled };
+        let cpu_pinout = Pinout::new();
+
+        (cpu, cpu_pinout)
+    }
+
+    pub fn set_decimal_enabled(&mut self, enabled: bool) {
+        self.decimal_enabled = enabled;
+    }
+
+    pub fn decimal_enabled(&self) -> bool {
+        self.decimal_enabled
+    }
+
+    pub fn tick<B: Bus>(&mut self, bus: &mut B, pinout: Pinout) -> Pinout {
+        if self.decimal_enabled {
+            tick_with(&mut self.cpu, bus, pinout, nmos_dispatch::<B, NmosDecimal>)
+        }
+        else {
+            tick_with(&mut self.cpu, bus, pinout, nmos_dispatch::<B, NoDecimal>)
+        }
+    }
+
+    pub fn cycle_count(&self) -> u64 {
+        self.cpu.cycle
+    }
+
+    pub fn get_context(&self) -> Context {
+        self.cpu
+    }
+
+    pub fn jammed(&self) -> Option<CpuJam> {
+        self.cpu.jam
+    }
+
+    pub fn reset(&mut self) {
+        self.cpu = Context::new();
+        self.cpu.ir.reset_to_rst();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::Ctrl;
+
+    struct Ram(Vec<u8>);
+
+    impl Bus for Ram {
+        fn read(&mut self, mut pinout: Pinout) -> Pinout {
+            pinout.data = self.0[pinout.address as usize];
+            pinout
+        }
+
+        fn write(&mut self, pinout: Pinout) -> Pinout {
+            self.0[pinout.address as usize] = pinout.data;
+            pinout
+        }
+    }
+
+    // runs the program at $0200 until the opcode fetch after the last instruction
+    fn run(program: &[u8], decimal_enabled: bool) -> Context {
+        let mut ram = Ram(vec![0; 0x10000]);
+        ram.0[0x200..0x200 + program.len()].copy_from_slice(program);
+
+        let (mut cpu, mut pinout) = Mos6502::from_context_at(Context::new(), 0x200);
+        cpu.set_decimal_enabled(decimal_enabled);
+        loop {
+            pinout = cpu.tick(&mut ram, pinout);
+            if pinout.ctrl.contains(Ctrl::SYNC) && pinout.address as usize >= 0x200 + program.len() {
+                return cpu.get_context();
+            }
+        }
+    }
+
+    #[test]
+    fn test_decimal_adc() {
+        // sed, clc, lda #$58, adc #$46
+        let cpu = run(&[0xF8, 0x18, 0xA9, 0x58, 0x69, 0x46], true);
+        assert_eq!(cpu.a, 0x04);
+        assert!(cpu.p.contains(StatusRegister::CARRY));
+
+        let cpu = run(&[0xF8, 0x18, 0xA9, 0x58, 0x69, 0x46], false);
+        assert_eq!(cpu.a, 0x9E);
+        assert!(!cpu.p.contains(StatusRegister::CARRY));
+    }
+
+    #[test]
+    fn test_decimal_sbc() {
+        // sed, sec, lda #$00, sbc #$01
+        let cpu = run(&[0xF8, 0x38, 0xA9, 0x00, 0xE9, 0x01], true);
+        assert_eq!(cpu.a, 0x99);
+        assert!(!cpu.p.contains(StatusRegister::CARRY));
+
+        // sed, clc, lda #$46, sbc #$12
+        let cpu = run(&[0xF8, 0x18, 0xA9, 0x46, 0xE9, 0x12], true);
+        assert_eq!(cpu.a, 0x33);
+        assert!(cpu.p.contains(StatusRegister::CARRY));
+    }
+}
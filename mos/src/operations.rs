@@ -1086,10 +1086,12 @@ pub fn indirect_y_store_c2<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: P
 
 pub fn indirect_y_store_c3<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
     if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
-    cpu.ops.adl = cpu.ops.bal.wrapping_add(cpu.y);
+    let bal = cpu.ops.bal.overflowing_add(cpu.y);
+    cpu.ops.adl = bal.0;
     cpu.ops.adh = cpu.ops.bah;
     // data discarded
     read_cycle!(cpu, bus, pinout, to_address(cpu.ops.adh, cpu.ops.adl));
+    cpu.ops.adh = cpu.ops.bah.wrapping_add(bal.1 as u8);
 
     pinout
 }
@@ -1749,14 +1751,14 @@ pub fn undoc_indirect_x_c1<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: P
 
 pub fn undoc_indirect_x_c2<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
     if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
-    read_cycle!(cpu, bus, pinout, to_address(0, cpu.ops.bal + cpu.x));
+    read_cycle!(cpu, bus, pinout, to_address(0, cpu.ops.bal.wrapping_add(cpu.x)));
     cpu.ops.adl = cpu.ops.dl;
     pinout
 }
 
 pub fn undoc_indirect_x_c3<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
     if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
-    read_cycle!(cpu, bus, pinout, to_address(0, cpu.ops.bal + cpu.x + 1));
+    read_cycle!(cpu, bus, pinout, to_address(0, cpu.ops.bal.wrapping_add(cpu.x).wrapping_add(1)));
     cpu.ops.adh = cpu.ops.dl;
     pinout
 }
@@ -1909,4 +1911,240 @@ pub fn undoc_absolute_y_c6<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: P
     if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
     first_cycle!(cpu, bus, pinout);
     pinout
-}
\ No newline at end of file
+}
+//=======================================================================
+// 65c02 push x/y
+//========================================================================
+pub fn push_c1<B: Bus, T: Instruction>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    T::execute(cpu);
+    write_cycle!(cpu, bus, pinout, to_address(0x1, cpu.sp), cpu.ops.dl);
+    // decrement stack pointer
+    cpu.sp = cpu.sp.wrapping_sub(1);
+    last_cycle!(cpu, pinout);
+    pinout
+}
+
+//=======================================================================
+// 65c02 pull x/y
+//========================================================================
+pub fn pull_c2<B: Bus, T: Instruction>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    cpu.sp = cpu.sp.wrapping_add(1);
+    read_cycle!(cpu, bus, pinout, to_address(0x1, cpu.sp));
+    T::execute(cpu);
+    last_cycle!(cpu, pinout);
+    pinout
+}
+
+//=======================================================================
+// 65c02 zero page indirect read
+//========================================================================
+pub fn indirect_zp_read_c0<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    second_cycle!(cpu, bus, pinout);
+    cpu.ops.ial = cpu.ops.dl;
+
+    cpu.pc.increment();
+    pinout
+}
+
+pub fn indirect_zp_read_c1<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    read_cycle!(cpu, bus, pinout, to_address(0, cpu.ops.ial));
+    cpu.ops.adl = cpu.ops.dl;
+
+    pinout
+}
+
+pub fn indirect_zp_read_c2<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    read_cycle!(cpu, bus, pinout, to_address(0, cpu.ops.ial.wrapping_add(1)));
+    cpu.ops.adh = cpu.ops.dl;
+
+    pinout
+}
+
+pub fn indirect_zp_read_c3<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    read_cycle!(cpu, bus, pinout, to_address(cpu.ops.adh, cpu.ops.adl));
+    pinout
+}
+
+pub fn indirect_zp_read_c4<B: Bus, T: Instruction>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    T::execute(cpu);
+    last_cycle!(cpu, pinout);
+    // if no interrupt do first cycle
+    first_cycle!(cpu, bus, pinout);
+    pinout
+}
+
+//=======================================================================
+// 65c02 zero page indirect store
+//========================================================================
+pub fn indirect_zp_store_c0<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    second_cycle!(cpu, bus, pinout);
+    cpu.ops.ial = cpu.ops.dl;
+
+    cpu.pc.increment();
+    pinout
+}
+
+pub fn indirect_zp_store_c1<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    read_cycle!(cpu, bus, pinout, to_address(0, cpu.ops.ial));
+    cpu.ops.adl = cpu.ops.dl;
+
+    pinout
+}
+
+pub fn indirect_zp_store_c2<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    read_cycle!(cpu, bus, pinout, to_address(0, cpu.ops.ial.wrapping_add(1)));
+    cpu.ops.adh = cpu.ops.dl;
+
+    pinout
+}
+
+pub fn indirect_zp_store_c3<B: Bus, T: Instruction>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    T::execute(cpu);
+    write_cycle!(cpu, bus, pinout, to_address(cpu.ops.adh, cpu.ops.adl), cpu.ops.dl);
+    last_cycle!(cpu, pinout);
+
+    pinout
+}
+
+pub fn indirect_zp_store_c4<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    first_cycle!(cpu, bus, pinout);
+    pinout
+}
+
+//=======================================================================
+// 65c02 jump indirect, the pointer high byte is read across the page
+// boundary and it takes an extra cycle. c0 and c1 are the nmos cycles
+//========================================================================
+pub fn jmp_indirect_fixed_c2<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    // data discarded
+    read_cycle!(cpu, bus, pinout, u16::from(cpu.pc));
+    pinout
+}
+
+// jmp (abs,x)
+pub fn jmp_indexed_indirect_c2<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    // data discarded
+    read_cycle!(cpu, bus, pinout, u16::from(cpu.pc));
+    let ial = cpu.ops.ial.overflowing_add(cpu.x);
+    cpu.ops.ial = ial.0;
+    cpu.ops.iah = cpu.ops.iah.wrapping_add(ial.1 as u8);
+    pinout
+}
+
+pub fn jmp_indirect_fixed_c3<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    read_cycle!(cpu, bus, pinout, to_address(cpu.ops.iah, cpu.ops.ial));
+    cpu.ops.adl = cpu.ops.dl;
+
+    pinout
+}
+
+pub fn jmp_indirect_fixed_c4<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    read_cycle!(cpu, bus, pinout, to_address(cpu.ops.iah, cpu.ops.ial).wrapping_add(1));
+    cpu.ops.adh = cpu.ops.dl;
+
+    cpu.pc.pcl = cpu.ops.adl;
+    cpu.pc.pch = cpu.ops.adh;
+
+    last_cycle!(cpu, pinout);
+    pinout
+}
+
+pub fn jmp_indirect_fixed_c5<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    first_cycle!(cpu, bus, pinout);
+    pinout
+}
+
+//=======================================================================
+// 65c02 branch on zero page bit (bbr/bbs)
+// c4.. are the regular branch cycles
+//========================================================================
+pub fn bit_branch_c0<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    second_cycle!(cpu, bus, pinout);
+    cpu.ops.adl = cpu.ops.dl;
+
+    cpu.pc.increment();
+    pinout
+}
+
+pub fn bit_branch_c1<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    read_cycle!(cpu, bus, pinout, to_address(0, cpu.ops.adl));
+    cpu.ops.bal = cpu.ops.dl;
+    pinout
+}
+
+pub fn bit_branch_c2<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    // data discarded
+    read_cycle!(cpu, bus, pinout, to_address(0, cpu.ops.adl));
+    pinout
+}
+
+pub fn bit_branch_c3<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    read_cycle!(cpu, bus, pinout, u16::from(cpu.pc));
+    cpu.ops.offset = cpu.ops.dl;
+    cpu.pc.increment();
+    last_cycle!(cpu, pinout);
+    pinout
+}
+
+//=======================================================================
+// 65c02 single cycle nop, the opcode fetch was the only cycle
+//========================================================================
+pub fn nop_single_cycle_c0<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    if pinout.ctrl.contains(Ctrl::RDY) == false { return pinout; }
+    // interrupts are not polled between single cycle nops
+    first_cycle!(cpu, bus, pinout);
+    pinout
+}
+
+//=======================================================================
+// 65c02 wai, sleeps until an interrupt line is asserted. with I set the
+// irq isn't taken and the next instruction executes
+//========================================================================
+pub fn wai_c1<B: Bus>(cpu: &mut Context, _bus: &mut B, pinout: Pinout) -> Pinout {
+    if is_nmi_asserted(cpu) || pinout.ctrl.contains(Ctrl::IRQ) == false {
+        cpu.ir.increment();
+    }
+    pinout
+}
+
+pub fn wai_c2<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    last_cycle!(cpu, pinout);
+    // if no interrupt do first cycle
+    first_cycle!(cpu, bus, pinout);
+    pinout
+}
+
+//=======================================================================
+// 65c02 stp, stops the clock until reset
+//========================================================================
+pub fn stp_c1<B: Bus>(_cpu: &mut Context, _bus: &mut B, pinout: Pinout) -> Pinout {
+    pinout
+}
+
+//=======================================================================
+// jam (kil), the cpu stops until reset
+//========================================================================
+pub fn jam_c1<B: Bus>(cpu: &mut Context, _bus: &mut B, pinout: Pinout) -> Pinout {
+    // pc wasn't incremented past the second byte
+    cpu.jam = Some(CpuJam { pc: u16::from(cpu.pc).wrapping_sub(1), opcode: cpu.ir.opcode });
+    pinout
+}
@@ -0,0 +1,174 @@
+use super::core::{Context, StatusRegister};
+use super::rp2a03::Rp2a03;
+use super::bus::Bus;
+use super::{Ctrl, Pinout};
+
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/*
+    Runs the SingleStepTests (github.com/SingleStepTests/65x02) nes6502 vectors, one json file per opcode
+    with the initial and final state of every test and the bus activity of every cycle
+
+    {"name": "a9 80", "initial": {"pc", "s", "a", "x", "y", "p", "ram": [[address, value], ..]},
+     "final": { same as initial }, "cycles": [[address, value, "read" | "write"], ..]}
+
+    The vectors are too large for the repo, test_vectors/fetch_nes6502.sh downloads them to
+    test_vectors/nes6502/v1, or point SINGLE_STEP_TESTS_DIR at a copy, then run with --ignored.
+    test_vectors/samples.json is always run, it covers the unstable and undocumented opcodes with
+    cases worked out from their documented behaviour.
+*/
+
+// jams the cpu, there is nothing to compare after the first cycles
+const JAM_OPCODES: [u8; 12] = [0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2];
+// longest instruction is 8 cycles (RMW indirect)
+const MAX_CYCLES: usize = 9;
+
+struct FlatBus {
+    ram: Vec<u8>,
+    cycles: Vec<(u16, u8, &'static str)>,
+}
+
+impl Bus for FlatBus {
+    fn read(&mut self, mut pinout: Pinout) -> Pinout {
+        pinout.data = self.ram[pinout.address as usize];
+        self.cycles.push((pinout.address, pinout.data, "read"));
+        pinout
+    }
+
+    fn write(&mut self, pinout: Pinout) -> Pinout {
+        self.ram[pinout.address as usize] = pinout.data;
+        self.cycles.push((pinout.address, pinout.data, "write"));
+        pinout
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct State {
+    pc: u16,
+    s: u8,
+    a: u8,
+    x: u8,
+    y: u8,
+    p: u8,
+}
+
+fn field(value: &Value, name: &str) -> u64 {
+    value[name].as_u64().unwrap_or_else(|| panic!("missing {}", name))
+}
+
+fn parse_state(value: &Value) -> State {
+    State {
+        pc: field(value, "pc") as u16,
+        s: field(value, "s") as u8,
+        a: field(value, "a") as u8,
+        x: field(value, "x") as u8,
+        y: field(value, "y") as u8,
+        // B and bit 5 don't exist in the status register
+        p: field(value, "p") as u8 & 0xCF,
+    }
+}
+
+fn parse_ram(value: &Value) -> Vec<(u16, u8)> {
+    value["ram"].as_array().unwrap().iter()
+        .map(|entry| (entry[0].as_u64().unwrap() as u16, entry[1].as_u64().unwrap() as u8))
+        .collect()
+}
+
+fn parse_cycles(test: &Value) -> Vec<(u16, u8, String)> {
+    test["cycles"].as_array().unwrap().iter()
+        .map(|cycle| (cycle[0].as_u64().unwrap() as u16, cycle[1].as_u64().unwrap() as u8, cycle[2].as_str().unwrap().to_string()))
+        .collect()
+}
+
+fn run_test(test: &Value) -> Result<(), String> {
+    let name = test["name"].as_str().unwrap_or("");
+    let initial = parse_state(&test["initial"]);
+    let expected = parse_state(&test["final"]);
+
+    let mut bus = FlatBus { ram: vec![0; 0x10000], cycles: Vec::new() };
+    for (address, data) in parse_ram(&test["initial"]) {
+        bus.ram[address as usize] = data;
+    }
+
+    let mut context = Context::new();
+    context.sp = initial.s;
+    context.a = initial.a;
+    context.x = initial.x;
+    context.y = initial.y;
+    context.p = StatusRegister::from_bits_truncate(initial.p);
+    let (mut cpu, mut pinout) = Rp2a03::from_context_at(context, initial.pc);
+
+    // the opcode fetch, then every cycle until the next opcode fetch which isn't part of the test
+    pinout = cpu.tick(&mut bus, pinout);
+    for _ in 0..MAX_CYCLES {
+        pinout = cpu.tick(&mut bus, pinout);
+        if pinout.ctrl.contains(Ctrl::SYNC) { break; }
+    }
+    if !pinout.ctrl.contains(Ctrl::SYNC) {
+        return Err(format!("{}: no opcode fetch after {} cycles", name, MAX_CYCLES));
+    }
+    bus.cycles.pop();
+
+    let ctx = cpu.get_context();
+    let actual = State { pc: pinout.address, s: ctx.sp, a: ctx.a, x: ctx.x, y: ctx.y, p: ctx.p.bits() & 0xCF };
+    if actual != expected {
+        return Err(format!("{}: registers\nexpected {:?}\nactual   {:?}", name, expected, actual));
+    }
+
+    let expected_cycles = parse_cycles(test);
+    let actual_cycles: Vec<(u16, u8, String)> = bus.cycles.iter().map(|(a, d, rw)| (*a, *d, rw.to_string())).collect();
+    if actual_cycles != expected_cycles {
+        return Err(format!("{}: cycles\nexpected {:?}\nactual   {:?}", name, expected_cycles, actual_cycles));
+    }
+
+    for (address, data) in parse_ram(&test["final"]) {
+        if bus.ram[address as usize] != data {
+            return Err(format!("{}: ram ${:04X} expected {:02X} actual {:02X}", name, address, data, bus.ram[address as usize]));
+        }
+    }
+
+    Ok(())
+}
+
+fn run_file(path: &Path) -> Vec<String> {
+    let json = std::fs::read_to_string(path).unwrap();
+    let tests: Value = serde_json::from_str(&json).unwrap();
+    tests.as_array().unwrap().iter().filter_map(|test| run_test(test).err()).collect()
+}
+
+fn vectors_dir() -> PathBuf {
+    match std::env::var("SINGLE_STEP_TESTS_DIR") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => Path::new(env!("CARGO_MANIFEST_DIR")).join("test_vectors/nes6502/v1"),
+    }
+}
+
+#[test]
+fn test_single_step_samples() {
+    let failures = run_file(&Path::new(env!("CARGO_MANIFEST_DIR")).join("test_vectors/samples.json"));
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+#[ignore = "needs the SingleStepTests vectors, see test_vectors/fetch_nes6502.sh"]
+fn test_single_step_vectors() {
+    let dir = vectors_dir();
+    assert!(dir.is_dir(), "{} not found, run test_vectors/fetch_nes6502.sh", dir.display());
+
+    let mut failures = Vec::new();
+    for opcode in (0..=0xFFu8).filter(|opcode| !JAM_OPCODES.contains(opcode)) {
+        let path = dir.join(format!("{:02x}.json", opcode));
+        if !path.is_file() {
+            failures.push(format!("{:02X}: {} missing", opcode, path.display()));
+            continue;
+        }
+
+        let errors = run_file(&path);
+        if let Some(first) = errors.first() {
+            failures.push(format!("{:02X}: {} failed, first {}", opcode, errors.len(), first));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
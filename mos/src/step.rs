@@ -0,0 +1,60 @@
+use super::core::Context;
+use super::{Ctrl, Pinout};
+
+const JSR: u8 = 0x20;
+const RTI: u8 = 0x40;
+const RTS: u8 = 0x60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepMode {
+    // to the next instruction boundary
+    Into,
+    // like Into, a JSR runs until it returns
+    Over,
+    // until the RTS/RTI that leaves the current routine
+    Out,
+}
+
+/*
+    Decides when a step is done, created at an instruction boundary (the opcode fetch, SYNC set)
+    and checked after every tick. Interrupts taken during the step are stepped over as well,
+    their RTI doesn't unwind the stack past the starting point.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stepper {
+    mode: StepMode,
+    return_pc: u16,
+    sp: u8,
+    opcode: Option<u8>,
+}
+
+impl Stepper {
+    pub fn new(mode: StepMode, cpu: &Context, pinout: Pinout) -> Stepper {
+        let at_boundary = pinout.ctrl.contains(Ctrl::SYNC);
+        let mode = match mode {
+            StepMode::Over if !at_boundary || cpu.ir.opcode != JSR => StepMode::Into,
+            mode => mode,
+        };
+
+        Stepper {
+            mode,
+            return_pc: pinout.address.wrapping_add(3),
+            sp: cpu.sp,
+            opcode: if at_boundary { Some(cpu.ir.opcode) } else { None },
+        }
+    }
+
+    // true once the cpu is at the opcode fetch the step was heading for
+    pub fn is_done(&mut self, cpu: &Context, pinout: Pinout) -> bool {
+        if !pinout.ctrl.contains(Ctrl::SYNC) {
+            return false;
+        }
+
+        let completed = self.opcode.replace(cpu.ir.opcode);
+        match self.mode {
+            StepMode::Into => true,
+            StepMode::Over => pinout.address == self.return_pc && cpu.sp >= self.sp,
+            StepMode::Out => (completed == Some(RTS) || completed == Some(RTI)) && cpu.sp > self.sp,
+        }
+    }
+}
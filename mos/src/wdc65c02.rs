@@ -0,0 +1,666 @@
+use super::core::*;
+use super::operations::*;
+use super::instructions::*;
+use super::rp2a03::{context_at, reset_context, tick_with, nmos_dispatch};
+use super::Pinout;
+use super::bus::Bus;
+
+/*
+    WDC 65C02, the CMOS opcodes are dispatched here and everything else falls through to the
+    nmos table with the cmos adc/sbc (N Z valid in decimal mode).
+
+    - BRA, PHX/PLX, PHY/PLY, STZ, TRB/TSB, BIT immediate and zp,x/abs,x, INC A/DEC A, (zp) addressing
+    - JMP ($xxFF) reads the high byte from the next page and takes 6 cycles, JMP (abs,x)
+    - RMB/SMB/BBR/BBS, WAI and STP
+    - BRK/IRQ/NMI clear the D flag
+    - the nmos undocumented opcodes are nops of the same length as the real chip
+
+    Differences in bus activity from the real chip: read-modify-write does a dummy write instead of a
+    dummy read, abs,x dummy reads use the nmos address, decimal adc/sbc don't take the extra cycle
+    and the $5C nop takes 4 cycles instead of 8.
+*/
+
+pub struct Wdc65C02 {
+    cpu: Context,
+}
+
+impl Wdc65C02 {
+    pub fn from_power_on() -> (Wdc65C02, Pinout) {
+        let mut cpu_context = Context::new();
+        cpu_context.ir.reset_to_rst();
+
+        let cpu = Wdc65C02 { cpu: cpu_context };
+        let cpu_pinout = Pinout::new();
+
+        (cpu, cpu_pinout)
+    }
+
+    // registers are taken from the context, the next tick fetches the opcode at pc
+    pub fn from_context_at(cpu_context: Context, pc: u16) -> (Wdc65C02, Pinout) {
+        let cpu = Wdc65C02 { cpu: context_at(cpu_context, pc) };
+        let cpu_pinout = Pinout::new();
+
+        (cpu, cpu_pinout)
+    }
+
+    // also the only way out of STP
+    pub fn from_reset(&self) -> (Wdc65C02, Pinout) {
+        let cpu = Wdc65C02 { cpu: reset_context(&self.cpu) };
+        let cpu_pinout = Pinout::new();
+
+        (cpu, cpu_pinout)
+    }
+
+    pub fn tick<B: Bus>(&mut self, bus: &mut B, pinout: Pinout) -> Pinout {
+        tick_with(&mut self.cpu, bus, pinout, cmos_dispatch::<B>)
+    }
+
+    pub fn cycle_count(&self) -> u64 {
+        self.cpu.cycle
+    }
+
+    pub fn get_context(&self) -> Context {
+        self.cpu
+    }
+
+    pub fn jammed(&self) -> Option<CpuJam> {
+        self.cpu.jam
+    }
+
+    pub fn reset(&mut self) {
+        self.cpu = Context::new();
+        self.cpu.ir.reset_to_rst();
+    }
+}
+
+fn cmos_dispatch<B: Bus>(cpu: &mut Context, bus: &mut B, mut pinout: Pinout) -> Pinout {
+    match u16::from(cpu.ir) {
+        // brk, irq and nmi clear the decimal flag
+        0x0004 => { pinout = brk_c4(cpu, bus, pinout); cpu.p.set(StatusRegister::DECIMAL, false); }
+        0x0024 => { pinout = nmi_c4(cpu, bus, pinout); cpu.p.set(StatusRegister::DECIMAL, false); }
+        0x0034 => { pinout = irq_c4(cpu, bus, pinout); cpu.p.set(StatusRegister::DECIMAL, false); }
+        // jmp indirect
+        0x6C00 =>  pinout = jmp_indirect_c0(cpu, bus, pinout),
+        0x6C01 =>  pinout = jmp_indirect_c1(cpu, bus, pinout),
+        0x6C02 =>  pinout = jmp_indirect_fixed_c2(cpu, bus, pinout),
+        0x6C03 =>  pinout = jmp_indirect_fixed_c3(cpu, bus, pinout),
+        0x6C04 =>  pinout = jmp_indirect_fixed_c4(cpu, bus, pinout),
+        0x6C05 =>  pinout = jmp_indirect_fixed_c5(cpu, bus, pinout),
+        // jmp absolute x indirect
+        0x7C00 =>  pinout = jmp_indirect_c0(cpu, bus, pinout),
+        0x7C01 =>  pinout = jmp_indirect_c1(cpu, bus, pinout),
+        0x7C02 =>  pinout = jmp_indexed_indirect_c2(cpu, bus, pinout),
+        0x7C03 =>  pinout = jmp_indirect_fixed_c3(cpu, bus, pinout),
+        0x7C04 =>  pinout = jmp_indirect_fixed_c4(cpu, bus, pinout),
+        0x7C05 =>  pinout = jmp_indirect_fixed_c5(cpu, bus, pinout),
+        // Bra
+        0x8000 =>  pinout = branch_c0(cpu, bus, pinout),
+        0x8001 =>  pinout = branch_c1::<B, Bra>(cpu, bus, pinout),
+        0x8002 =>  pinout = branch_c2(cpu, bus, pinout),
+        0x8003 =>  pinout = branch_c3(cpu, bus, pinout),
+        // Bit immediate
+        0x8900 =>  pinout = immediate_read_c0(cpu, bus, pinout),
+        0x8901 =>  pinout = immediate_read_c1::<B, BitImm>(cpu, bus, pinout),
+        // Bit zero page x read
+        0x3400 =>  pinout = zeropage_x_read_c0(cpu, bus, pinout),
+        0x3401 =>  pinout = zeropage_x_read_c1(cpu, bus, pinout),
+        0x3402 =>  pinout = zeropage_x_read_c2(cpu, bus, pinout),
+        0x3403 =>  pinout = zeropage_x_read_c3::<B, Bit>(cpu, bus, pinout),
+        // Bit absolute x read
+        0x3C00 =>  pinout = absolute_x_read_c0(cpu, bus, pinout),
+        0x3C01 =>  pinout = absolute_x_read_c1(cpu, bus, pinout),
+        0x3C02 =>  pinout = absolute_x_read_c2(cpu, bus, pinout),
+        0x3C03 =>  pinout = absolute_x_read_c3(cpu, bus, pinout),
+        0x3C04 =>  pinout = absolute_x_read_c4::<B, Bit>(cpu, bus, pinout),
+        // Inc accumulator
+        0x1A00 =>  pinout = single_byte_c0(cpu, bus, pinout),
+        0x1A01 =>  pinout = single_byte_c1::<B, IncAccum>(cpu, bus, pinout),
+        // Dec accumulator
+        0x3A00 =>  pinout = single_byte_c0(cpu, bus, pinout),
+        0x3A01 =>  pinout = single_byte_c1::<B, DecAccum>(cpu, bus, pinout),
+        // Phx
+        0xDA00 =>  pinout = pha_c0(cpu, bus, pinout),
+        0xDA01 =>  pinout = push_c1::<B, Phx>(cpu, bus, pinout),
+        0xDA02 =>  pinout = pha_c2(cpu, bus, pinout),
+        // Phy
+        0x5A00 =>  pinout = pha_c0(cpu, bus, pinout),
+        0x5A01 =>  pinout = push_c1::<B, Phy>(cpu, bus, pinout),
+        0x5A02 =>  pinout = pha_c2(cpu, bus, pinout),
+        // Plx
+        0xFA00 =>  pinout = pla_c0(cpu, bus, pinout),
+        0xFA01 =>  pinout = pla_c1(cpu, bus, pinout),
+        0xFA02 =>  pinout = pull_c2::<B, Plx>(cpu, bus, pinout),
+        0xFA03 =>  pinout = pla_c3(cpu, bus, pinout),
+        // Ply
+        0x7A00 =>  pinout = pla_c0(cpu, bus, pinout),
+        0x7A01 =>  pinout = pla_c1(cpu, bus, pinout),
+        0x7A02 =>  pinout = pull_c2::<B, Ply>(cpu, bus, pinout),
+        0x7A03 =>  pinout = pla_c3(cpu, bus, pinout),
+        // Stz zero page store
+        0x6400 =>  pinout = zeropage_store_c0(cpu, bus, pinout),
+        0x6401 =>  pinout = zeropage_store_c1::<B, Stz>(cpu, bus, pinout),
+        0x6402 =>  pinout = zeropage_store_c2(cpu, bus, pinout),
+        // Stz zero page x store
+        0x7400 =>  pinout = zeropage_x_store_c0(cpu, bus, pinout),
+        0x7401 =>  pinout = zeropage_x_store_c1(cpu, bus, pinout),
+        0x7402 =>  pinout = zeropage_x_store_c2::<B, Stz>(cpu, bus, pinout),
+        0x7403 =>  pinout = zeropage_x_store_c3(cpu, bus, pinout),
+        // Stz absolute store
+        0x9C00 =>  pinout = absolute_store_c0(cpu, bus, pinout),
+        0x9C01 =>  pinout = absolute_store_c1(cpu, bus, pinout),
+        0x9C02 =>  pinout = absolute_store_c2::<B, Stz>(cpu, bus, pinout),
+        0x9C03 =>  pinout = absolute_store_c3(cpu, bus, pinout),
+        // Stz absolute x store
+        0x9E00 =>  pinout = absolute_x_store_c0(cpu, bus, pinout),
+        0x9E01 =>  pinout = absolute_x_store_c1(cpu, bus, pinout),
+        0x9E02 =>  pinout = absolute_x_store_c2(cpu, bus, pinout),
+        0x9E03 =>  pinout = absolute_x_store_c3::<B, Stz>(cpu, bus, pinout),
+        0x9E04 =>  pinout = absolute_x_store_c4(cpu, bus, pinout),
+        // Tsb zero page modify
+        0x0400 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0x0401 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0x0402 =>  pinout = zeropage_modify_c2::<B, Tsb>(cpu, bus, pinout),
+        0x0403 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0x0404 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Tsb absolute modify
+        0x0C00 =>  pinout = absolute_modify_c0(cpu, bus, pinout),
+        0x0C01 =>  pinout = absolute_modify_c1(cpu, bus, pinout),
+        0x0C02 =>  pinout = absolute_modify_c2(cpu, bus, pinout),
+        0x0C03 =>  pinout = absolute_modify_c3::<B, Tsb>(cpu, bus, pinout),
+        0x0C04 =>  pinout = absolute_modify_c4(cpu, bus, pinout),
+        0x0C05 =>  pinout = absolute_modify_c5(cpu, bus, pinout),
+        // Trb zero page modify
+        0x1400 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0x1401 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0x1402 =>  pinout = zeropage_modify_c2::<B, Trb>(cpu, bus, pinout),
+        0x1403 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0x1404 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Trb absolute modify
+        0x1C00 =>  pinout = absolute_modify_c0(cpu, bus, pinout),
+        0x1C01 =>  pinout = absolute_modify_c1(cpu, bus, pinout),
+        0x1C02 =>  pinout = absolute_modify_c2(cpu, bus, pinout),
+        0x1C03 =>  pinout = absolute_modify_c3::<B, Trb>(cpu, bus, pinout),
+        0x1C04 =>  pinout = absolute_modify_c4(cpu, bus, pinout),
+        0x1C05 =>  pinout = absolute_modify_c5(cpu, bus, pinout),
+        // Ora zero page indirect read
+        0x1200 =>  pinout = indirect_zp_read_c0(cpu, bus, pinout),
+        0x1201 =>  pinout = indirect_zp_read_c1(cpu, bus, pinout),
+        0x1202 =>  pinout = indirect_zp_read_c2(cpu, bus, pinout),
+        0x1203 =>  pinout = indirect_zp_read_c3(cpu, bus, pinout),
+        0x1204 =>  pinout = indirect_zp_read_c4::<B, Ora>(cpu, bus, pinout),
+        // And zero page indirect read
+        0x3200 =>  pinout = indirect_zp_read_c0(cpu, bus, pinout),
+        0x3201 =>  pinout = indirect_zp_read_c1(cpu, bus, pinout),
+        0x3202 =>  pinout = indirect_zp_read_c2(cpu, bus, pinout),
+        0x3203 =>  pinout = indirect_zp_read_c3(cpu, bus, pinout),
+        0x3204 =>  pinout = indirect_zp_read_c4::<B, And>(cpu, bus, pinout),
+        // Eor zero page indirect read
+        0x5200 =>  pinout = indirect_zp_read_c0(cpu, bus, pinout),
+        0x5201 =>  pinout = indirect_zp_read_c1(cpu, bus, pinout),
+        0x5202 =>  pinout = indirect_zp_read_c2(cpu, bus, pinout),
+        0x5203 =>  pinout = indirect_zp_read_c3(cpu, bus, pinout),
+        0x5204 =>  pinout = indirect_zp_read_c4::<B, Eor>(cpu, bus, pinout),
+        // AdcCmos zero page indirect read
+        0x7200 =>  pinout = indirect_zp_read_c0(cpu, bus, pinout),
+        0x7201 =>  pinout = indirect_zp_read_c1(cpu, bus, pinout),
+        0x7202 =>  pinout = indirect_zp_read_c2(cpu, bus, pinout),
+        0x7203 =>  pinout = indirect_zp_read_c3(cpu, bus, pinout),
+        0x7204 =>  pinout = indirect_zp_read_c4::<B, AdcCmos>(cpu, bus, pinout),
+        // Lda zero page indirect read
+        0xB200 =>  pinout = indirect_zp_read_c0(cpu, bus, pinout),
+        0xB201 =>  pinout = indirect_zp_read_c1(cpu, bus, pinout),
+        0xB202 =>  pinout = indirect_zp_read_c2(cpu, bus, pinout),
+        0xB203 =>  pinout = indirect_zp_read_c3(cpu, bus, pinout),
+        0xB204 =>  pinout = indirect_zp_read_c4::<B, Lda>(cpu, bus, pinout),
+        // Cmp zero page indirect read
+        0xD200 =>  pinout = indirect_zp_read_c0(cpu, bus, pinout),
+        0xD201 =>  pinout = indirect_zp_read_c1(cpu, bus, pinout),
+        0xD202 =>  pinout = indirect_zp_read_c2(cpu, bus, pinout),
+        0xD203 =>  pinout = indirect_zp_read_c3(cpu, bus, pinout),
+        0xD204 =>  pinout = indirect_zp_read_c4::<B, Cmp>(cpu, bus, pinout),
+        // SbcCmos zero page indirect read
+        0xF200 =>  pinout = indirect_zp_read_c0(cpu, bus, pinout),
+        0xF201 =>  pinout = indirect_zp_read_c1(cpu, bus, pinout),
+        0xF202 =>  pinout = indirect_zp_read_c2(cpu, bus, pinout),
+        0xF203 =>  pinout = indirect_zp_read_c3(cpu, bus, pinout),
+        0xF204 =>  pinout = indirect_zp_read_c4::<B, SbcCmos>(cpu, bus, pinout),
+        // Sta zero page indirect store
+        0x9200 =>  pinout = indirect_zp_store_c0(cpu, bus, pinout),
+        0x9201 =>  pinout = indirect_zp_store_c1(cpu, bus, pinout),
+        0x9202 =>  pinout = indirect_zp_store_c2(cpu, bus, pinout),
+        0x9203 =>  pinout = indirect_zp_store_c3::<B, Sta>(cpu, bus, pinout),
+        0x9204 =>  pinout = indirect_zp_store_c4(cpu, bus, pinout),
+        // Rmb0 zero page modify
+        0x0700 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0x0701 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0x0702 =>  pinout = zeropage_modify_c2::<B, Rmb<0>>(cpu, bus, pinout),
+        0x0703 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0x0704 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Rmb1 zero page modify
+        0x1700 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0x1701 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0x1702 =>  pinout = zeropage_modify_c2::<B, Rmb<1>>(cpu, bus, pinout),
+        0x1703 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0x1704 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Rmb2 zero page modify
+        0x2700 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0x2701 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0x2702 =>  pinout = zeropage_modify_c2::<B, Rmb<2>>(cpu, bus, pinout),
+        0x2703 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0x2704 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Rmb3 zero page modify
+        0x3700 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0x3701 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0x3702 =>  pinout = zeropage_modify_c2::<B, Rmb<3>>(cpu, bus, pinout),
+        0x3703 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0x3704 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Rmb4 zero page modify
+        0x4700 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0x4701 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0x4702 =>  pinout = zeropage_modify_c2::<B, Rmb<4>>(cpu, bus, pinout),
+        0x4703 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0x4704 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Rmb5 zero page modify
+        0x5700 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0x5701 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0x5702 =>  pinout = zeropage_modify_c2::<B, Rmb<5>>(cpu, bus, pinout),
+        0x5703 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0x5704 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Rmb6 zero page modify
+        0x6700 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0x6701 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0x6702 =>  pinout = zeropage_modify_c2::<B, Rmb<6>>(cpu, bus, pinout),
+        0x6703 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0x6704 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Rmb7 zero page modify
+        0x7700 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0x7701 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0x7702 =>  pinout = zeropage_modify_c2::<B, Rmb<7>>(cpu, bus, pinout),
+        0x7703 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0x7704 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Smb0 zero page modify
+        0x8700 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0x8701 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0x8702 =>  pinout = zeropage_modify_c2::<B, Smb<0>>(cpu, bus, pinout),
+        0x8703 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0x8704 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Smb1 zero page modify
+        0x9700 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0x9701 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0x9702 =>  pinout = zeropage_modify_c2::<B, Smb<1>>(cpu, bus, pinout),
+        0x9703 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0x9704 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Smb2 zero page modify
+        0xA700 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0xA701 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0xA702 =>  pinout = zeropage_modify_c2::<B, Smb<2>>(cpu, bus, pinout),
+        0xA703 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0xA704 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Smb3 zero page modify
+        0xB700 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0xB701 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0xB702 =>  pinout = zeropage_modify_c2::<B, Smb<3>>(cpu, bus, pinout),
+        0xB703 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0xB704 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Smb4 zero page modify
+        0xC700 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0xC701 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0xC702 =>  pinout = zeropage_modify_c2::<B, Smb<4>>(cpu, bus, pinout),
+        0xC703 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0xC704 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Smb5 zero page modify
+        0xD700 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0xD701 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0xD702 =>  pinout = zeropage_modify_c2::<B, Smb<5>>(cpu, bus, pinout),
+        0xD703 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0xD704 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Smb6 zero page modify
+        0xE700 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0xE701 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0xE702 =>  pinout = zeropage_modify_c2::<B, Smb<6>>(cpu, bus, pinout),
+        0xE703 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0xE704 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Smb7 zero page modify
+        0xF700 =>  pinout = zeropage_modify_c0(cpu, bus, pinout),
+        0xF701 =>  pinout = zeropage_modify_c1(cpu, bus, pinout),
+        0xF702 =>  pinout = zeropage_modify_c2::<B, Smb<7>>(cpu, bus, pinout),
+        0xF703 =>  pinout = zeropage_modify_c3(cpu, bus, pinout),
+        0xF704 =>  pinout = zeropage_modify_c4(cpu, bus, pinout),
+        // Bbr0
+        0x0F00 =>  pinout = bit_branch_c0(cpu, bus, pinout),
+        0x0F01 =>  pinout = bit_branch_c1(cpu, bus, pinout),
+        0x0F02 =>  pinout = bit_branch_c2(cpu, bus, pinout),
+        0x0F03 =>  pinout = bit_branch_c3(cpu, bus, pinout),
+        0x0F04 =>  pinout = branch_c1::<B, Bbr<0>>(cpu, bus, pinout),
+        0x0F05 =>  pinout = branch_c2(cpu, bus, pinout),
+        0x0F06 =>  pinout = branch_c3(cpu, bus, pinout),
+        // Bbr1
+        0x1F00 =>  pinout = bit_branch_c0(cpu, bus, pinout),
+        0x1F01 =>  pinout = bit_branch_c1(cpu, bus, pinout),
+        0x1F02 =>  pinout = bit_branch_c2(cpu, bus, pinout),
+        0x1F03 =>  pinout = bit_branch_c3(cpu, bus, pinout),
+        0x1F04 =>  pinout = branch_c1::<B, Bbr<1>>(cpu, bus, pinout),
+        0x1F05 =>  pinout = branch_c2(cpu, bus, pinout),
+        0x1F06 =>  pinout = branch_c3(cpu, bus, pinout),
+        // Bbr2
+        0x2F00 =>  pinout = bit_branch_c0(cpu, bus, pinout),
+        0x2F01 =>  pinout = bit_branch_c1(cpu, bus, pinout),
+        0x2F02 =>  pinout = bit_branch_c2(cpu, bus, pinout),
+        0x2F03 =>  pinout = bit_branch_c3(cpu, bus, pinout),
+        0x2F04 =>  pinout = branch_c1::<B, Bbr<2>>(cpu, bus, pinout),
+        0x2F05 =>  pinout = branch_c2(cpu, bus, pinout),
+        0x2F06 =>  pinout = branch_c3(cpu, bus, pinout),
+        // Bbr3
+        0x3F00 =>  pinout = bit_branch_c0(cpu, bus, pinout),
+        0x3F01 =>  pinout = bit_branch_c1(cpu, bus, pinout),
+        0x3F02 =>  pinout = bit_branch_c2(cpu, bus, pinout),
+        0x3F03 =>  pinout = bit_branch_c3(cpu, bus, pinout),
+        0x3F04 =>  pinout = branch_c1::<B, Bbr<3>>(cpu, bus, pinout),
+        0x3F05 =>  pinout = branch_c2(cpu, bus, pinout),
+        0x3F06 =>  pinout = branch_c3(cpu, bus, pinout),
+        // Bbr4
+        0x4F00 =>  pinout = bit_branch_c0(cpu, bus, pinout),
+        0x4F01 =>  pinout = bit_branch_c1(cpu, bus, pinout),
+        0x4F02 =>  pinout = bit_branch_c2(cpu, bus, pinout),
+        0x4F03 =>  pinout = bit_branch_c3(cpu, bus, pinout),
+        0x4F04 =>  pinout = branch_c1::<B, Bbr<4>>(cpu, bus, pinout),
+        0x4F05 =>  pinout = branch_c2(cpu, bus, pinout),
+        0x4F06 =>  pinout = branch_c3(cpu, bus, pinout),
+        // Bbr5
+        0x5F00 =>  pinout = bit_branch_c0(cpu, bus, pinout),
+        0x5F01 =>  pinout = bit_branch_c1(cpu, bus, pinout),
+        0x5F02 =>  pinout = bit_branch_c2(cpu, bus, pinout),
+        0x5F03 =>  pinout = bit_branch_c3(cpu, bus, pinout),
+        0x5F04 =>  pinout = branch_c1::<B, Bbr<5>>(cpu, bus, pinout),
+        0x5F05 =>  pinout = branch_c2(cpu, bus, pinout),
+        0x5F06 =>  pinout = branch_c3(cpu, bus, pinout),
+        // Bbr6
+        0x6F00 =>  pinout = bit_branch_c0(cpu, bus, pinout),
+        0x6F01 =>  pinout = bit_branch_c1(cpu, bus, pinout),
+        0x6F02 =>  pinout = bit_branch_c2(cpu, bus, pinout),
+        0x6F03 =>  pinout = bit_branch_c3(cpu, bus, pinout),
+        0x6F04 =>  pinout = branch_c1::<B, Bbr<6>>(cpu, bus, pinout),
+        0x6F05 =>  pinout = branch_c2(cpu, bus, pinout),
+        0x6F06 =>  pinout = branch_c3(cpu, bus, pinout),
+        // Bbr7
+        0x7F00 =>  pinout = bit_branch_c0(cpu, bus, pinout),
+        0x7F01 =>  pinout = bit_branch_c1(cpu, bus, pinout),
+        0x7F02 =>  pinout = bit_branch_c2(cpu, bus, pinout),
+        0x7F03 =>  pinout = bit_branch_c3(cpu, bus, pinout),
+        0x7F04 =>  pinout = branch_c1::<B, Bbr<7>>(cpu, bus, pinout),
+        0x7F05 =>  pinout = branch_c2(cpu, bus, pinout),
+        0x7F06 =>  pinout = branch_c3(cpu, bus, pinout),
+        // Bbs0
+        0x8F00 =>  pinout = bit_branch_c0(cpu, bus, pinout),
+        0x8F01 =>  pinout = bit_branch_c1(cpu, bus, pinout),
+        0x8F02 =>  pinout = bit_branch_c2(cpu, bus, pinout),
+        0x8F03 =>  pinout = bit_branch_c3(cpu, bus, pinout),
+        0x8F04 =>  pinout = branch_c1::<B, Bbs<0>>(cpu, bus, pinout),
+        0x8F05 =>  pinout = branch_c2(cpu, bus, pinout),
+        0x8F06 =>  pinout = branch_c3(cpu, bus, pinout),
+        // Bbs1
+        0x9F00 =>  pinout = bit_branch_c0(cpu, bus, pinout),
+        0x9F01 =>  pinout = bit_branch_c1(cpu, bus, pinout),
+        0x9F02 =>  pinout = bit_branch_c2(cpu, bus, pinout),
+        0x9F03 =>  pinout = bit_branch_c3(cpu, bus, pinout),
+        0x9F04 =>  pinout = branch_c1::<B, Bbs<1>>(cpu, bus, pinout),
+        0x9F05 =>  pinout = branch_c2(cpu, bus, pinout),
+        0x9F06 =>  pinout = branch_c3(cpu, bus, pinout),
+        // Bbs2
+        0xAF00 =>  pinout = bit_branch_c0(cpu, bus, pinout),
+        0xAF01 =>  pinout = bit_branch_c1(cpu, bus, pinout),
+        0xAF02 =>  pinout = bit_branch_c2(cpu, bus, pinout),
+        0xAF03 =>  pinout = bit_branch_c3(cpu, bus, pinout),
+        0xAF04 =>  pinout = branch_c1::<B, Bbs<2>>(cpu, bus, pinout),
+        0xAF05 =>  pinout = branch_c2(cpu, bus, pinout),
+        0xAF06 =>  pinout = branch_c3(cpu, bus, pinout),
+        // Bbs3
+        0xBF00 =>  pinout = bit_branch_c0(cpu, bus, pinout),
+        0xBF01 =>  pinout = bit_branch_c1(cpu, bus, pinout),
+        0xBF02 =>  pinout = bit_branch_c2(cpu, bus, pinout),
+        0xBF03 =>  pinout = bit_branch_c3(cpu, bus, pinout),
+        0xBF04 =>  pinout = branch_c1::<B, Bbs<3>>(cpu, bus, pinout),
+        0xBF05 =>  pinout = branch_c2(cpu, bus, pinout),
+        0xBF06 =>  pinout = branch_c3(cpu, bus, pinout),
+        // Bbs4
+        0xCF00 =>  pinout = bit_branch_c0(cpu, bus, pinout),
+        0xCF01 =>  pinout = bit_branch_c1(cpu, bus, pinout),
+        0xCF02 =>  pinout = bit_branch_c2(cpu, bus, pinout),
+        0xCF03 =>  pinout = bit_branch_c3(cpu, bus, pinout),
+        0xCF04 =>  pinout = branch_c1::<B, Bbs<4>>(cpu, bus, pinout),
+        0xCF05 =>  pinout = branch_c2(cpu, bus, pinout),
+        0xCF06 =>  pinout = branch_c3(cpu, bus, pinout),
+        // Bbs5
+        0xDF00 =>  pinout = bit_branch_c0(cpu, bus, pinout),
+        0xDF01 =>  pinout = bit_branch_c1(cpu, bus, pinout),
+        0xDF02 =>  pinout = bit_branch_c2(cpu, bus, pinout),
+        0xDF03 =>  pinout = bit_branch_c3(cpu, bus, pinout),
+        0xDF04 =>  pinout = branch_c1::<B, Bbs<5>>(cpu, bus, pinout),
+        0xDF05 =>  pinout = branch_c2(cpu, bus, pinout),
+        0xDF06 =>  pinout = branch_c3(cpu, bus, pinout),
+        // Bbs6
+        0xEF00 =>  pinout = bit_branch_c0(cpu, bus, pinout),
+        0xEF01 =>  pinout = bit_branch_c1(cpu, bus, pinout),
+        0xEF02 =>  pinout = bit_branch_c2(cpu, bus, pinout),
+        0xEF03 =>  pinout = bit_branch_c3(cpu, bus, pinout),
+        0xEF04 =>  pinout = branch_c1::<B, Bbs<6>>(cpu, bus, pinout),
+        0xEF05 =>  pinout = branch_c2(cpu, bus, pinout),
+        0xEF06 =>  pinout = branch_c3(cpu, bus, pinout),
+        // Bbs7
+        0xFF00 =>  pinout = bit_branch_c0(cpu, bus, pinout),
+        0xFF01 =>  pinout = bit_branch_c1(cpu, bus, pinout),
+        0xFF02 =>  pinout = bit_branch_c2(cpu, bus, pinout),
+        0xFF03 =>  pinout = bit_branch_c3(cpu, bus, pinout),
+        0xFF04 =>  pinout = branch_c1::<B, Bbs<7>>(cpu, bus, pinout),
+        0xFF05 =>  pinout = branch_c2(cpu, bus, pinout),
+        0xFF06 =>  pinout = branch_c3(cpu, bus, pinout),
+        // Wai
+        0xCB00 =>  pinout = single_byte_c0(cpu, bus, pinout),
+        0xCB01 =>  pinout = wai_c1(cpu, bus, pinout),
+        0xCB02 =>  pinout = wai_c2(cpu, bus, pinout),
+        // Stp
+        0xDB00 =>  pinout = single_byte_c0(cpu, bus, pinout),
+        0xDB01 =>  pinout = stp_c1(cpu, bus, pinout),
+        // the remaining nmos undocumented opcodes are nops
+        // Nop immediate
+        0x0200 =>  pinout = immediate_read_c0(cpu, bus, pinout),
+        0x0201 =>  pinout = immediate_read_c1::<B, Nop>(cpu, bus, pinout),
+        0x2200 =>  pinout = immediate_read_c0(cpu, bus, pinout),
+        0x2201 =>  pinout = immediate_read_c1::<B, Nop>(cpu, bus, pinout),
+        0x4200 =>  pinout = immediate_read_c0(cpu, bus, pinout),
+        0x4201 =>  pinout = immediate_read_c1::<B, Nop>(cpu, bus, pinout),
+        0x6200 =>  pinout = immediate_read_c0(cpu, bus, pinout),
+        0x6201 =>  pinout = immediate_read_c1::<B, Nop>(cpu, bus, pinout),
+        0x8200 =>  pinout = immediate_read_c0(cpu, bus, pinout),
+        0x8201 =>  pinout = immediate_read_c1::<B, Nop>(cpu, bus, pinout),
+        0xC200 =>  pinout = immediate_read_c0(cpu, bus, pinout),
+        0xC201 =>  pinout = immediate_read_c1::<B, Nop>(cpu, bus, pinout),
+        0xE200 =>  pinout = immediate_read_c0(cpu, bus, pinout),
+        0xE201 =>  pinout = immediate_read_c1::<B, Nop>(cpu, bus, pinout),
+        // Nop zero page read
+        0x4400 =>  pinout = zeropage_read_c0(cpu, bus, pinout),
+        0x4401 =>  pinout = zeropage_read_c1(cpu, bus, pinout),
+        0x4402 =>  pinout = zeropage_read_c2::<B, Nop>(cpu, bus, pinout),
+        // Nop zero page x read
+        0x5400 =>  pinout = zeropage_x_read_c0(cpu, bus, pinout),
+        0x5401 =>  pinout = zeropage_x_read_c1(cpu, bus, pinout),
+        0x5402 =>  pinout = zeropage_x_read_c2(cpu, bus, pinout),
+        0x5403 =>  pinout = zeropage_x_read_c3::<B, Nop>(cpu, bus, pinout),
+        0xD400 =>  pinout = zeropage_x_read_c0(cpu, bus, pinout),
+        0xD401 =>  pinout = zeropage_x_read_c1(cpu, bus, pinout),
+        0xD402 =>  pinout = zeropage_x_read_c2(cpu, bus, pinout),
+        0xD403 =>  pinout = zeropage_x_read_c3::<B, Nop>(cpu, bus, pinout),
+        0xF400 =>  pinout = zeropage_x_read_c0(cpu, bus, pinout),
+        0xF401 =>  pinout = zeropage_x_read_c1(cpu, bus, pinout),
+        0xF402 =>  pinout = zeropage_x_read_c2(cpu, bus, pinout),
+        0xF403 =>  pinout = zeropage_x_read_c3::<B, Nop>(cpu, bus, pinout),
+        // Nop absolute read, $5C really takes 8 cycles
+        0x5C00 =>  pinout = absolute_read_c0(cpu, bus, pinout),
+        0x5C01 =>  pinout = absolute_read_c1(cpu, bus, pinout),
+        0x5C02 =>  pinout = absolute_read_c2(cpu, bus, pinout),
+        0x5C03 =>  pinout = absolute_read_c3::<B, Nop>(cpu, bus, pinout),
+        0xDC00 =>  pinout = absolute_read_c0(cpu, bus, pinout),
+        0xDC01 =>  pinout = absolute_read_c1(cpu, bus, pinout),
+        0xDC02 =>  pinout = absolute_read_c2(cpu, bus, pinout),
+        0xDC03 =>  pinout = absolute_read_c3::<B, Nop>(cpu, bus, pinout),
+        0xFC00 =>  pinout = absolute_read_c0(cpu, bus, pinout),
+        0xFC01 =>  pinout = absolute_read_c1(cpu, bus, pinout),
+        0xFC02 =>  pinout = absolute_read_c2(cpu, bus, pinout),
+        0xFC03 =>  pinout = absolute_read_c3::<B, Nop>(cpu, bus, pinout),
+        // Nop single cycle
+        0x0300 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x0B00 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x1300 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x1B00 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x2300 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x2B00 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x3300 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x3B00 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x4300 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x4B00 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x5300 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x5B00 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x6300 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x6B00 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x7300 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x7B00 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x8300 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x8B00 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x9300 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0x9B00 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0xA300 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0xAB00 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0xB300 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0xBB00 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0xC300 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0xD300 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0xE300 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0xEB00 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0xF300 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        0xFB00 =>  pinout = nop_single_cycle_c0(cpu, bus, pinout),
+        _ => pinout = nmos_dispatch::<B, CmosDecimal>(cpu, bus, pinout),
+    }
+    pinout
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::Ctrl;
+
+    struct Ram(Vec<u8>);
+
+    impl Bus for Ram {
+        fn read(&mut self, mut pinout: Pinout) -> Pinout {
+            pinout.data = self.0[pinout.address as usize];
+            pinout
+        }
+
+        fn write(&mut self, pinout: Pinout) -> Pinout {
+            self.0[pinout.address as usize] = pinout.data;
+            pinout
+        }
+    }
+
+    // runs from $0200 until an opcode is fetched at end, returns the cpu, ram and cycles taken
+    fn run(ram: &mut Ram, end: u16) -> (Context, u64) {
+        let (mut cpu, mut pinout) = Wdc65C02::from_context_at(Context::new(), 0x200);
+        pinout = cpu.tick(ram, pinout);
+        let start = cpu.cycle_count();
+        for _ in 0..1000 {
+            pinout = cpu.tick(ram, pinout);
+            if pinout.ctrl.contains(Ctrl::SYNC) && pinout.address == end {
+                return (cpu.get_context(), cpu.cycle_count() - start);
+            }
+        }
+        panic!("no opcode fetch at ${:04X}", end);
+    }
+
+    fn load(program: &[u8]) -> Ram {
+        let mut ram = Ram(vec![0; 0x10000]);
+        ram.0[0x200..0x200 + program.len()].copy_from_slice(program);
+        ram
+    }
+
+    #[test]
+    fn test_jmp_indirect_page_wrap() {
+        // jmp ($10FF)
+        let mut ram = load(&[0x6C, 0xFF, 0x10]);
+        ram.0[0x10FF] = 0x34;
+        ram.0[0x1100] = 0x12;
+        ram.0[0x1000] = 0x56;
+        let (_, cycles) = run(&mut ram, 0x1234);
+        assert_eq!(cycles, 6);
+
+        // ldx #$02, jmp ($0300,x)
+        let mut ram = load(&[0xA2, 0x02, 0x7C, 0x00, 0x03]);
+        ram.0[0x0302] = 0x78;
+        ram.0[0x0303] = 0x56;
+        run(&mut ram, 0x5678);
+    }
+
+    #[test]
+    fn test_cmos_opcodes() {
+        let mut ram = load(&[
+            0xA9, 0x0F,         // lda #$0F
+            0x80, 0x02,         // bra +2
+            0xA9, 0xFF,         // lda #$FF (skipped)
+            0x8D, 0x00, 0x03,   // sta $0300
+            0x9C, 0x00, 0x03,   // stz $0300
+            0xA9, 0x81,         // lda #$81
+            0x04, 0x10,         // tsb $10
+            0xA9, 0x01,         // lda #$01
+            0x14, 0x11,         // trb $11
+            0xB2, 0x20,         // lda ($20)
+            0x1A,               // inc a
+            0x92, 0x22,         // sta ($22)
+            0xA2, 0x5A,         // ldx #$5A
+            0xDA,               // phx
+            0x7A,               // ply
+            0x89, 0x00,         // bit #$00
+            0x03,               // 1 cycle nop
+            0xF7, 0x30,         // smb7 $30
+            0x0F, 0x30, 0x00,   // bbr0 $30, +0 (taken)
+            0x8F, 0x30, 0x00,   // bbs0 $30, +0 (not taken)
+        ]);
+        ram.0[0x0010] = 0x02;
+        ram.0[0x0011] = 0x03;
+        ram.0[0x0020] = 0x00;
+        ram.0[0x0021] = 0x04;
+        ram.0[0x0400] = 0x41;
+        ram.0[0x0022] = 0x01;
+        ram.0[0x0023] = 0x04;
+        ram.0[0x0030] = 0x00;
+        let (cpu, _) = run(&mut ram, 0x228);
+
+        assert_eq!(ram.0[0x0300], 0x00);
+        assert_eq!(ram.0[0x0010], 0x83);
+        assert_eq!(ram.0[0x0011], 0x02);
+        assert_eq!(ram.0[0x0401], 0x42);
+        assert_eq!(ram.0[0x0030], 0x80);
+        assert_eq!(cpu.y, 0x5A);
+        assert!(cpu.p.contains(StatusRegister::ZERO));
+    }
+
+    #[test]
+    fn test_decimal_cleared_by_brk() {
+        // sed, brk
+        let mut ram = load(&[0xF8, 0x00]);
+        ram.0[0xFFFE] = 0x00;
+        ram.0[0xFFFF] = 0x80;
+        let (cpu, _) = run(&mut ram, 0x8000);
+        assert!(!cpu.p.contains(StatusRegister::DECIMAL));
+        // pushed status still has D set
+        assert_eq!(ram.0[0x0100 + cpu.sp.wrapping_add(1) as usize] & 0x08, 0x08);
+    }
+
+    #[test]
+    fn test_decimal_flags() {
+        // sed, clc, lda #$99, adc #$01
+        let mut ram = load(&[0xF8, 0x18, 0xA9, 0x99, 0x69, 0x01]);
+        let (cpu, _) = run(&mut ram, 0x206);
+        assert_eq!(cpu.a, 0x00);
+        assert!(cpu.p.contains(StatusRegister::ZERO));
+        assert!(cpu.p.contains(StatusRegister::CARRY));
+    }
+}
@@ -0,0 +1,10 @@
+#!/bin/sh
+# Fetches the SingleStepTests nes6502 vectors into test_vectors/nes6502/v1 for test_single_step_vectors
+#   cargo test -p mos single_step -- --ignored
+set -e
+cd "$(dirname "$0")"
+rm -rf nes6502 65x02
+git clone --depth 1 --filter=blob:none --sparse https://github.com/SingleStepTests/65x02.git
+git -C 65x02 sparse-checkout set nes6502/v1
+mv 65x02/nes6502 nes6502
+rm -rf 65x02
@@ -1,7 +1,11 @@
 use super::dma::{Dma, ApuDmaInterconnect};
-use super::mappers::Mapper;
+use super::mappers::{Mapper, Context};
+use super::ppu;
 use super::ppu::rp2c02::Rp2c02;
-use super::controllers::NesControllers;
+use super::controllers::ControllerPorts;
+use super::debugger::breakpoints::Breakpoints;
+use super::debugger::cdl::{CodeDataLogger, ChrFlags};
+use super::cheats::Cheats;
 
 
 /*
@@ -29,28 +33,57 @@ pub struct CpuBus<'a> {
     mapper: &'a mut dyn Mapper,
     dma: &'a mut Dma,
     ppu: &'a mut Rp2c02,
-    controllers: &'a mut NesControllers,
+    controllers: &'a mut dyn ControllerPorts,
+    breakpoints: Option<&'a mut Breakpoints>,
+    cdl: Option<&'a mut CodeDataLogger>,
+    cheats: Option<&'a Cheats>,
     // TODO PPU, APU, Controller
 }
 
 impl<'a> CpuBus<'a> {
-    pub fn new(mapper: &'a mut dyn Mapper, dma: &'a mut Dma, ppu: &'a mut Rp2c02, controllers: &'a mut NesControllers) -> CpuBus<'a> {
+    pub fn new(mapper: &'a mut dyn Mapper, dma: &'a mut Dma, ppu: &'a mut Rp2c02, controllers: &'a mut dyn ControllerPorts) -> CpuBus<'a> {
         CpuBus {
             mapper: mapper,
             dma: dma,
             ppu: ppu,
             controllers: controllers,
+            breakpoints: None,
+            cdl: None,
+            cheats: None,
         }
     }
+
+    // records the accesses that match a breakpoint, left out when none are enabled
+    pub fn with_breakpoints(mut self, breakpoints: &'a mut Breakpoints) -> CpuBus<'a> {
+        self.breakpoints = Some(breakpoints);
+        self
+    }
+
+    pub fn with_cdl(mut self, cdl: &'a mut CodeDataLogger) -> CpuBus<'a> {
+        self.cdl = Some(cdl);
+        self
+    }
+
+    // PRG-ROM read substitutions, ram cheats are applied by the console once a frame
+    pub fn with_cheats(mut self, cheats: &'a Cheats) -> CpuBus<'a> {
+        self.cheats = Some(cheats);
+        self
+    }
 }
 
 impl<'a> mos::bus::Bus for CpuBus<'a> {
     fn read(&mut self, mut pinout: mos::Pinout) -> mos::Pinout {
+        let vram_address = self.ppu.vram_address();
         match pinout.address {
             0x0000..=0x1fff => { pinout = self.mapper.read_cpu_internal_ram(pinout); }
             0x4020..=0x5fff => { pinout = self.mapper.read_cpu_exp(pinout); }
             0x6000..=0x7fff => { pinout = self.mapper.read_cpu_wram(pinout); }
-            0x8000..=0xffff => { pinout = self.mapper.read_cpu_prg(pinout); }
+            0x8000..=0xffff => {
+                pinout = self.mapper.read_cpu_prg(pinout);
+                if let Some(cheats) = self.cheats {
+                    pinout = cheats.read_prg(pinout);
+                }
+            }
             0x2000..=0x3FFF => {
                 match pinout.address & 0x07 {
                     0 => { pinout = self.ppu.read_port(pinout); }
@@ -81,10 +114,22 @@ impl<'a> mos::bus::Bus for CpuBus<'a> {
             _ => { /* open bus */ }
         }
 
+        if let Some(breakpoints) = self.breakpoints.as_deref_mut() {
+            breakpoints.cpu_read(pinout, vram_address);
+        }
+
+        if let Some(cdl) = self.cdl.as_deref_mut() {
+            cdl.cpu_read(&*self.mapper, pinout);
+            if (0x2000..=0x3FFF).contains(&pinout.address) && pinout.address & 0x07 == 7 {
+                cdl.chr_read(&*self.mapper, vram_address, ChrFlags::READ);
+            }
+        }
+
         pinout
     }
 
     fn write(&mut self, mut pinout: mos::Pinout) -> mos::Pinout {
+        let vram_address = self.ppu.vram_address();
         match pinout.address {
             0x0000..=0x1fff => { pinout = self.mapper.write_cpu_internal_ram(pinout); }
             0x4020..=0x5fff => { pinout = self.mapper.write_cpu_exp(pinout); }
@@ -117,6 +162,10 @@ impl<'a> mos::bus::Bus for CpuBus<'a> {
             _ => { /* open bus */ }
         }
 
+        if let Some(breakpoints) = self.breakpoints.as_deref_mut() {
+            breakpoints.cpu_write(pinout, vram_address);
+        }
+
         pinout
     }
 }
@@ -127,22 +176,33 @@ impl<'a> mos::bus::Bus for CpuBus<'a> {
 pub struct DmaBus<'a> {
     mapper: &'a mut dyn Mapper,
     ppu: &'a mut Rp2c02,
-    controllers: &'a mut NesControllers,
+    controllers: &'a mut dyn ControllerPorts,
+    cdl: Option<&'a mut CodeDataLogger>,
+    // the dmc sample is marked when it reaches the apu
+    last_read: u16,
     // TODO PPU, APU
 }
 
 impl<'a> DmaBus<'a> {
-    pub fn new(mapper: &'a mut dyn Mapper,  ppu: &'a mut Rp2c02, controllers: &'a mut NesControllers) -> DmaBus<'a> {
+    pub fn new(mapper: &'a mut dyn Mapper,  ppu: &'a mut Rp2c02, controllers: &'a mut dyn ControllerPorts) -> DmaBus<'a> {
         DmaBus {
             mapper: mapper,
             ppu: ppu,
             controllers: controllers,
+            cdl: None,
+            last_read: 0,
         }
     }
+
+    pub fn with_cdl(mut self, cdl: &'a mut CodeDataLogger) -> DmaBus<'a> {
+        self.cdl = Some(cdl);
+        self
+    }
 }
 
 impl<'a> mos::bus::Bus for DmaBus<'a> {
     fn read(&mut self, mut pinout: mos::Pinout) -> mos::Pinout {
+        self.last_read = pinout.address;
         match pinout.address {
             0x0000..=0x1fff => { pinout = self.mapper.read_cpu_internal_ram(pinout); }
             0x4020..=0x5fff => { pinout = self.mapper.read_cpu_exp(pinout); }
@@ -204,6 +264,71 @@ impl<'a> mos::bus::Bus for DmaBus<'a> {
 
 impl<'a> ApuDmaInterconnect for DmaBus<'a> {
     fn update_dmc_sample(&mut self, _sample: u8) {
+        if let Some(cdl) = self.cdl.as_deref_mut() {
+            cdl.dmc_read(&*self.mapper, self.last_read);
+        }
         // TODO update APU
     }
 }
+
+//==================================================
+// PPU bus
+//===================================================
+// the mapper as the ppu sees it, chr and nametable accesses are passed to the vram watchpoints
+pub struct PpuBus<'a> {
+    mapper: &'a mut dyn Mapper,
+    breakpoints: &'a mut Breakpoints,
+}
+
+impl<'a> PpuBus<'a> {
+    pub fn new(mapper: &'a mut dyn Mapper, breakpoints: &'a mut Breakpoints) -> PpuBus<'a> {
+        PpuBus {
+            mapper: mapper,
+            breakpoints: breakpoints,
+        }
+    }
+}
+
+impl<'a> Mapper for PpuBus<'a> {
+    fn read_cpu_internal_ram(&mut self, pinout: mos::Pinout) -> mos::Pinout { self.mapper.read_cpu_internal_ram(pinout) }
+    fn read_cpu_exp(&mut self, pinout: mos::Pinout) -> mos::Pinout { self.mapper.read_cpu_exp(pinout) }
+    fn read_cpu_wram(&mut self, pinout: mos::Pinout) -> mos::Pinout { self.mapper.read_cpu_wram(pinout) }
+    fn read_cpu_prg(&mut self, pinout: mos::Pinout) -> mos::Pinout { self.mapper.read_cpu_prg(pinout) }
+
+    fn write_cpu_internal_ram(&mut self, pinout: mos::Pinout) -> mos::Pinout { self.mapper.write_cpu_internal_ram(pinout) }
+    fn write_cpu_exp(&mut self, pinout: mos::Pinout) -> mos::Pinout { self.mapper.write_cpu_exp(pinout) }
+    fn write_cpu_wram(&mut self, pinout: mos::Pinout) -> mos::Pinout { self.mapper.write_cpu_wram(pinout) }
+    fn write_cpu_prg(&mut self, pinout: mos::Pinout) -> mos::Pinout { self.mapper.write_cpu_prg(pinout) }
+
+    fn read_ppu_chr(&mut self, pinout: ppu::Pinout) -> ppu::Pinout {
+        let pinout = self.mapper.read_ppu_chr(pinout);
+        self.breakpoints.ppu_read(pinout);
+        pinout
+    }
+
+    fn read_ppu_nt(&mut self, pinout: ppu::Pinout) -> ppu::Pinout {
+        let pinout = self.mapper.read_ppu_nt(pinout);
+        self.breakpoints.ppu_read(pinout);
+        pinout
+    }
+
+    fn write_ppu_chr(&mut self, pinout: ppu::Pinout) -> ppu::Pinout {
+        let pinout = self.mapper.write_ppu_chr(pinout);
+        self.breakpoints.ppu_write(pinout);
+        pinout
+    }
+
+    fn write_ppu_nt(&mut self, pinout: ppu::Pinout) -> ppu::Pinout {
+        let pinout = self.mapper.write_ppu_nt(pinout);
+        self.breakpoints.ppu_write(pinout);
+        pinout
+    }
+
+    fn cpu_tick(&mut self, pinout: mos::Pinout) -> mos::Pinout { self.mapper.cpu_tick(pinout) }
+    fn ppu_tick(&mut self, pinout: ppu::Pinout) -> ppu::Pinout { self.mapper.ppu_tick(pinout) }
+
+    fn get_context(&self) -> &Context { self.mapper.get_context() }
+    fn get_context_mut(&mut self) -> &mut Context { self.mapper.get_context_mut() }
+    fn get_registers(&self) -> Vec<u8> { self.mapper.get_registers() }
+    fn set_register(&mut self, index: usize, data: u8) -> bool { self.mapper.set_register(index, data) }
+}
@@ -0,0 +1,279 @@
+use crate::consoles::EmuError;
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+const GAME_GENIE_LETTERS: &str = "APZLGITYEOXUKSVN";
+
+/*
+    Cheat codes, either Game Genie (6 or 8 letters) or raw "AAAA:VV" / "AAAA:VV:CC" codes.
+    Codes for $8000-$FFFF replace what the cpu reads from PRG-ROM, only while the mapped bank
+    holds the compare byte if there is one. Codes for $0000-$7FFF freeze the byte by writing it
+    at the end of every frame.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cheat {
+    pub code: String,
+    pub description: String,
+    pub enabled: bool,
+    pub address: u16,
+    pub value: u8,
+    pub compare: Option<u8>,
+}
+
+impl Cheat {
+    pub fn parse(code: &str) -> Result<Cheat, EmuError> {
+        let code = code.trim();
+        let (address, value, compare) = match decode_game_genie(code) {
+            Some(decoded) => decoded,
+            None => decode_raw(code).ok_or_else(|| EmuError::InvalidCheat(code.to_string()))?,
+        };
+
+        Ok(Cheat {
+            code: code.to_uppercase(),
+            description: String::new(),
+            enabled: true,
+            address,
+            value,
+            compare,
+        })
+    }
+
+    pub fn is_rom_cheat(&self) -> bool {
+        self.address >= 0x8000
+    }
+}
+
+// (address, value, compare)
+pub fn decode_game_genie(code: &str) -> Option<(u16, u8, Option<u8>)> {
+    let n = code.chars()
+        .map(|c| GAME_GENIE_LETTERS.find(c.to_ascii_uppercase()).map(|i| i as u16))
+        .collect::<Option<Vec<u16>>>()?;
+    if n.len() != 6 && n.len() != 8 {
+        return None;
+    }
+
+    let address = 0x8000 | ((n[3] & 7) << 12) | ((n[5] & 7) << 8) | ((n[4] & 8) << 8)
+        | ((n[2] & 7) << 4) | ((n[1] & 8) << 4) | (n[4] & 7) | (n[3] & 8);
+
+    // the high bit of the last letter moves to the compare byte in 8 letter codes
+    let last = n[n.len() - 1];
+    let value = ((n[1] & 7) << 4) | ((n[0] & 8) << 4) | (n[0] & 7) | (last & 8);
+    let compare = match n.len() {
+        8 => Some(((n[7] & 7) << 4) | ((n[6] & 8) << 4) | (n[6] & 7) | (n[5] & 8)),
+        _ => None,
+    };
+
+    Some((address, value as u8, compare.map(|c| c as u8)))
+}
+
+fn decode_raw(code: &str) -> Option<(u16, u8, Option<u8>)> {
+    let code = code.trim_start_matches('$');
+    let fields: Vec<&str> = code.split(':').collect();
+    match fields.as_slice() {
+        [address, value] => Some((hex(address, 4)? as u16, hex(value, 2)? as u8, None)),
+        [address, value, compare] => Some((hex(address, 4)? as u16, hex(value, 2)? as u8, Some(hex(compare, 2)? as u8))),
+        _ => None,
+    }
+}
+
+fn hex(text: &str, max_digits: usize) -> Option<u32> {
+    let text = text.trim_start_matches('$');
+    if text.is_empty() || text.len() > max_digits {
+        return None;
+    }
+    u32::from_str_radix(text, 16).ok()
+}
+
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+}
+
+impl Cheats {
+    pub fn new() -> Cheats {
+        Cheats {
+            cheats: Vec::new(),
+        }
+    }
+
+    // cheat list for a rom, named after the crc of the rom like screenshots are
+    pub fn file_name(rom_crc: u32) -> String {
+        format!("{:08X}.cht", rom_crc)
+    }
+
+    // returns the index of the new cheat
+    pub fn add(&mut self, code: &str, description: &str) -> Result<usize, EmuError> {
+        let mut cheat = Cheat::parse(code)?;
+        cheat.description = description.trim().to_string();
+        self.cheats.push(cheat);
+        Ok(self.cheats.len() - 1)
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Cheat> {
+        if index < self.cheats.len() { Some(self.cheats.remove(index)) } else { None }
+    }
+
+    pub fn clear(&mut self) {
+        self.cheats.clear();
+    }
+
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) -> bool {
+        match self.cheats.get_mut(index) {
+            Some(cheat) => { cheat.enabled = enabled; true }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Cheat> {
+        self.cheats.get(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cheat> {
+        self.cheats.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cheats.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    pub(crate) fn has_rom_cheats(&self) -> bool {
+        self.cheats.iter().any(|c| c.enabled && c.is_rom_cheat())
+    }
+
+    // PRG-ROM reads, data is what the mapped bank holds
+    pub(crate) fn read_prg(&self, mut pinout: mos::Pinout) -> mos::Pinout {
+        let cheat = self.cheats.iter().find(|c| {
+            c.enabled && c.address == pinout.address && c.compare.is_none_or(|compare| compare == pinout.data)
+        });
+        if let Some(cheat) = cheat {
+            pinout.data = cheat.value;
+        }
+
+        pinout
+    }
+
+    // ram cheats with their current value, the value to write is returned when the compare byte matches
+    pub(crate) fn ram_writes<F: FnMut(u16) -> u8>(&self, mut peek: F) -> Vec<(u16, u8)> {
+        self.cheats.iter()
+            .filter(|c| c.enabled && !c.is_rom_cheat())
+            .filter(|c| c.compare.is_none_or(|compare| compare == peek(c.address)))
+            .map(|c| (c.address, c.value))
+            .collect()
+    }
+
+    /*
+        One cheat per line, the code then an optional description
+        SXIOPO Infinite lives
+        -0075:09 Disabled cheats start with '-', blank lines and lines starting with ';' are skipped
+    */
+    pub fn from_reader<R: BufRead>(r: R) -> Result<Cheats, EmuError> {
+        let mut cheats = Cheats::new();
+        for line in r.lines() {
+            let line = line.map_err(|_| EmuError::CheatFileError)?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+
+            let (enabled, line) = match line.strip_prefix('-') {
+                Some(line) => (false, line),
+                None => (true, line),
+            };
+            let (code, description) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let index = cheats.add(code, description)?;
+            cheats.set_enabled(index, enabled);
+        }
+
+        Ok(cheats)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Cheats, EmuError> {
+        let file = File::open(path).map_err(|_| EmuError::CheatFileError)?;
+        Cheats::from_reader(BufReader::new(file))
+    }
+
+    pub fn save<W: Write>(&self, w: &mut W) -> Result<(), EmuError> {
+        for cheat in self.cheats.iter() {
+            let disabled = if cheat.enabled { "" } else { "-" };
+            let line = format!("{}{} {}", disabled, cheat.code, cheat.description);
+            writeln!(w, "{}", line.trim_end()).map_err(|_| EmuError::CheatFileError)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Cheats {
+    fn default() -> Self {
+        Cheats::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::consoles::Console;
+    use crate::consoles::nes_ntsc::NesNtsc;
+    use crate::debugger::memory::MemoryType;
+    use std::path::Path;
+
+    #[test]
+    fn test_decode() {
+        // smb infinite lives
+        assert_eq!(decode_game_genie("SXIOPO"), Some((0x91D9, 0xAD, None)));
+        assert_eq!(decode_game_genie("sxiopo"), Some((0x91D9, 0xAD, None)));
+        assert_eq!(decode_game_genie("AAEAULPA"), Some((0x8B03, 0x00, Some(0x01))));
+        assert_eq!(decode_game_genie("SXIOP"), None);
+        assert_eq!(decode_game_genie("SXIOPB"), None);
+
+        let cheat = Cheat::parse("0075:09").unwrap();
+        assert_eq!((cheat.address, cheat.value, cheat.compare, cheat.is_rom_cheat()), (0x0075, 0x09, None, false));
+        let cheat = Cheat::parse("$C5F5:EA:A2").unwrap();
+        assert_eq!((cheat.address, cheat.value, cheat.compare), (0xC5F5, 0xEA, Some(0xA2)));
+        assert!(Cheat::parse("07FF01").is_err());
+        assert!(Cheat::parse("aéé:a").is_err());
+        assert!(Cheat::parse("12345:00").is_err());
+        assert!(Cheat::parse("0075").is_err());
+    }
+
+    #[test]
+    fn test_cheat_file() {
+        let file = "; smb\nSXIOPO Infinite lives\n\n-0075:09 Start on world 8\n";
+        let cheats = Cheats::from_reader(file.as_bytes()).unwrap();
+        assert_eq!(cheats.len(), 2);
+        assert_eq!(cheats.get(0).unwrap().description, "Infinite lives");
+        assert!(!cheats.get(1).unwrap().enabled);
+
+        let mut saved = Vec::new();
+        cheats.save(&mut saved).unwrap();
+        assert_eq!(String::from_utf8(saved).unwrap(), "SXIOPO Infinite lives\n-0075:09 Start on world 8\n");
+        assert!(Cheats::from_reader("XXXXXXX\n".as_bytes()).is_err());
+        assert_eq!(Cheats::file_name(0x1234ABCD), "1234ABCD.cht");
+    }
+
+    #[test]
+    fn test_nestest_cheats() {
+        let test_roms = Path::new(env!("CARGO_MANIFEST_DIR")).join("../rustnes-dev/test_roms");
+        let mut nes = NesNtsc::new();
+        nes.load_rom(test_roms.join("nestest.nes"));
+        nes.power_on_console_at(0xC000);
+
+        // C5F5 LDX #$00 becomes LDX #$42, the compare byte doesn't match the other cheat, C5F7 STX $00
+        nes.cheats().add("C5F6:42:00", "").unwrap();
+        nes.cheats().add("C5F6:10:FF", "").unwrap();
+        nes.cheats().add("0010:77", "").unwrap();
+        while nes.step_instruction().unwrap() != 0xC5F9 {}
+        assert_eq!(nes.peek_memory(MemoryType::CpuRam, 0x0000), Some(0x42));
+
+        // rom itself is unchanged, ram is frozen at the end of the frame
+        assert_eq!(nes.peek_memory(MemoryType::PrgRom, 0x05F6), Some(0x00));
+        nes.power_on_console();
+        nes.execute_frame().unwrap();
+        assert_eq!(nes.peek_memory(MemoryType::CpuRam, 0x0010), Some(0x77));
+    }
+}
@@ -1,13 +1,33 @@
+pub mod nes_core;
 pub mod nes_ntsc;
+pub mod vs_system;
 
 use crate::controllers::JoypadInput;
+use crate::video::output::{Overscan, PixelAspect, VideoOutput};
+use crate::video::screenshot;
+use crate::debugger::breakpoints::BreakpointHit;
+use crate::debugger::memory::MemoryType;
 use std::path::Path;
 use std::io::Write;
 
+#[derive(Debug)]
 pub enum EmuError {
     LoadRomError,
     PixBufferError,
     LogError,
+    LoadPaletteError,
+    ScreenshotError,
+    RecordError,
+    LoadMovieError,
+    LoadSymbolsError,
+    CdlError,
+    InvalidCheat(String),
+    CheatFileError,
+    // KIL or an invalid cpu state, execution stops until the console is reset
+    CpuJammed { pc: u16, opcode: u8 },
+    // execution stopped on a breakpoint, the next execute_frame carries on from there
+    BreakpointHit(BreakpointHit),
+    InvalidExpression(String),
 }
 
 pub trait Console {
@@ -16,9 +36,13 @@ pub trait Console {
     fn restart_console(& mut self);
 
     fn get_frame_number(&self) -> u64;
+    fn get_rom_crc(&self) -> u32;
     fn get_index_buffer(&self) -> &[u16];
+    fn get_scanline_phases(&self) -> &[u8];
 
-    fn execute_frame(&mut self);
+    // returns early with CpuJammed once the cpu jams, until the console is reset,
+    // or with BreakpointHit after the cycle that triggered a breakpoint
+    fn execute_frame(&mut self) -> Result<(), EmuError>;
 
     fn input_joypad1_state(&mut self, joypad: JoypadInput);
     fn input_joypad2_state(&mut self, joypad: JoypadInput);
@@ -26,4 +50,19 @@ pub trait Console {
     fn output_pixel_buffer(&mut self, frame_buffer: &mut [u32]) -> Result<(), EmuError>;
     fn output_cpu_log<W: Write>(&mut self , w: &mut W);    
     fn output_ppu_log<W: Write>(&mut self , w: &mut W);    
-}
\ No newline at end of file
+
+    // debugger memory access without bus side effects, see debugger::memory
+    fn memory_size(&self, memory: MemoryType) -> usize;
+    fn peek_memory(&mut self, memory: MemoryType, address: usize) -> Option<u8>;
+    fn poke_memory(&mut self, memory: MemoryType, address: usize, data: u8) -> bool;
+
+    // PNG of the current frame with the overscan cropped, tagged with the rom crc and frame number
+    fn output_screenshot<W: Write>(&mut self, w: &mut W, overscan: Overscan) -> Result<(), EmuError> {
+        let mut frame_buffer = vec![0u32; 256 * 240];
+        self.output_pixel_buffer(&mut frame_buffer)?;
+
+        let mut cropped = Vec::new();
+        let (width, height) = VideoOutput::new(overscan, PixelAspect::Square).process(&frame_buffer, 256, 240, &mut cropped);
+        screenshot::encode_png(w, &cropped, width, height, self.get_rom_crc(), self.get_frame_number())
+    }
+}
@@ -0,0 +1,528 @@
+use super::*;
+use crate::dma::Dma;
+use crate::ppu::{PpuModel, Ctrl as PpuCtrl};
+use crate::ppu::rp2c02::Rp2c02;
+use crate::ppu::viewers::{self, OamSprite};
+use crate::mappers;
+use crate::mappers::Mapper;
+use crate::controllers::{ControllerPorts, JoypadInput};
+use crate::palette::*;
+use crate::bus::*;
+use crate::utils::cpu_trace_logger::{CpuTraceLogger, CpuTraceFormat};
+use crate::utils::nestest_trace_logger::{self, NestestTraceLogger, CpuState};
+use crate::utils::ppu_trace_logger::PpuTraceLogger;
+use crate::debugger::breakpoints::Breakpoints;
+use crate::debugger::expression::{Expression, EvalContext};
+use crate::debugger::disassembler::{self, DisassemblyLine};
+use crate::debugger::symbols::SymbolTable;
+use crate::debugger::memory::{self, MemoryType};
+use crate::cheats::Cheats;
+use crate::debugger::cdl::{CodeDataLogger, ChrFlags};
+use mos::{Pinout, Ctrl, rp2a03::Rp2a03, core::CpuJam};
+
+use std::fs::File;
+use std::io::{Read, BufReader, BufWriter};
+use std::path::Path;
+use ::nes_rom::ines;
+
+const WIDTH: u32 = 256;
+const HEIGHT: u32 = 240;
+
+/*
+    CPU, DMA, PPU and cartridge shared by the NES and the Vs. System, generic over what is
+    plugged into the controller ports. The consoles only add a constructor and their own
+    inputs, so everything else behaves the same on both.
+*/
+pub struct NesCore<C: ControllerPorts> {
+    cpu: Rp2a03,
+    cpu_pinout: Pinout,
+    dma: Dma,
+    ppu: Rp2c02,
+    pub(super) controllers: C,
+    mapper: Box<dyn Mapper>,
+    cpu_logger: CpuTraceLogger,
+    ppu_logger: PpuTraceLogger,
+    nestest_logger: NestestTraceLogger,
+    pbuffer: Vec<u16>,
+    rom_crc: u32,
+    cpu_trace: bool,
+    cpu_trace_format: CpuTraceFormat,
+    palette: Palette,
+    breakpoints: Breakpoints,
+    symbols: SymbolTable,
+    cdl: Option<CodeDataLogger>,
+    cheats: Cheats,
+    // PRG-ROM and CHR-ROM sizes of the loaded rom, CHR-RAM is 0
+    rom_sizes: (usize, usize),
+}
+
+impl<C: ControllerPorts> NesCore<C> {
+    pub(super) fn with_controllers(controllers: C, model: PpuModel) -> Self {
+        let (cpu, cpu_pinout) = Rp2a03::from_power_on();
+        NesCore {
+            cpu,
+            cpu_pinout,
+            dma: Dma::from_power_on(),
+            ppu: Rp2c02::from_power_on_as(model),
+            controllers,
+            mapper: mappers::create_mapper_null(),
+            cpu_logger: CpuTraceLogger::new(),
+            ppu_logger: PpuTraceLogger::new(),
+            nestest_logger: NestestTraceLogger::new(),
+            pbuffer: vec![0; (WIDTH*HEIGHT) as usize],
+            rom_crc: 0,
+            cpu_trace: false,
+            cpu_trace_format: CpuTraceFormat::Cycles,
+            palette: Palette::from_source(model.palette_source()),
+            breakpoints: Breakpoints::new(),
+            symbols: SymbolTable::new(),
+            cdl: None,
+            cheats: Cheats::new(),
+            rom_sizes: (0, 0),
+        }
+    }
+
+    // RGB PPUs for PlayChoice-10 and Vs. System boards, PPU is reset to power on state
+    // and the palette is reset to the model's palette
+    pub fn set_ppu_model(&mut self, model: PpuModel) {
+        self.ppu = Rp2c02::from_power_on_as(model);
+        self.reset_palette();
+    }
+
+    pub fn get_ppu_model(&self) -> PpuModel {
+        self.ppu.model()
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    pub fn get_palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    pub fn load_palette<P: AsRef<Path>>(&mut self, pal_path: P) -> Result<(), EmuError> {
+        self.palette = Palette::from_pal_file(pal_path)?;
+        Ok(())
+    }
+
+    pub fn generate_palette(&mut self, settings: PaletteSettings) {
+        self.palette = Palette::generate(settings);
+    }
+
+    pub fn reset_palette(&mut self) {
+        self.palette = Palette::from_source(self.ppu.model().palette_source());
+    }
+
+    // logs every cpu cycle of the next frames for output_cpu_log, slows emulation down noticeably
+    pub fn set_cpu_trace(&mut self, enabled: bool) {
+        self.cpu_trace = enabled;
+    }
+
+    pub fn set_cpu_trace_format(&mut self, format: CpuTraceFormat) {
+        self.cpu_trace_format = format;
+    }
+
+    // power on with the cpu starting at pc instead of the reset vector, e.g. nestest automation mode at $C000
+    pub fn power_on_console_at(&mut self, pc: u16) {
+        self.power_on_console();
+        let (cpu, cpu_pinout) = Rp2a03::from_power_on_at(pc);
+        self.cpu = cpu;
+        self.cpu_pinout = cpu_pinout;
+    }
+
+    // runs until the next opcode fetch and returns its address, the trace log keeps growing until the next frame
+    pub fn step_instruction(&mut self) -> Result<u16, EmuError> {
+        loop {
+            if self.tick() {
+                self.end_frame();
+            }
+            self.check_cpu_jam()?;
+            self.check_breakpoints()?;
+            if self.cpu_pinout.ctrl.contains(Ctrl::SYNC) {
+                return Ok(self.cpu_pinout.address);
+            }
+        }
+    }
+
+    pub fn get_cpu_jam(&self) -> Option<CpuJam> {
+        self.cpu.jammed()
+    }
+
+    // address of the current instruction, or of the next one at an opcode fetch
+    pub fn get_cpu_pc(&self) -> u16 {
+        self.cpu.pc()
+    }
+
+    // execution breakpoints and watchpoints, checked while execute_frame and step_instruction run
+    pub fn breakpoints(&mut self) -> &mut Breakpoints {
+        &mut self.breakpoints
+    }
+
+    // labels for the disassembly, cpu trace and breakpoint conditions, kept when a rom is loaded
+    pub fn symbols(&mut self) -> &mut SymbolTable {
+        &mut self.symbols
+    }
+
+    // cpu address of a label, rom labels only resolve while their bank is mapped
+    pub fn resolve_symbol(&self, name: &str) -> Option<u16> {
+        self.symbols.resolve(name, &*self.mapper)
+    }
+
+    // breakpoint condition with labels resolved through the current prg banks
+    pub fn parse_expression(&self, source: &str) -> Result<Expression, EmuError> {
+        Expression::parse_with_symbols(source, |name| self.resolve_symbol(name))
+    }
+
+    // count instructions from address through the current prg banks
+    pub fn disassemble(&mut self, address: u16, count: usize) -> Vec<DisassemblyLine> {
+        disassembler::disassemble(&mut *self.mapper, &self.symbols, address, count)
+    }
+
+    // cleared when a rom is loaded, see Cheats::file_name to keep a list per rom
+    pub fn cheats(&mut self) -> &mut Cheats {
+        &mut self.cheats
+    }
+
+    pub fn load_cheats<P: AsRef<Path>>(&mut self, cheats_path: P) -> Result<(), EmuError> {
+        self.cheats = Cheats::from_file(cheats_path)?;
+        Ok(())
+    }
+
+    pub fn save_cheats<P: AsRef<Path>>(&self, cheats_path: P) -> Result<(), EmuError> {
+        let file = File::create(cheats_path).map_err(|_| EmuError::CheatFileError)?;
+        self.cheats.save(&mut BufWriter::new(file))
+    }
+
+    // starts logging code and data for the loaded rom, a running log is kept
+    pub fn start_cdl(&mut self) {
+        if self.cdl.is_none() {
+            self.cdl = Some(CodeDataLogger::new(self.rom_sizes.0, self.rom_sizes.1));
+        }
+    }
+
+    // returns the log, None if it wasn't running
+    pub fn stop_cdl(&mut self) -> Option<CodeDataLogger> {
+        self.cdl.take()
+    }
+
+    pub fn get_cdl(&self) -> Option<&CodeDataLogger> {
+        self.cdl.as_ref()
+    }
+
+    pub fn save_cdl<P: AsRef<Path>>(&self, cdl_path: P) -> Result<(), EmuError> {
+        let cdl = self.cdl.as_ref().ok_or(EmuError::CdlError)?;
+        let file = File::create(cdl_path).map_err(|_| EmuError::CdlError)?;
+        cdl.save(&mut BufWriter::new(file))
+    }
+
+    // merges a saved log into the running one, starts logging if it wasn't running
+    pub fn load_cdl<P: AsRef<Path>>(&mut self, cdl_path: P) -> Result<(), EmuError> {
+        let file = File::open(cdl_path).map_err(|_| EmuError::CdlError)?;
+        let mut cdl = self.cdl.take().unwrap_or_else(|| CodeDataLogger::new(self.rom_sizes.0, self.rom_sizes.1));
+        let result = cdl.load(&mut BufReader::new(file));
+        self.cdl = Some(cdl);
+        result
+    }
+
+    // cpu address space without side effects, registers and open bus read as 0
+    pub fn peek_cpu(&mut self, address: u16) -> u8 {
+        mappers::peek_cpu(&mut *self.mapper, address)
+    }
+
+    // 2K internal ram, read through the mapper so nothing on the bus is affected
+    pub fn get_cpu_ram(&mut self) -> Vec<u8> {
+        let mut pinout = Pinout::new();
+        (0..0x800u16).map(|address| {
+            pinout.address = address;
+            self.mapper.read_cpu_internal_ram(pinout).data
+        }).collect()
+    }
+
+    // $6000-$7FFF through the mapper, 0 when the cart has no ram or it is disabled
+    pub fn read_wram(&mut self, address: u16) -> u8 {
+        let mut pinout = Pinout::new();
+        pinout.address = 0x6000 | (address & 0x1FFF);
+        self.mapper.read_cpu_wram(pinout).data
+    }
+
+    // ppu viewers, buffer sizes are in ppu_viewers
+    pub fn render_nametables(&mut self, buffer: &mut [u32], scroll_overlay: bool) {
+        viewers::render_nametables(&mut self.ppu, &*self.mapper, &self.palette, buffer, scroll_overlay);
+    }
+
+    pub fn render_pattern_tables(&mut self, buffer: &mut [u32], palette_index: u8) {
+        viewers::render_pattern_tables(&mut self.ppu, &*self.mapper, &self.palette, buffer, palette_index);
+    }
+
+    pub fn render_oam(&mut self, buffer: &mut [u32]) {
+        viewers::render_oam(&mut self.ppu, &*self.mapper, &self.palette, buffer);
+    }
+
+    pub fn get_oam_sprites(&mut self) -> Vec<OamSprite> {
+        viewers::oam_sprites(&mut self.ppu)
+    }
+
+    pub fn render_palette_ram(&mut self, buffer: &mut [u32]) {
+        viewers::render_palette_ram(&mut self.ppu, &self.palette, buffer);
+    }
+
+    fn check_cpu_jam(&self) -> Result<(), EmuError> {
+        match self.cpu.jammed() {
+            Some(jam) => Err(EmuError::CpuJammed { pc: jam.pc, opcode: jam.opcode }),
+            None => Ok(()),
+        }
+    }
+
+    fn check_breakpoints(&mut self) -> Result<(), EmuError> {
+        if !self.breakpoints.has_pending() {
+            return Ok(());
+        }
+
+        let cpu = self.cpu.get_context();
+        let context = EvalContext {
+            a: cpu.a,
+            x: cpu.x,
+            y: cpu.y,
+            sp: cpu.sp,
+            p: cpu.p.bits(),
+            pc: self.cpu.pc(),
+            ..Default::default()
+        };
+
+        let mapper = &mut *self.mapper;
+        match self.breakpoints.resolve(context, |address| mappers::peek_cpu(mapper, address)) {
+            Some(hit) => Err(EmuError::BreakpointHit(hit)),
+            None => Ok(()),
+        }
+    }
+
+    // one cpu cycle, returns true at the end of the frame
+    fn tick(&mut self) -> bool {
+        let mut end_of_frame = false;
+
+        {
+            let mut bus = CpuBus::new(&mut *self.mapper, &mut self.dma, &mut self.ppu, &mut self.controllers);
+            if self.breakpoints.is_active() {
+                bus = bus.with_breakpoints(&mut self.breakpoints);
+            }
+            if let Some(cdl) = self.cdl.as_mut() {
+                bus = bus.with_cdl(cdl);
+            }
+            if self.cheats.has_rom_cheats() {
+                bus = bus.with_cheats(&self.cheats);
+            }
+            self.cpu_pinout = self.cpu.tick(&mut bus, self.cpu_pinout);
+        }
+
+        if self.cpu_trace && self.cpu_trace_format == CpuTraceFormat::Nestest && self.cpu_pinout.ctrl.contains(Ctrl::SYNC) {
+            self.log_nestest_line();
+        }
+
+        {
+            let mut bus = DmaBus::new(&mut *self.mapper, &mut self.ppu, &mut self.controllers);
+            if let Some(cdl) = self.cdl.as_mut() {
+                bus = bus.with_cdl(cdl);
+            }
+            self.cpu_pinout = self.dma.tick(&mut bus, self.cpu_pinout);
+        }
+
+        {
+            self.ppu_tick();
+            //self.ppu_logger.log(self.ppu.get_context(), self.ppu.get_pinout(), self.ppu.get_background());
+            if self.ppu.is_end_of_frame() { end_of_frame = true; }
+            self.log_chr_fetch();
+
+            self.ppu_tick();
+            //self.ppu_logger.log(self.ppu.get_context(), self.ppu.get_pinout(), self.ppu.get_background());
+            if self.ppu.is_end_of_frame() { end_of_frame = true; }
+            self.log_chr_fetch();
+
+            self.ppu_tick();
+            //self.ppu_logger.log(self.ppu.get_context(), self.ppu.get_pinout(), self.ppu.get_background());
+            if self.ppu.is_end_of_frame() { end_of_frame = true; }
+            self.log_chr_fetch();
+        }
+
+        {
+            // APU
+        }
+
+        {
+            self.cpu_pinout = (*self.mapper).cpu_tick(self.cpu_pinout);
+        }
+
+        if self.cpu_trace && self.cpu_trace_format == CpuTraceFormat::Cycles {
+            let bank = disassembler::prg_bank(&*self.mapper, self.cpu_pinout.address);
+            self.cpu_logger.log(self.cpu.get_context(), self.cpu_pinout, bank.map(|b| b.rom_offset));
+        }
+
+        end_of_frame
+    }
+
+    // one ppu cycle, the vram watchpoints see the mapper through the ppu bus
+    fn ppu_tick(&mut self) {
+        if self.breakpoints.is_active() {
+            let mut bus = PpuBus::new(&mut *self.mapper, &mut self.breakpoints);
+            self.cpu_pinout = self.ppu.tick(&mut self.pbuffer, &mut bus, self.cpu_pinout);
+        } else {
+            self.cpu_pinout = self.ppu.tick(&mut self.pbuffer, &mut *self.mapper, self.cpu_pinout);
+        }
+    }
+
+    // after the last cpu cycle of a frame
+    fn end_frame(&mut self) {
+        self.freeze_cheats();
+        self.controllers.end_frame();
+    }
+
+    fn freeze_cheats(&mut self) {
+        let mapper = &mut *self.mapper;
+        for (address, data) in self.cheats.ram_writes(|address| mappers::peek_cpu(mapper, address)) {
+            memory::poke(mapper, &mut self.ppu, MemoryType::CpuBus, address as usize, data);
+        }
+    }
+
+    // pattern fetches while rendering, $2007 reads are logged on the cpu bus
+    fn log_chr_fetch(&mut self) {
+        if let Some(cdl) = self.cdl.as_mut() {
+            let pinout = self.ppu.get_pinout();
+            if !pinout.ctrl.contains(PpuCtrl::RD) && pinout.address < 0x2000 && self.ppu.is_rendering() {
+                cdl.chr_read(&*self.mapper, pinout.address, ChrFlags::RENDERED);
+            }
+        }
+    }
+
+    // called on the opcode fetch, registers still hold the state before the instruction
+    fn log_nestest_line(&mut self) {
+        let cpu = self.cpu.get_context();
+        let ppu = self.ppu.get_context();
+        let state = CpuState {
+            pc: self.cpu_pinout.address,
+            a: cpu.a,
+            x: cpu.x,
+            y: cpu.y,
+            p: cpu.p.bits(),
+            sp: cpu.sp,
+            scanline: ppu.vpos,
+            dot: ppu.hpos,
+            // cycle is counted at the end of the tick
+            cycle: cpu.cycle - 1,
+        };
+        let line = nestest_trace_logger::format_nestest_line(&state, |address| self.peek_cpu(address));
+        self.nestest_logger.log(line);
+    }
+}
+
+impl<C: ControllerPorts> Console for NesCore<C> {
+    fn load_rom<P: AsRef<Path>>(&mut self, rom_path: P) {
+        // only accepting ines for now
+        let mut rom_data = Vec::new();
+        File::open(rom_path).unwrap().read_to_end(&mut rom_data).unwrap();
+        let ines = ines::Ines::from_rom(std::io::Cursor::new(&rom_data[..])).unwrap();
+        // crc of the rom without the ines header, same as no-intro and most emulators
+        self.rom_crc = crc32fast::hash(&rom_data[16.min(rom_data.len())..]);
+        self.mapper = mappers::create_mapper(&ines);
+        self.rom_sizes = (ines.prg_data.len(), ines.chr_data.len());
+        self.cdl = None;
+        self.cheats.clear();
+
+        self.power_on_console();
+    }
+
+    fn power_on_console(&mut self) {
+        let (cpu, cpu_pinout) = Rp2a03::from_power_on();
+        self.cpu = cpu;
+        self.cpu_pinout = cpu_pinout;
+        
+        self.ppu = Rp2c02::from_power_on_as(self.ppu.model());
+        self.dma = Dma::from_power_on();
+        self.pbuffer = vec![0; (WIDTH*HEIGHT) as usize];
+    }
+
+    fn restart_console(&mut self) {
+        let (cpu, cpu_pinout) = self.cpu.from_reset();
+        self.cpu = cpu;
+        self.cpu_pinout = cpu_pinout;
+        
+        self.ppu = self.ppu.from_reset();
+        self.dma = Dma::from_power_on();
+        self.pbuffer = vec![0; (WIDTH*HEIGHT) as usize];
+    }
+
+    fn get_frame_number(&self) -> u64 {
+        self.ppu.frame_number()
+    }
+
+    fn get_rom_crc(&self) -> u32 {
+        self.rom_crc
+    }
+
+    fn get_index_buffer(&self) -> &[u16] {
+        self.pbuffer.as_slice()
+    }
+
+    fn get_scanline_phases(&self) -> &[u8] {
+        self.ppu.scanline_phases()
+    }
+
+    fn execute_frame(&mut self) -> Result<(), EmuError> {
+        self.cpu_logger.clear();
+        self.ppu_logger.clear();
+        self.nestest_logger.clear();
+
+        loop {
+            // a jam or breakpoint on the last cycle still ends the frame, or the next call would run two
+            let end_of_frame = self.tick();
+            if end_of_frame { self.end_frame(); }
+            self.check_cpu_jam()?;
+            self.check_breakpoints()?;
+            if end_of_frame { break; }
+        }
+
+        Ok(())
+    }
+
+    fn input_joypad1_state(&mut self, controller: JoypadInput) {
+        self.controllers.set_joypad1_state(controller);
+    }
+
+    fn input_joypad2_state(&mut self, controller: JoypadInput) {
+        self.controllers.set_joypad2_state(controller);
+    }
+
+    fn output_pixel_buffer(&mut self, frame_buffer: &mut [u32]) -> Result<(), EmuError> {
+        for it in self.pbuffer.iter_mut().zip(frame_buffer.iter_mut()) {
+            let (fi, pi) = it;
+            if *fi > 0x1FF {
+                return Err(EmuError::PixBufferError);
+            }
+            *pi = self.palette.color(*fi);
+        }
+
+        Ok(())
+    }
+
+    fn output_cpu_log<W: Write>(&mut self , w: &mut W) {
+        match self.cpu_trace_format {
+            CpuTraceFormat::Cycles => self.cpu_logger.output_log_with_symbols(w, &self.symbols),
+            CpuTraceFormat::Nestest => self.nestest_logger.output_log(w),
+        }
+    }
+
+    fn output_ppu_log<W: Write>(&mut self , w: &mut W) {
+        self.ppu_logger.output_log(w);
+    }
+
+    fn memory_size(&self, memory: MemoryType) -> usize {
+        memory::memory_size(&*self.mapper, memory)
+    }
+
+    fn peek_memory(&mut self, memory: MemoryType, address: usize) -> Option<u8> {
+        memory::peek(&mut *self.mapper, &mut self.ppu, memory, address)
+    }
+
+    fn poke_memory(&mut self, memory: MemoryType, address: usize, data: u8) -> bool {
+        memory::poke(&mut *self.mapper, &mut self.ppu, memory, address, data)
+    }
+}
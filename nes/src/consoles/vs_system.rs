@@ -0,0 +1,77 @@
+use super::nes_core::NesCore;
+use crate::ppu::PpuModel;
+use crate::controllers::{VsControllers, CoinSlot};
+
+/*
+    Nintendo Vs. System (single cpu UniSystem)
+    Same cpu and timing as the NES but with an RGB PPU, coin slots, service button and
+    8 dip switches read through the controller ports. Each game expects a specific PPU,
+    the wrong one scrambles the palette or breaks the PPUCTRL/PPUMASK swap protection.
+*/
+pub type VsSystem = NesCore<VsControllers>;
+
+impl VsSystem {
+    pub fn new() -> Self {
+        NesCore::with_controllers(VsControllers::from_power_on(), PpuModel::Rp2c04_0001)
+    }
+
+    // bit 0 is dip switch 1, most games only read dip switches on reset
+    pub fn set_dip_switches(&mut self, dip_switches: u8) {
+        self.controllers.set_dip_switches(dip_switches);
+    }
+
+    pub fn get_dip_switches(&self) -> u8 {
+        self.controllers.dip_switches()
+    }
+
+    pub fn insert_coin(&mut self, slot: CoinSlot) {
+        self.controllers.insert_coin(slot);
+    }
+
+    pub fn input_service_button(&mut self, pressed: bool) {
+        self.controllers.set_service_button(pressed);
+    }
+
+    // some games read player 1 from $4017
+    pub fn set_swap_joypads(&mut self, swap: bool) {
+        self.controllers.set_swap_joypads(swap);
+    }
+}
+
+impl Default for VsSystem {
+    fn default() -> Self {
+        VsSystem::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::consoles::{Console, EmuError};
+    use crate::debugger::breakpoints::BreakOn;
+    use crate::palette::Palette;
+    use std::path::Path;
+
+    #[test]
+    fn test_debugger_on_vs_system() {
+        let test_roms = Path::new(env!("CARGO_MANIFEST_DIR")).join("../rustnes-dev/test_roms");
+        let mut vs = VsSystem::new();
+        vs.load_rom(test_roms.join("nestest.nes"));
+        vs.power_on_console_at(0xC000);
+        assert_eq!(vs.get_palette().color(0x00), Palette::from_source(PpuModel::Rp2c04_0001.palette_source()).color(0x00));
+
+        // C5F5 LDX #$00, C5F7 STX $00
+        let exec = vs.breakpoints().add(BreakOn::EXECUTE, 0xC5F7, 0xC5F7, None);
+        vs.set_cpu_trace(true);
+        match vs.execute_frame() {
+            Err(EmuError::BreakpointHit(hit)) => assert_eq!(hit.id, exec),
+            _ => panic!("execute breakpoint not hit"),
+        }
+        assert_eq!(vs.step_instruction().unwrap(), 0xC5F9);
+        assert!(!vs.disassemble(0xC5F9, 1).is_empty());
+
+        let mut log = Vec::new();
+        vs.output_cpu_log(&mut log);
+        assert!(!log.is_empty());
+    }
+}
@@ -3,6 +3,8 @@ use std::thread::JoinHandle;
 
 const NESS001_MASK: u8 = 0b11100000;
 const NESS101_MASK: u8 = 0b11100100;
+// frames a coin switch is held closed, games ignore pulses that are too short
+const COIN_PULSE_FRAMES: u8 = 4;
 
 bitflags! {
     pub struct JoypadInput: u8 {
@@ -51,20 +53,34 @@ impl NesControllers {
         }
     }
 
-    pub fn set_joypad1_state(&mut self, controller: JoypadInput) {
-        self.joypad1 = controller;
+    pub fn clear_joypads_state(&mut self) {
+        self.joypad1.clear();
+        self.joypad2.clear();
     }
+}
 
-    pub fn set_joypad2_state(&mut self, controller: JoypadInput) {
-        self.joypad2 = controller;
+// cpu side of the controller ports $4016/$4017 and the joypads plugged into them
+pub trait ControllerPorts {
+    fn write_4016(&mut self, pinout: mos::Pinout) -> mos::Pinout;
+    fn read_4016(&mut self, pinout: mos::Pinout) -> mos::Pinout;
+    fn read_4017(&mut self, pinout: mos::Pinout) -> mos::Pinout;
+
+    fn set_joypad1_state(&mut self, controller: JoypadInput);
+    fn set_joypad2_state(&mut self, controller: JoypadInput);
+    // called once per frame after the last cpu cycle
+    fn end_frame(&mut self) {}
+}
+
+impl ControllerPorts for NesControllers {
+    fn set_joypad1_state(&mut self, controller: JoypadInput) {
+        self.joypad1 = controller;
     }
 
-    pub fn clear_joypads_state(&mut self) {
-        self.joypad1.clear();
-        self.joypad2.clear();
+    fn set_joypad2_state(&mut self, controller: JoypadInput) {
+        self.joypad2 = controller;
     }
 
-    pub fn write_4016(&mut self, pinout: mos::Pinout) -> mos::Pinout {
+    fn write_4016(&mut self, pinout: mos::Pinout) -> mos::Pinout {
         if (pinout.data & 0x01) == 1 && self.polling == false {
             self.polling = true;
             self.shift1_count = 8;
@@ -79,7 +95,7 @@ impl NesControllers {
         pinout
     }
 
-    pub fn read_4016(&mut self, mut pinout: mos::Pinout) ->  mos::Pinout {
+    fn read_4016(&mut self, mut pinout: mos::Pinout) ->  mos::Pinout {
         if self.polling == true {
             pinout.data = (pinout.data & NESS001_MASK) | (self.joypad1_latch & 0x1);
         }
@@ -97,7 +113,7 @@ impl NesControllers {
         pinout
     }
 
-    pub fn read_4017(&mut self, mut pinout: mos::Pinout) ->  mos::Pinout {
+    fn read_4017(&mut self, mut pinout: mos::Pinout) ->  mos::Pinout {
         if self.polling == true {
             pinout.data = (pinout.data & NESS001_MASK) | (self.joypad2_latch & 0x1);
         }
@@ -116,6 +132,114 @@ impl NesControllers {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoinSlot {
+    Left,
+    Right,
+}
+
+/*
+    Vs. System controller ports
+    $4016 read:
+        bit 0 - serial joypad data
+        bit 2 - service button
+        bit 3,4 - dip switches 1,2
+        bit 5,6 - coin slots 1,2
+        bit 7 - 0 for primary cpu
+    $4017 read:
+        bit 0 - serial joypad data
+        bit 2..7 - dip switches 3..8
+    Start and select are wired to the opposite bits of a standard joypad, and some games
+    read player 1 from $4017.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct VsControllers {
+    joypads: NesControllers,
+    dip_switches: u8,
+    coin_frames: [u8; 2],
+    service: bool,
+    swap_joypads: bool,
+}
+
+impl VsControllers {
+    pub fn from_power_on() -> Self {
+        VsControllers {
+            joypads: NesControllers::from_power_on(),
+            dip_switches: 0,
+            coin_frames: [0; 2],
+            service: false,
+            swap_joypads: false,
+        }
+    }
+
+    pub fn set_swap_joypads(&mut self, swap: bool) {
+        self.swap_joypads = swap;
+    }
+
+    // bit 0 is dip switch 1
+    pub fn set_dip_switches(&mut self, dip_switches: u8) {
+        self.dip_switches = dip_switches;
+    }
+
+    pub fn dip_switches(&self) -> u8 {
+        self.dip_switches
+    }
+
+    pub fn set_service_button(&mut self, pressed: bool) {
+        self.service = pressed;
+    }
+
+    pub fn insert_coin(&mut self, slot: CoinSlot) {
+        match slot {
+            CoinSlot::Left => { self.coin_frames[0] = COIN_PULSE_FRAMES; }
+            CoinSlot::Right => { self.coin_frames[1] = COIN_PULSE_FRAMES; }
+        }
+    }
+}
+
+impl ControllerPorts for VsControllers {
+    fn set_joypad1_state(&mut self, controller: JoypadInput) {
+        self.joypads.set_joypad1_state(swap_start_select(controller));
+    }
+
+    fn set_joypad2_state(&mut self, controller: JoypadInput) {
+        self.joypads.set_joypad2_state(swap_start_select(controller));
+    }
+
+    // releases the coin switches
+    fn end_frame(&mut self) {
+        self.coin_frames[0] = self.coin_frames[0].saturating_sub(1);
+        self.coin_frames[1] = self.coin_frames[1].saturating_sub(1);
+    }
+
+    fn write_4016(&mut self, pinout: mos::Pinout) -> mos::Pinout {
+        self.joypads.write_4016(pinout)
+    }
+
+    fn read_4016(&mut self, mut pinout: mos::Pinout) -> mos::Pinout {
+        pinout = if self.swap_joypads { self.joypads.read_4017(pinout) } else { self.joypads.read_4016(pinout) };
+        pinout.data &= 0x01;
+        if self.service { pinout.data |= 0x04; }
+        pinout.data |= (self.dip_switches & 0x03) << 3;
+        if self.coin_frames[0] > 0 { pinout.data |= 0x20; }
+        if self.coin_frames[1] > 0 { pinout.data |= 0x40; }
+        pinout
+    }
+
+    fn read_4017(&mut self, mut pinout: mos::Pinout) -> mos::Pinout {
+        pinout = if self.swap_joypads { self.joypads.read_4016(pinout) } else { self.joypads.read_4017(pinout) };
+        pinout.data = (pinout.data & 0x01) | (self.dip_switches & 0xFC);
+        pinout
+    }
+}
+
+fn swap_start_select(controller: JoypadInput) -> JoypadInput {
+    let mut swapped = controller;
+    swapped.set(JoypadInput::START, controller.contains(JoypadInput::SELECT));
+    swapped.set(JoypadInput::SELECT, controller.contains(JoypadInput::START));
+    swapped
+}
+
 #[cfg(test)]
 mod test {
     use super::*; 
@@ -167,5 +291,41 @@ mod test {
         assert_eq!(pinout.data, 0b10100000 | 1);
        
     }
-}
 
+    #[test]
+    fn test_vs_system_ports() {
+        let mut ct = VsControllers::from_power_on();
+        let mut pinout = mos::Pinout::new();
+
+        ct.set_dip_switches(0b1010_0110);
+        ct.insert_coin(CoinSlot::Right);
+        ct.set_service_button(true);
+
+        pinout.data = 0xFF;
+        pinout = ct.read_4016(pinout);
+        assert_eq!(pinout.data, 0x40 | (0b10 << 3) | 0x04 | 0x1);
+        pinout = ct.read_4017(pinout);
+        assert_eq!(pinout.data, 0b1010_0100 | 0x1);
+
+        // coin is released after a few frames
+        for _ in 0..COIN_PULSE_FRAMES { ct.end_frame(); }
+        ct.set_service_button(false);
+        pinout = ct.read_4016(pinout);
+        assert_eq!(pinout.data, (0b10 << 3) | 0x1);
+
+        // start is read in the select position
+        let mut p1 = JoypadInput::new();
+        p1.set(JoypadInput::START, true);
+        ct.set_joypad1_state(p1);
+        pinout.data = 1;
+        pinout = ct.write_4016(pinout);
+        pinout.data = 0;
+        pinout = ct.write_4016(pinout);
+        let mut bits = 0;
+        for i in 0..8 {
+            pinout = ct.read_4016(pinout);
+            bits |= (pinout.data & 0x01) << i;
+        }
+        assert_eq!(bits, JoypadInput::SELECT.bits());
+    }
+}
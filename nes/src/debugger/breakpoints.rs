@@ -0,0 +1,359 @@
+use super::expression::{Expression, EvalContext};
+use mos::{Pinout, Ctrl};
+use crate::ppu;
+
+bitflags! {
+    pub struct BreakOn: u8 {
+        const EXECUTE =   0b00000001;   // opcode fetch
+        const READ =      0b00000010;
+        const WRITE =     0b00000100;
+        const PPU_READ =  0b00001000;   // ppu bus read, address is the vram address
+        const PPU_WRITE = 0b00010000;   // ppu bus write, address is the vram address
+    }
+}
+
+// the first breakpoint hit, kind is a single flag
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BreakpointHit {
+    pub id: u32,
+    pub kind: BreakOn,
+    pub address: u16,
+    pub value: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breakpoint {
+    pub on: BreakOn,
+    pub start: u16,
+    pub end: u16,
+    pub enabled: bool,
+    // counted whenever the address matches and the condition holds
+    pub hits: u64,
+    condition: Option<Expression>,
+}
+
+impl Breakpoint {
+    pub fn condition(&self) -> Option<&str> {
+        self.condition.as_ref().map(|c| c.source())
+    }
+
+    fn matches(&self, kind: BreakOn, address: u16) -> bool {
+        self.enabled && self.on.intersects(kind) && self.start <= address && address <= self.end
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Access {
+    kind: BreakOn,
+    address: u16,
+    value: u8,
+}
+
+/*
+    The cpu bus records accesses that match a breakpoint address, conditions are checked after
+    the cpu cycle when the registers and memory can be read. VRAM watchpoints see every chr and
+    nametable access the ppu makes, rendering fetches and the delayed $2007 accesses alike, with
+    the byte on the ppu data bus. Palette ram sits inside the ppu, so palette watchpoints are taken
+    from the $2007 accesses of the cpu instead.
+*/
+pub struct Breakpoints {
+    breakpoints: Vec<(u32, Breakpoint)>,
+    next_id: u32,
+    pending: Vec<Access>,
+}
+
+impl Breakpoints {
+    pub fn new() -> Breakpoints {
+        Breakpoints {
+            breakpoints: Vec::new(),
+            next_id: 1,
+            pending: Vec::new(),
+        }
+    }
+
+    // range is inclusive, returns the id of the new breakpoint
+    pub fn add(&mut self, on: BreakOn, start: u16, end: u16, condition: Option<Expression>) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.breakpoints.push((id, Breakpoint {
+            on,
+            start: start.min(end),
+            end: start.max(end),
+            enabled: true,
+            hits: 0,
+            condition,
+        }));
+
+        id
+    }
+
+    pub fn remove(&mut self, id: u32) -> bool {
+        let len = self.breakpoints.len();
+        self.breakpoints.retain(|(i, _)| *i != id);
+        self.breakpoints.len() != len
+    }
+
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+        self.pending.clear();
+    }
+
+    pub fn set_enabled(&mut self, id: u32, enabled: bool) -> bool {
+        match self.get_mut(id) {
+            Some(bp) => { bp.enabled = enabled; true }
+            None => false,
+        }
+    }
+
+    pub fn set_condition(&mut self, id: u32, condition: Option<Expression>) -> bool {
+        match self.get_mut(id) {
+            Some(bp) => { bp.condition = condition; true }
+            None => false,
+        }
+    }
+
+    pub fn reset_hit_counts(&mut self) {
+        for (_, bp) in self.breakpoints.iter_mut() {
+            bp.hits = 0;
+        }
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Breakpoint> {
+        self.breakpoints.iter().find(|(i, _)| *i == id).map(|(_, bp)| bp)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &Breakpoint)> {
+        self.breakpoints.iter().map(|(id, bp)| (*id, bp))
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.breakpoints.iter().any(|(_, bp)| bp.enabled)
+    }
+
+    fn get_mut(&mut self, id: u32) -> Option<&mut Breakpoint> {
+        self.breakpoints.iter_mut().find(|(i, _)| *i == id).map(|(_, bp)| bp)
+    }
+
+    fn record(&mut self, kind: BreakOn, address: u16, value: u8) {
+        if self.breakpoints.iter().any(|(_, bp)| bp.matches(kind, address)) {
+            self.pending.push(Access { kind, address, value });
+        }
+    }
+
+    // vram_address is the ppu address before the access, only used for $2007 palette accesses
+    pub(crate) fn cpu_read(&mut self, pinout: Pinout, vram_address: u16) {
+        let kind = if pinout.ctrl.contains(Ctrl::SYNC) { BreakOn::EXECUTE } else { BreakOn::READ };
+        self.record(kind, pinout.address, pinout.data);
+        if is_palette_access(pinout, vram_address) {
+            self.record(BreakOn::PPU_READ, vram_address, pinout.data);
+        }
+    }
+
+    pub(crate) fn cpu_write(&mut self, pinout: Pinout, vram_address: u16) {
+        self.record(BreakOn::WRITE, pinout.address, pinout.data);
+        if is_palette_access(pinout, vram_address) {
+            self.record(BreakOn::PPU_WRITE, vram_address, pinout.data);
+        }
+    }
+
+    // $3F00-$3FFF reads also fill the read buffer from the nametable underneath, that one isn't a palette access
+    pub(crate) fn ppu_read(&mut self, pinout: ppu::Pinout) {
+        if pinout.address < 0x3F00 {
+            self.record(BreakOn::PPU_READ, pinout.address, pinout.data);
+        }
+    }
+
+    pub(crate) fn ppu_write(&mut self, pinout: ppu::Pinout) {
+        self.record(BreakOn::PPU_WRITE, pinout.address, pinout.data);
+    }
+
+    pub(crate) fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    // counts the hits of the recorded accesses and returns the first one
+    pub(crate) fn resolve<F: FnMut(u16) -> u8>(&mut self, mut context: EvalContext, mut peek: F) -> Option<BreakpointHit> {
+        let mut hit = None;
+        for access in self.pending.drain(..) {
+            context.address = access.address;
+            context.value = access.value;
+            for (id, bp) in self.breakpoints.iter_mut() {
+                if !bp.matches(access.kind, access.address) {
+                    continue;
+                }
+                if let Some(condition) = &bp.condition {
+                    if !condition.is_true(&context, &mut peek) {
+                        continue;
+                    }
+                }
+
+                bp.hits += 1;
+                if hit.is_none() {
+                    hit = Some(BreakpointHit { id: *id, kind: access.kind, address: access.address, value: access.value });
+                }
+            }
+        }
+
+        hit
+    }
+}
+
+impl Default for Breakpoints {
+    fn default() -> Self {
+        Breakpoints::new()
+    }
+}
+
+fn is_palette_access(pinout: Pinout, vram_address: u16) -> bool {
+    (0x2000..=0x3FFF).contains(&pinout.address) && pinout.address & 0x07 == 7 && vram_address >= 0x3F00
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::consoles::{Console, EmuError};
+    use crate::consoles::nes_ntsc::NesNtsc;
+    use crate::debugger::memory::MemoryType;
+    use std::path::Path;
+
+    fn pinout(address: u16, data: u8, sync: bool) -> Pinout {
+        let mut pinout = Pinout::new();
+        pinout.address = address;
+        pinout.data = data;
+        if sync { pinout.ctrl.insert(Ctrl::SYNC); }
+        pinout
+    }
+
+    #[test]
+    fn test_conditions_and_hits() {
+        let mut breakpoints = Breakpoints::new();
+        let write = breakpoints.add(BreakOn::WRITE, 0x00FF, 0x0000, Expression::parse("VALUE > 3 && A == #$10").ok());
+        let exec = breakpoints.add(BreakOn::EXECUTE, 0xC000, 0xC000, None);
+        assert_eq!(breakpoints.get(write).unwrap().start, 0x0000);
+
+        let context = EvalContext { a: 0x10, ..Default::default() };
+        breakpoints.cpu_write(pinout(0x0010, 2, false), 0);
+        assert_eq!(breakpoints.resolve(context, |_| 0), None);
+
+        breakpoints.cpu_write(pinout(0x0010, 4, false), 0);
+        // an opcode fetch isn't a read
+        breakpoints.cpu_read(pinout(0x0010, 0, true), 0);
+        breakpoints.cpu_read(pinout(0xC000, 0, false), 0);
+        let hit = breakpoints.resolve(context, |_| 0).unwrap();
+        assert_eq!((hit.id, hit.kind, hit.address, hit.value), (write, BreakOn::WRITE, 0x0010, 4));
+
+        breakpoints.cpu_read(pinout(0xC000, 0xA9, true), 0);
+        assert_eq!(breakpoints.resolve(context, |_| 0).map(|h| h.id), Some(exec));
+        assert_eq!(breakpoints.get(write).unwrap().hits, 1);
+        assert_eq!(breakpoints.get(exec).unwrap().hits, 1);
+
+        breakpoints.set_enabled(exec, false);
+        breakpoints.cpu_read(pinout(0xC000, 0xA9, true), 0);
+        assert_eq!(breakpoints.resolve(context, |_| 0), None);
+        assert!(breakpoints.remove(exec));
+        assert!(!breakpoints.set_condition(exec, None));
+    }
+
+    #[test]
+    fn test_vram_watchpoints() {
+        let mut breakpoints = Breakpoints::new();
+        let id = breakpoints.add(BreakOn::PPU_WRITE, 0x2000, 0x23FF, Expression::parse("[$00] == 1").ok());
+        let palette = breakpoints.add(BreakOn::PPU_WRITE | BreakOn::PPU_READ, 0x3F00, 0x3F1F, None);
+
+        // the cpu side of $2007 only counts for palette ram, the vram write happens later on the ppu bus
+        breakpoints.cpu_write(pinout(0x2007, 0x55, false), 0x2010);
+        assert_eq!(breakpoints.resolve(EvalContext::default(), |_| 1), None);
+
+        let mut ppu_pinout = ppu::Pinout::new();
+        ppu_pinout.address = 0x2010;
+        ppu_pinout.data = 0x55;
+        breakpoints.ppu_write(ppu_pinout);
+        assert_eq!(breakpoints.resolve(EvalContext::default(), |_| 0), None);
+        breakpoints.ppu_write(ppu_pinout);
+        let hit = breakpoints.resolve(EvalContext::default(), |_| 1).unwrap();
+        assert_eq!((hit.id, hit.kind, hit.address, hit.value), (id, BreakOn::PPU_WRITE, 0x2010, 0x55));
+
+        // the read buffer fill from under the palette isn't a palette read
+        ppu_pinout.address = 0x3F01;
+        breakpoints.ppu_read(ppu_pinout);
+        assert_eq!(breakpoints.resolve(EvalContext::default(), |_| 0), None);
+        breakpoints.cpu_read(pinout(0x2007, 0x0F, false), 0x3F01);
+        let hit = breakpoints.resolve(EvalContext::default(), |_| 0).unwrap();
+        assert_eq!((hit.id, hit.kind, hit.address, hit.value), (palette, BreakOn::PPU_READ, 0x3F01, 0x0F));
+    }
+
+    #[test]
+    fn test_rendering_fetches_hit_vram_watchpoints() {
+        let test_roms = Path::new(env!("CARGO_MANIFEST_DIR")).join("../rustnes-dev/test_roms");
+        let mut nes = NesNtsc::new();
+        nes.load_rom(test_roms.join("nestest.nes"));
+        nes.power_on_console();
+
+        // the cpu never reads the pattern tables, only the background and sprite fetches do
+        let id = nes.breakpoints().add(BreakOn::PPU_READ, 0x0000, 0x1FFF, None);
+        let mut hit = None;
+        for _ in 0..10 {
+            if let Err(EmuError::BreakpointHit(h)) = nes.execute_frame() {
+                hit = Some(h);
+                break;
+            }
+        }
+
+        let hit = hit.expect("pattern table watchpoint not hit");
+        assert_eq!(hit.id, id);
+        // nrom has a single unbanked chr rom
+        assert_eq!(Some(hit.value), nes.peek_memory(MemoryType::Chr, hit.address as usize));
+    }
+
+    #[test]
+    fn test_execute_frame_stops() {
+        let test_roms = Path::new(env!("CARGO_MANIFEST_DIR")).join("../rustnes-dev/test_roms");
+        let mut nes = NesNtsc::new();
+        nes.load_rom(test_roms.join("nestest.nes"));
+        nes.power_on_console_at(0xC000);
+
+        // C5F5 LDX #$00, C5F7 STX $00, C5F9 STX $10
+        let condition = nes.parse_expression("X == 0").ok();
+        let exec = nes.breakpoints().add(BreakOn::EXECUTE, 0xC5F7, 0xC5F7, condition);
+        let write = nes.breakpoints().add(BreakOn::WRITE, 0x0010, 0x0011, None);
+
+        match nes.execute_frame() {
+            Err(EmuError::BreakpointHit(hit)) => assert_eq!((hit.id, hit.address), (exec, 0xC5F7)),
+            _ => panic!("execute breakpoint not hit"),
+        }
+        assert_eq!(nes.get_cpu_pc(), 0xC5F7);
+
+        match nes.execute_frame() {
+            Err(EmuError::BreakpointHit(hit)) => assert_eq!((hit.id, hit.kind, hit.address), (write, BreakOn::WRITE, 0x0010)),
+            _ => panic!("write breakpoint not hit"),
+        }
+        match nes.execute_frame() {
+            Err(EmuError::BreakpointHit(hit)) => assert_eq!(hit.address, 0x0011),
+            _ => panic!("write breakpoint not hit"),
+        }
+        assert_eq!(nes.breakpoints().get(write).unwrap().hits, 2);
+    }
+
+    #[test]
+    fn test_hit_on_the_last_frame_cycle_ends_the_frame() {
+        let test_roms = Path::new(env!("CARGO_MANIFEST_DIR")).join("../rustnes-dev/test_roms");
+        let mut nes = NesNtsc::new();
+        nes.load_rom(test_roms.join("nestest.nes"));
+        nes.power_on_console();
+
+        // every cpu cycle hits, so the frame ends on a call that stops on a breakpoint
+        nes.breakpoints().add(BreakOn::READ | BreakOn::WRITE, 0x0000, 0xFFFF, None);
+        nes.cheats().add("0010:77", "").unwrap();
+        let frame = nes.get_frame_number();
+        while nes.get_frame_number() == frame {
+            nes.poke_memory(MemoryType::CpuRam, 0x0010, 0x00);
+            assert!(matches!(nes.execute_frame(), Err(EmuError::BreakpointHit(_))));
+        }
+
+        // ram is frozen on the last cycle and the next call runs exactly one frame
+        assert_eq!(nes.peek_memory(MemoryType::CpuRam, 0x0010), Some(0x77));
+        nes.breakpoints().clear();
+        nes.execute_frame().unwrap();
+        assert_eq!(nes.get_frame_number(), frame + 2);
+    }
+}
@@ -0,0 +1,236 @@
+use std::io::{Read, Write};
+use mos::{Pinout, Ctrl};
+use mos::disassembler::{OPCODES, Mode, instruction_length};
+use crate::consoles::EmuError;
+use crate::mappers::Mapper;
+use super::disassembler::prg_bank;
+
+const JMP_INDIRECT: u8 = 0x6C;
+
+bitflags! {
+    // bits 2-3 are the cpu bank the byte was last read through, $8000 $A000 $C000 $E000
+    pub struct PrgFlags: u8 {
+        const CODE =          0b00000001;
+        const DATA =          0b00000010;
+        const BANK =          0b00001100;
+        const INDIRECT_CODE = 0b00010000;   // JMP ($nnnn) target
+        const INDIRECT_DATA = 0b00100000;   // read through ($nn,X) or ($nn),Y
+        const PCM =           0b01000000;   // dmc sample
+    }
+}
+
+bitflags! {
+    pub struct ChrFlags: u8 {
+        const RENDERED = 0b00000001;
+        const READ =     0b00000010;   // $2007
+    }
+}
+
+/*
+    Code/Data Logger, one flag byte per ROM byte in the FCEUX .cdl layout: PRG-ROM flags then CHR-ROM flags.
+    Operand bytes are code, reads that aren't part of the current instruction are data. The dummy read
+    of the byte after a one or two byte instruction isn't logged. CHR-RAM carts have no CHR flags.
+*/
+pub struct CodeDataLogger {
+    prg: Vec<u8>,
+    chr: Vec<u8>,
+    opcode_address: u16,
+    opcode: u8,
+    mode: Mode,
+    indirect_jump: bool,
+}
+
+impl CodeDataLogger {
+    pub fn new(prg_size: usize, chr_size: usize) -> CodeDataLogger {
+        CodeDataLogger {
+            prg: vec![0; prg_size],
+            chr: vec![0; chr_size],
+            opcode_address: 0,
+            opcode: 0,
+            mode: Mode::Imp,
+            indirect_jump: false,
+        }
+    }
+
+    pub fn prg(&self) -> &[u8] {
+        &self.prg
+    }
+
+    pub fn chr(&self) -> &[u8] {
+        &self.chr
+    }
+
+    pub fn prg_flags(&self, offset: usize) -> PrgFlags {
+        PrgFlags::from_bits_truncate(self.prg.get(offset).copied().unwrap_or(0))
+    }
+
+    pub fn chr_flags(&self, offset: usize) -> ChrFlags {
+        ChrFlags::from_bits_truncate(self.chr.get(offset).copied().unwrap_or(0))
+    }
+
+    pub fn clear(&mut self) {
+        self.prg.iter_mut().for_each(|f| *f = 0);
+        self.chr.iter_mut().for_each(|f| *f = 0);
+    }
+
+    pub fn save<W: Write>(&self, w: &mut W) -> Result<(), EmuError> {
+        w.write_all(&self.prg).map_err(|_| EmuError::CdlError)?;
+        w.write_all(&self.chr).map_err(|_| EmuError::CdlError)
+    }
+
+    // the file has to match the rom size, flags are merged with the ones logged so far
+    pub fn load<R: Read>(&mut self, r: &mut R) -> Result<(), EmuError> {
+        let mut data = Vec::new();
+        r.read_to_end(&mut data).map_err(|_| EmuError::CdlError)?;
+        if data.len() != self.prg.len() + self.chr.len() {
+            return Err(EmuError::CdlError);
+        }
+
+        let (prg, chr) = data.split_at(self.prg.len());
+        self.prg.iter_mut().zip(prg).for_each(|(f, l)| *f |= l);
+        self.chr.iter_mut().zip(chr).for_each(|(f, l)| *f |= l);
+        Ok(())
+    }
+
+    // every cpu read, the opcode fetches outside of PRG-ROM are needed to know the current instruction
+    pub(crate) fn cpu_read(&mut self, mapper: &dyn Mapper, pinout: Pinout) {
+        let sync = pinout.ctrl.contains(Ctrl::SYNC);
+        if sync {
+            self.indirect_jump = self.opcode == JMP_INDIRECT;
+            self.opcode_address = pinout.address;
+            self.opcode = pinout.data;
+            self.mode = OPCODES[pinout.data as usize].1;
+        }
+
+        let offset = match prg_bank(mapper, pinout.address) {
+            Some(bank) if bank.rom_offset < self.prg.len() => bank.rom_offset,
+            _ => return,
+        };
+
+        let distance = pinout.address.wrapping_sub(self.opcode_address) as usize;
+        let flags = if sync || distance < instruction_length(self.opcode) {
+            if self.indirect_jump { PrgFlags::CODE | PrgFlags::INDIRECT_CODE } else { PrgFlags::CODE }
+        }
+        else if distance < 3 {
+            // dummy read of the next opcode
+            return;
+        }
+        else {
+            match self.mode {
+                Mode::Izx | Mode::Izy => PrgFlags::DATA | PrgFlags::INDIRECT_DATA,
+                _ => PrgFlags::DATA,
+            }
+        };
+
+        self.mark_prg(offset, pinout.address, flags);
+    }
+
+    pub(crate) fn dmc_read(&mut self, mapper: &dyn Mapper, address: u16) {
+        if let Some(bank) = prg_bank(mapper, address) {
+            if bank.rom_offset < self.prg.len() {
+                self.mark_prg(bank.rom_offset, address, PrgFlags::DATA | PrgFlags::PCM);
+            }
+        }
+    }
+
+    pub(crate) fn chr_read(&mut self, mapper: &dyn Mapper, address: u16, flags: ChrFlags) {
+        if address >= 0x2000 || self.chr.is_empty() {
+            return;
+        }
+
+        let (size, index) = mapper.get_context().chr_addr_mapper.address_bank_details(address);
+        let offset = index * size + (address as usize & (size - 1));
+        if let Some(f) = self.chr.get_mut(offset) {
+            *f |= flags.bits();
+        }
+    }
+
+    fn mark_prg(&mut self, offset: usize, address: u16, flags: PrgFlags) {
+        let bank = (((address >> 13) & 0x03) << 2) as u8;
+        let f = &mut self.prg[offset];
+        *f = (*f & !PrgFlags::BANK.bits()) | flags.bits() | bank;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mappers::mapper_debug::MapperDebug;
+    use crate::consoles::Console;
+    use crate::consoles::nes_ntsc::NesNtsc;
+    use std::path::Path;
+
+    fn read(cdl: &mut CodeDataLogger, mapper: &MapperDebug, address: u16, data: u8, sync: bool) {
+        let mut pinout = Pinout::new();
+        pinout.address = address;
+        pinout.data = data;
+        pinout.ctrl.set(Ctrl::SYNC, sync);
+        cdl.cpu_read(mapper, pinout);
+    }
+
+    #[test]
+    fn test_prg_flags() {
+        // 16K of prg mirrored at $8000 and $C000
+        let mapper = MapperDebug::new();
+        let mut cdl = CodeDataLogger::new(0x4000, 0x2000);
+
+        // lda ($10),y at $C000 reading $8100, then clc and its dummy read
+        read(&mut cdl, &mapper, 0xC000, 0xB1, true);
+        read(&mut cdl, &mapper, 0xC001, 0x10, false);
+        read(&mut cdl, &mapper, 0x8100, 0x00, false);
+        read(&mut cdl, &mapper, 0xC002, 0x18, true);
+        read(&mut cdl, &mapper, 0xC003, 0x00, false);
+        // jmp ($0200) to $E000
+        read(&mut cdl, &mapper, 0xC003, 0x6C, true);
+        read(&mut cdl, &mapper, 0xE000, 0xEA, true);
+
+        assert_eq!(cdl.prg_flags(0x0000), PrgFlags::CODE | PrgFlags::from_bits_truncate(0x08));
+        assert_eq!(cdl.prg_flags(0x0001), PrgFlags::CODE | PrgFlags::from_bits_truncate(0x08));
+        assert_eq!(cdl.prg_flags(0x0100), PrgFlags::DATA | PrgFlags::INDIRECT_DATA);
+        assert_eq!(cdl.prg_flags(0x0003), PrgFlags::CODE | PrgFlags::from_bits_truncate(0x08));
+        assert_eq!(cdl.prg[0x2000], 0x01 | 0x10 | 0x0C);
+
+        cdl.dmc_read(&mapper, 0xC004);
+        assert_eq!(cdl.prg_flags(0x0004), PrgFlags::DATA | PrgFlags::PCM | PrgFlags::from_bits_truncate(0x08));
+
+        cdl.chr_read(&mapper, 0x1010, ChrFlags::RENDERED);
+        cdl.chr_read(&mapper, 0x1010, ChrFlags::READ);
+        assert_eq!(cdl.chr_flags(0x1010), ChrFlags::RENDERED | ChrFlags::READ);
+    }
+
+    #[test]
+    fn test_save_load() {
+        let mapper = MapperDebug::new();
+        let mut cdl = CodeDataLogger::new(0x4000, 0x2000);
+        read(&mut cdl, &mapper, 0x8000, 0xEA, true);
+
+        let mut file = Vec::new();
+        cdl.save(&mut file).unwrap();
+        assert_eq!(file.len(), 0x6000);
+        assert_eq!(file[0], 0x01);
+
+        let mut loaded = CodeDataLogger::new(0x4000, 0x2000);
+        loaded.load(&mut &file[..]).unwrap();
+        assert_eq!(loaded.prg(), cdl.prg());
+        assert!(CodeDataLogger::new(0x8000, 0).load(&mut &file[..]).is_err());
+    }
+
+    #[test]
+    fn test_nestest_cdl() {
+        let test_roms = Path::new(env!("CARGO_MANIFEST_DIR")).join("../rustnes-dev/test_roms");
+        let mut nes = NesNtsc::new();
+        nes.load_rom(test_roms.join("nestest.nes"));
+        nes.power_on_console_at(0xC000);
+        nes.start_cdl();
+        for _ in 0..4 {
+            nes.step_instruction().unwrap();
+        }
+
+        // JMP $C5F5, LDX #$00, STX $00
+        let cdl = nes.get_cdl().unwrap();
+        assert_eq!(cdl.prg()[0x0000..0x0003], [0x09, 0x09, 0x09]);
+        assert_eq!(cdl.prg()[0x05F5..0x05F9], [0x09, 0x09, 0x09, 0x09]);
+        assert_eq!(cdl.prg()[0x0003], 0x00);
+        assert_eq!(cdl.chr().len(), 0x2000);
+    }
+}
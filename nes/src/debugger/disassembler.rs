@@ -0,0 +1,146 @@
+use std::fmt;
+use mos::disassembler::Disassembly;
+use crate::mappers::{self, Mapper};
+use super::symbols::{SymbolTable, SourceLine};
+
+// where an address in $8000-$FFFF is currently mapped in PRG-ROM
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrgBank {
+    pub index: usize,
+    pub size: usize,
+    // offset into PRG-ROM, the file offset without the ines header
+    pub rom_offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisassemblyLine {
+    pub instruction: Disassembly,
+    // None outside of PRG-ROM
+    pub bank: Option<PrgBank>,
+    pub label: Option<String>,
+    pub operand_label: Option<String>,
+    pub source: Option<SourceLine>,
+}
+
+/*
+    e.g. "03:C5F5  A2 00     LDX #$00", ram and registers show "--" as the bank
+    A label goes on its own line before the instruction, the source line is added as a comment.
+*/
+impl fmt::Display for DisassemblyLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(label) = &self.label {
+            writeln!(f, "{}:", label)?;
+        }
+
+        let bank = match self.bank {
+            Some(bank) => format!("{:02X}", bank.index),
+            None => "--".to_string(),
+        };
+        let hex = self.instruction.bytes().iter().map(|b| format!("{:02X}", b)).collect::<Vec<String>>().join(" ");
+        let unofficial = if self.instruction.unofficial { '*' } else { ' ' };
+        let text = match &self.operand_label {
+            Some(label) => format!("{} {}", self.instruction.mnemonic, self.instruction.operand_text_labeled(label)),
+            None => self.instruction.to_string(),
+        };
+
+        write!(f, "{}:{:04X}  {:<8} {}{}", bank, self.instruction.address, hex, unofficial, text)?;
+        if let Some(source) = &self.source {
+            write!(f, "  ; {}:{}", source.file, source.line)?;
+        }
+
+        Ok(())
+    }
+}
+
+// None below $8000 and before a rom is loaded
+pub fn prg_bank(mapper: &dyn Mapper, address: u16) -> Option<PrgBank> {
+    if address < 0x8000 || mapper.get_context().prg_rom.is_empty() {
+        return None;
+    }
+
+    let (size, index) = mapper.get_context().prg_addr_mapper.address_bank_details(address);
+    Some(PrgBank {
+        index,
+        size,
+        rom_offset: index * size + (address as usize & (size - 1)),
+    })
+}
+
+// first cpu address in $8000-$FFFF that is mapped to the PRG-ROM offset
+pub fn cpu_address(mapper: &dyn Mapper, prg_offset: usize) -> Option<u16> {
+    (0x8000..=0xFFFFu32).step_by(0x400).find_map(|page| {
+        let bank = prg_bank(mapper, page as u16)?;
+        match prg_offset.checked_sub(bank.rom_offset) {
+            Some(delta) if delta < 0x400 => Some((page as usize + delta) as u16),
+            _ => None,
+        }
+    })
+}
+
+pub fn label_at(mapper: &dyn Mapper, symbols: &SymbolTable, address: u16) -> Option<String> {
+    let offset = prg_bank(mapper, address).map(|bank| bank.rom_offset);
+    symbols.label(address, offset).map(|s| s.to_string())
+}
+
+pub fn disassemble(mapper: &mut dyn Mapper, symbols: &SymbolTable, address: u16, count: usize) -> Vec<DisassemblyLine> {
+    let instructions = mos::disassembler::disassemble_range(address, count, |a| mappers::peek_cpu(mapper, a));
+    instructions.into_iter().map(|instruction| {
+        let bank = prg_bank(mapper, instruction.address);
+        DisassemblyLine {
+            instruction,
+            bank,
+            label: label_at(mapper, symbols, instruction.address),
+            operand_label: instruction.operand_address().and_then(|a| label_at(mapper, symbols, a)),
+            source: bank.and_then(|bank| symbols.source_line(bank.rom_offset).cloned()),
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::consoles::Console;
+    use crate::consoles::nes_ntsc::NesNtsc;
+    use std::path::Path;
+
+    #[test]
+    fn test_disassemble_prg() {
+        let test_roms = Path::new(env!("CARGO_MANIFEST_DIR")).join("../rustnes-dev/test_roms");
+        let mut nes = NesNtsc::new();
+        // no cartridge reads open bus
+        assert_eq!(nes.disassemble(0xC5F5, 1)[0].bank, None);
+        nes.load_rom(test_roms.join("nestest.nes"));
+
+        let lines = nes.disassemble(0xC5F5, 3);
+        let text: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        assert_eq!(text, [
+            "00:C5F5  A2 00     LDX #$00",
+            "00:C5F7  86 00     STX $00",
+            "00:C5F9  86 10     STX $10",
+        ]);
+
+        // 16K nrom is mirrored into both halves
+        assert_eq!(lines[0].bank.unwrap().rom_offset, 0x05F5);
+        assert_eq!(nes.disassemble(0x85F5, 1)[0].bank, lines[0].bank);
+        assert_eq!(nes.disassemble(0x0200, 1)[0].bank, None);
+    }
+
+    #[test]
+    fn test_disassemble_symbols() {
+        let test_roms = Path::new(env!("CARGO_MANIFEST_DIR")).join("../rustnes-dev/test_roms");
+        let mut nes = NesNtsc::new();
+        nes.load_rom(test_roms.join("nestest.nes"));
+        nes.symbols().parse_mesen_mlb("P:05F5:start\nR:0010:temp\nP:072D:init\n");
+        nes.symbols().add_source_line(0x05F7, SourceLine { file: "nestest.s".to_string(), line: 7 });
+
+        let lines = nes.disassemble(0xC5F5, 6);
+        let text: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        assert_eq!(text[0], "start:\n00:C5F5  A2 00     LDX #$00");
+        assert_eq!(text[1], "00:C5F7  86 00     STX $00  ; nestest.s:7");
+        assert_eq!(text[2], "00:C5F9  86 10     STX temp");
+        assert_eq!(text[4], "00:C5FD  20 2D C7  JSR init");
+
+        // first of the two mirrors
+        assert_eq!(nes.resolve_symbol("init"), Some(0x872D));
+    }
+}
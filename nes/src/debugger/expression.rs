@@ -0,0 +1,344 @@
+use crate::consoles::EmuError;
+
+/*
+    Breakpoint conditions, e.g. "A == #$10 && [$00FF] > 3"
+        registers   A X Y SP P PC, VALUE and ADDR are the data and address of the access
+        labels      replaced by their cpu address when the expression is parsed
+        numbers     $FF hex, 255 decimal, the 6502 style # prefix is allowed
+        memory      [expr] reads a byte of cpu address space without side effects
+        operators   || && | ^ & == != < <= > >= + - and unary ! ~, C precedence
+    Comparisons and logical operators give 1 or 0, the condition holds when the result isn't 0.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct EvalContext {
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub sp: u8,
+    pub p: u8,
+    pub pc: u16,
+    pub address: u16,
+    pub value: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Register {
+    A,
+    X,
+    Y,
+    Sp,
+    P,
+    Pc,
+    Address,
+    Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Or,
+    And,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(i64),
+    Register(Register),
+    Memory(Box<Node>),
+    Not(Box<Node>),
+    Complement(Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(i64),
+    Ident(String),
+    Op(&'static str),
+}
+
+// longest first so "<=" isn't read as "<"
+const OPERATORS: [&str; 19] = ["&&", "||", "==", "!=", "<=", ">=", "<", ">", "&", "|", "^", "+", "-", "!", "~", "(", ")", "[", "]"];
+
+// lowest precedence first
+const BINARY_LEVELS: [&[(&str, BinaryOp)]; 8] = [
+    &[("||", BinaryOp::Or)],
+    &[("&&", BinaryOp::And)],
+    &[("|", BinaryOp::BitOr)],
+    &[("^", BinaryOp::BitXor)],
+    &[("&", BinaryOp::BitAnd)],
+    &[("==", BinaryOp::Eq), ("!=", BinaryOp::Ne)],
+    &[("<", BinaryOp::Lt), ("<=", BinaryOp::Le), (">", BinaryOp::Gt), (">=", BinaryOp::Ge)],
+    &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    source: String,
+    root: Node,
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Expression, EmuError> {
+        Expression::parse_with_symbols(source, |_| None)
+    }
+
+    // resolve returns the address of a label, registers take precedence over labels
+    pub fn parse_with_symbols<F: Fn(&str) -> Option<u16>>(source: &str, resolve: F) -> Result<Expression, EmuError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens: &tokens, pos: 0, resolve: &resolve };
+        let root = parser.binary(0)?;
+        if parser.pos != tokens.len() {
+            return Err(EmuError::InvalidExpression(format!("unexpected {:?} in \"{}\"", tokens[parser.pos], source)));
+        }
+
+        Ok(Expression { source: source.trim().to_string(), root })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn evaluate<F: FnMut(u16) -> u8>(&self, context: &EvalContext, mut peek: F) -> i64 {
+        evaluate(&self.root, context, &mut peek)
+    }
+
+    pub fn is_true<F: FnMut(u16) -> u8>(&self, context: &EvalContext, peek: F) -> bool {
+        self.evaluate(context, peek) != 0
+    }
+}
+
+fn evaluate<F: FnMut(u16) -> u8>(node: &Node, context: &EvalContext, peek: &mut F) -> i64 {
+    match node {
+        Node::Number(n) => *n,
+        Node::Register(r) => match r {
+            Register::A => context.a as i64,
+            Register::X => context.x as i64,
+            Register::Y => context.y as i64,
+            Register::Sp => context.sp as i64,
+            Register::P => context.p as i64,
+            Register::Pc => context.pc as i64,
+            Register::Address => context.address as i64,
+            Register::Value => context.value as i64,
+        },
+        Node::Memory(address) => {
+            let address = evaluate(address, context, peek);
+            peek(address as u16) as i64
+        }
+        Node::Not(n) => (evaluate(n, context, peek) == 0) as i64,
+        Node::Complement(n) => !evaluate(n, context, peek),
+        Node::Binary(BinaryOp::Or, l, r) => (evaluate(l, context, peek) != 0 || evaluate(r, context, peek) != 0) as i64,
+        Node::Binary(BinaryOp::And, l, r) => (evaluate(l, context, peek) != 0 && evaluate(r, context, peek) != 0) as i64,
+        Node::Binary(op, l, r) => {
+            let l = evaluate(l, context, peek);
+            let r = evaluate(r, context, peek);
+            match op {
+                BinaryOp::BitOr => l | r,
+                BinaryOp::BitXor => l ^ r,
+                BinaryOp::BitAnd => l & r,
+                BinaryOp::Eq => (l == r) as i64,
+                BinaryOp::Ne => (l != r) as i64,
+                BinaryOp::Lt => (l < r) as i64,
+                BinaryOp::Le => (l <= r) as i64,
+                BinaryOp::Gt => (l > r) as i64,
+                BinaryOp::Ge => (l >= r) as i64,
+                BinaryOp::Add => l.wrapping_add(r),
+                BinaryOp::Sub => l.wrapping_sub(r),
+                BinaryOp::Or | BinaryOp::And => unreachable!(),
+            }
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, EmuError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        }
+        else if c == '#' || c == '$' || c.is_ascii_digit() {
+            if c == '#' { i += 1; }
+            let radix = if i < chars.len() && chars[i] == '$' { i += 1; 16 } else { 10 };
+            let start = i;
+            while i < chars.len() && chars[i].is_digit(radix) { i += 1; }
+            let digits: String = chars[start..i].iter().collect();
+            match i64::from_str_radix(&digits, radix) {
+                Ok(n) => tokens.push(Token::Number(n)),
+                Err(_) => return Err(EmuError::InvalidExpression(format!("bad number at {} in \"{}\"", start, source))),
+            }
+        }
+        else if is_ident_char(c) {
+            let start = i;
+            while i < chars.len() && is_ident_char(chars[i]) { i += 1; }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        }
+        else {
+            let rest: String = chars[i..].iter().take(2).collect();
+            match OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+                Some(op) => {
+                    tokens.push(Token::Op(op));
+                    i += op.len();
+                }
+                None => return Err(EmuError::InvalidExpression(format!("unexpected '{}' at {} in \"{}\"", c, i, source))),
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+// ca65 cheap locals start with @ and scopes are joined with ::
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '@' || c == ':'
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    resolve: &'a dyn Fn(&str) -> Option<u16>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => Some(*op),
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), EmuError> {
+        if self.peek_op() == Some(op) {
+            self.pos += 1;
+            Ok(())
+        }
+        else {
+            Err(EmuError::InvalidExpression(format!("expected '{}'", op)))
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Node, EmuError> {
+        if level == BINARY_LEVELS.len() {
+            return self.unary();
+        }
+
+        let mut node = self.binary(level + 1)?;
+        while let Some(&(_, op)) = BINARY_LEVELS[level].iter().find(|(s, _)| Some(*s) == self.peek_op()) {
+            self.pos += 1;
+            let rhs = self.binary(level + 1)?;
+            node = Node::Binary(op, Box::new(node), Box::new(rhs));
+        }
+
+        Ok(node)
+    }
+
+    fn unary(&mut self) -> Result<Node, EmuError> {
+        match self.peek_op() {
+            Some("!") => { self.pos += 1; Ok(Node::Not(Box::new(self.unary()?))) }
+            Some("~") => { self.pos += 1; Ok(Node::Complement(Box::new(self.unary()?))) }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Node, EmuError> {
+        let token = match self.tokens.get(self.pos) {
+            Some(token) => token.clone(),
+            None => return Err(EmuError::InvalidExpression("unexpected end of expression".to_string())),
+        };
+        self.pos += 1;
+
+        match token {
+            Token::Number(n) => Ok(Node::Number(n)),
+            Token::Ident(name) => {
+                let register = match name.to_ascii_uppercase().as_str() {
+                    "A" => Register::A,
+                    "X" => Register::X,
+                    "Y" => Register::Y,
+                    "SP" | "S" => Register::Sp,
+                    "P" => Register::P,
+                    "PC" => Register::Pc,
+                    "ADDR" => Register::Address,
+                    "VALUE" => Register::Value,
+                    _ => match (self.resolve)(&name) {
+                        Some(address) => return Ok(Node::Number(address as i64)),
+                        None => return Err(EmuError::InvalidExpression(format!("unknown register or label {}", name))),
+                    },
+                };
+                Ok(Node::Register(register))
+            }
+            Token::Op("(") => {
+                let node = self.binary(0)?;
+                self.expect(")")?;
+                Ok(node)
+            }
+            Token::Op("[") => {
+                let node = self.binary(0)?;
+                self.expect("]")?;
+                Ok(Node::Memory(Box::new(node)))
+            }
+            Token::Op(op) => Err(EmuError::InvalidExpression(format!("unexpected '{}'", op))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn eval(source: &str, context: &EvalContext) -> i64 {
+        let mut ram = [0u8; 0x800];
+        ram[0xFF] = 5;
+        ram[0x10] = 0xFF;
+        Expression::parse(source).unwrap().evaluate(context, |address| ram[address as usize & 0x7FF])
+    }
+
+    #[test]
+    fn test_conditions() {
+        let context = EvalContext { a: 0x10, x: 2, pc: 0xC000, ..Default::default() };
+        assert_eq!(eval("A == #$10 && [$00FF] > 3", &context), 1);
+        assert_eq!(eval("a == 16 && [$00FF] > 5", &context), 0);
+        assert_eq!(eval("[$10 + X - 2] == $FF", &context), 1);
+        assert_eq!(eval("PC >= $C000 || !X", &context), 1);
+        assert_eq!(eval("A & $F0 | 1", &context), 0x11);
+        assert_eq!(eval("(A + X) - 2", &context), 0x10);
+    }
+
+    #[test]
+    fn test_precedence() {
+        let context = EvalContext::default();
+        assert_eq!(eval("1 + 2 == 3", &context), 1);
+        assert_eq!(eval("2 == 2 < 3", &context), 0);
+        assert_eq!(eval("0 || 1 && 0", &context), 0);
+        assert_eq!(eval("1 | 2 ^ 3 & 1", &context), 3);
+        assert_eq!(eval("~0 & $FF", &context), 0xFF);
+    }
+
+    #[test]
+    fn test_labels() {
+        let resolve = |name: &str| if name == "player_x" { Some(0x10) } else { None };
+        let expression = Expression::parse_with_symbols("[player_x] == $FF && a == 0", resolve).unwrap();
+        assert!(expression.is_true(&EvalContext::default(), |address| if address == 0x10 { 0xFF } else { 0 }));
+        assert!(Expression::parse_with_symbols("[player_y] == 0", resolve).is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for source in ["", "A ==", "B == 1", "[$10", "A = 1", "$G"].iter() {
+            assert!(Expression::parse(source).is_err(), "{}", source);
+        }
+    }
+}
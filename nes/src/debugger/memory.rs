@@ -0,0 +1,168 @@
+use crate::mappers::{self, Mapper};
+use crate::ppu::rp2c02::Rp2c02;
+use super::disassembler::prg_bank;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemoryType {
+    // $0000-$FFFF as the cpu sees it, registers and open bus read as 0 and ignore pokes
+    CpuBus,
+    CpuRam,
+    PrgRom,
+    PrgRam,
+    // CHR-ROM or CHR-RAM
+    Chr,
+    NametableRam,
+    PaletteRam,
+    Oam,
+    SecondaryOam,
+    MapperRegisters,
+}
+
+/*
+    Debugger access to every memory of the console without going through the buses, nothing is
+    clocked so reading $2002 or PPUDATA doesn't change the ppu. Poking PRG-ROM patches the rom
+    image until it is reloaded and poking a mapper register switches banks like a cpu write would.
+*/
+pub fn memory_size(mapper: &dyn Mapper, memory: MemoryType) -> usize {
+    let context = mapper.get_context();
+    match memory {
+        MemoryType::CpuBus => 0x10000,
+        MemoryType::CpuRam => context.sys_ram.len(),
+        MemoryType::PrgRom => context.prg_rom.len(),
+        MemoryType::PrgRam => context.prg_ram.len(),
+        MemoryType::Chr => context.chr.len(),
+        MemoryType::NametableRam => context.vram.len(),
+        MemoryType::PaletteRam => 32,
+        MemoryType::Oam => 256,
+        MemoryType::SecondaryOam => 32,
+        MemoryType::MapperRegisters => mapper.get_registers().len(),
+    }
+}
+
+// None past the end of the memory
+pub fn peek(mapper: &mut dyn Mapper, ppu: &mut Rp2c02, memory: MemoryType, address: usize) -> Option<u8> {
+    if address >= memory_size(mapper, memory) {
+        return None;
+    }
+
+    let context = mapper.get_context();
+    let data = match memory {
+        MemoryType::CpuBus => mappers::peek_cpu(mapper, address as u16),
+        MemoryType::CpuRam => context.sys_ram[address],
+        MemoryType::PrgRom => context.prg_rom[address],
+        MemoryType::PrgRam => context.prg_ram[address],
+        MemoryType::Chr => context.chr[address],
+        MemoryType::NametableRam => context.vram[address],
+        MemoryType::PaletteRam => ppu.peek_palette(address),
+        MemoryType::Oam => ppu.oam()[address],
+        MemoryType::SecondaryOam => ppu.secondary_oam()[address],
+        MemoryType::MapperRegisters => mapper.get_registers()[address],
+    };
+
+    Some(data)
+}
+
+// false when nothing was written
+pub fn poke(mapper: &mut dyn Mapper, ppu: &mut Rp2c02, memory: MemoryType, address: usize, data: u8) -> bool {
+    if address >= memory_size(mapper, memory) {
+        return false;
+    }
+
+    if memory == MemoryType::CpuBus {
+        return poke_cpu(mapper, address as u16, data);
+    }
+
+    let context = mapper.get_context_mut();
+    match memory {
+        MemoryType::CpuRam => context.sys_ram[address] = data,
+        MemoryType::PrgRom => context.prg_rom[address] = data,
+        MemoryType::PrgRam => context.prg_ram[address] = data,
+        MemoryType::Chr => context.chr[address] = data,
+        MemoryType::NametableRam => context.vram[address] = data,
+        MemoryType::PaletteRam => ppu.poke_palette(address, data),
+        MemoryType::Oam => ppu.oam()[address] = data,
+        MemoryType::SecondaryOam => ppu.secondary_oam()[address] = data,
+        MemoryType::MapperRegisters => return mapper.set_register(address, data),
+        MemoryType::CpuBus => return false,
+    }
+
+    true
+}
+
+// ram, wram and the mapped prg banks, wram is written even when the mapper has it disabled
+fn poke_cpu(mapper: &mut dyn Mapper, address: u16, data: u8) -> bool {
+    let offset = match address {
+        0x0000..=0x1FFF => Some(address as usize & 0x7FF),
+        0x6000..=0x7FFF if !mapper.get_context().prg_ram.is_empty() => {
+            let (size, index) = mapper.get_context().wram_addr_mapper.address_bank_details(address);
+            Some(index * size + (address as usize & (size - 1)))
+        }
+        0x8000..=0xFFFF => prg_bank(mapper, address).map(|bank| bank.rom_offset),
+        _ => None,
+    };
+
+    let context = mapper.get_context_mut();
+    let memory = match address {
+        0x0000..=0x1FFF => &mut context.sys_ram,
+        0x6000..=0x7FFF => &mut context.prg_ram,
+        _ => &mut context.prg_rom,
+    };
+
+    match offset.and_then(|offset| memory.get_mut(offset)) {
+        Some(byte) => { *byte = data; true }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::consoles::Console;
+    use crate::consoles::nes_ntsc::NesNtsc;
+    use std::path::Path;
+
+    #[test]
+    fn test_peek_poke() {
+        let test_roms = Path::new(env!("CARGO_MANIFEST_DIR")).join("../rustnes-dev/test_roms");
+        let mut nes = NesNtsc::new();
+        // nothing but internal ram and vram before a rom is loaded
+        assert_eq!(nes.memory_size(MemoryType::PrgRom), 0);
+        assert_eq!(nes.peek_memory(MemoryType::PrgRom, 0), None);
+        assert!(nes.poke_memory(MemoryType::CpuRam, 0x10, 0x12));
+        assert!(!nes.poke_memory(MemoryType::MapperRegisters, 0, 0x12));
+        nes.load_rom(test_roms.join("nestest.nes"));
+
+        // 16K prg is mirrored, the patch shows up in both halves
+        assert_eq!(nes.peek_memory(MemoryType::CpuBus, 0xC5F5), Some(0xA2));
+        assert!(nes.poke_memory(MemoryType::CpuBus, 0x85F5, 0xEA));
+        assert_eq!(nes.peek_memory(MemoryType::PrgRom, 0x05F5), Some(0xEA));
+        assert_eq!(nes.peek_memory(MemoryType::CpuBus, 0xC5F5), Some(0xEA));
+
+        assert!(nes.poke_memory(MemoryType::CpuBus, 0x0812, 0x34));
+        assert_eq!(nes.peek_memory(MemoryType::CpuRam, 0x0012), Some(0x34));
+        assert!(!nes.poke_memory(MemoryType::CpuBus, 0x2002, 0x00));
+
+        // $3F10 mirrors $3F00
+        assert!(nes.poke_memory(MemoryType::PaletteRam, 0x10, 0xFF));
+        assert_eq!(nes.peek_memory(MemoryType::PaletteRam, 0x00), Some(0x3F));
+
+        assert!(nes.poke_memory(MemoryType::Oam, 0xFF, 0x12));
+        assert_eq!(nes.peek_memory(MemoryType::Oam, 0xFF), Some(0x12));
+        assert_eq!(nes.peek_memory(MemoryType::Oam, 0x100), None);
+        assert_eq!(nes.memory_size(MemoryType::Chr), 0x2000);
+        assert_eq!(nes.memory_size(MemoryType::MapperRegisters), 0);
+    }
+
+    #[test]
+    fn test_mapper_registers() {
+        let mut mapper = crate::mappers::mapper_debug::MapperDebug::new();
+        let mut ppu = Rp2c02::from_power_on();
+        assert_eq!(peek(&mut mapper, &mut ppu, MemoryType::MapperRegisters, 0), None);
+        assert!(!poke(&mut mapper, &mut ppu, MemoryType::MapperRegisters, 0, 1));
+
+        assert!(poke(&mut mapper, &mut ppu, MemoryType::NametableRam, 0x400, 0x24));
+        assert_eq!(mapper.peek_nt(0x400), 0x24);
+        assert!(poke(&mut mapper, &mut ppu, MemoryType::SecondaryOam, 31, 0x00));
+        assert_eq!(peek(&mut mapper, &mut ppu, MemoryType::SecondaryOam, 31), Some(0x00));
+    }
+}
@@ -0,0 +1,7 @@
+pub mod expression;
+pub mod breakpoints;
+pub mod disassembler;
+pub mod symbols;
+pub mod cdl;
+pub mod memory;
+pub mod ram_search;
@@ -0,0 +1,259 @@
+use crate::consoles::Console;
+use super::memory::MemoryType;
+
+const SEARCHED: [MemoryType; 2] = [MemoryType::CpuRam, MemoryType::PrgRam];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueSize {
+    Bits8,
+    // little endian, the high byte is the next address
+    Bits16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Greater,
+    Less,
+    GreaterOrEqual,
+    LessOrEqual,
+}
+
+impl Comparison {
+    fn test(&self, a: i64, b: i64) -> bool {
+        match self {
+            Comparison::Equal => a == b,
+            Comparison::NotEqual => a != b,
+            Comparison::Greater => a > b,
+            Comparison::Less => a < b,
+            Comparison::GreaterOrEqual => a >= b,
+            Comparison::LessOrEqual => a <= b,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchFilter {
+    // current value against the one at the previous search step, NotEqual keeps the ones that changed
+    Previous(Comparison),
+    Value(Comparison, i64),
+    // current minus previous, e.g. -1 for a lives counter
+    ChangedBy(i64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub memory: MemoryType,
+    pub offset: usize,
+    pub value: i64,
+    // value at the start and after every filter, oldest first
+    pub history: Vec<i64>,
+}
+
+impl Candidate {
+    // internal ram at $0000, prg ram at $6000
+    pub fn cpu_address(&self) -> u16 {
+        match self.memory {
+            MemoryType::PrgRam => 0x6000 + (self.offset & 0x1FFF) as u16,
+            _ => self.offset as u16,
+        }
+    }
+}
+
+struct Snapshot {
+    cpu_ram: Vec<u8>,
+    prg_ram: Vec<u8>,
+}
+
+impl Snapshot {
+    fn take<C: Console>(console: &mut C) -> Snapshot {
+        let mut read = |memory| (0..console.memory_size(memory)).map(|i| console.peek_memory(memory, i).unwrap_or(0)).collect();
+        Snapshot {
+            cpu_ram: read(MemoryType::CpuRam),
+            prg_ram: read(MemoryType::PrgRam),
+        }
+    }
+
+    fn memory(&self, memory: MemoryType) -> &[u8] {
+        match memory {
+            MemoryType::PrgRam => &self.prg_ram,
+            _ => &self.cpu_ram,
+        }
+    }
+}
+
+/*
+    Cheat finder over internal ram and prg ram. start takes the first snapshot with every address as a
+    candidate, each filter compares the memory now with the last snapshot, drops the candidates that
+    fail and takes a new snapshot. Runs on anything implementing Console, nothing needs a window.
+*/
+pub struct RamSearch {
+    size: ValueSize,
+    signed: bool,
+    candidates: Vec<(MemoryType, usize)>,
+    snapshots: Vec<Snapshot>,
+}
+
+impl RamSearch {
+    pub fn new(size: ValueSize, signed: bool) -> RamSearch {
+        RamSearch {
+            size,
+            signed,
+            candidates: Vec::new(),
+            snapshots: Vec::new(),
+        }
+    }
+
+    pub fn size(&self) -> ValueSize {
+        self.size
+    }
+
+    pub fn signed(&self) -> bool {
+        self.signed
+    }
+
+    // changing how values are read keeps the candidates and history
+    pub fn set_mode(&mut self, size: ValueSize, signed: bool) {
+        self.size = size;
+        self.signed = signed;
+        let last = [self.last_offset(SEARCHED[0]), self.last_offset(SEARCHED[1])];
+        self.candidates.retain(|&(memory, offset)| Some(offset) <= last[(memory == SEARCHED[1]) as usize]);
+    }
+
+    pub fn is_started(&self) -> bool {
+        !self.snapshots.is_empty()
+    }
+
+    pub fn start<C: Console>(&mut self, console: &mut C) {
+        self.snapshots = vec![Snapshot::take(console)];
+        self.candidates = SEARCHED.iter()
+            .filter_map(|&memory| self.last_offset(memory).map(|last| (memory, last)))
+            .flat_map(|(memory, last)| (0..=last).map(move |offset| (memory, offset)))
+            .collect();
+    }
+
+    // returns the number of candidates left
+    pub fn filter<C: Console>(&mut self, console: &mut C, filter: SearchFilter) -> usize {
+        if !self.is_started() {
+            self.start(console);
+        }
+
+        let current = Snapshot::take(console);
+        let previous = self.snapshots.last().unwrap();
+        let candidates = std::mem::take(&mut self.candidates);
+        self.candidates = candidates.into_iter().filter(|&(memory, offset)| {
+            let value = self.value(&current, memory, offset);
+            let previous = self.value(previous, memory, offset);
+            match filter {
+                SearchFilter::Previous(comparison) => comparison.test(value, previous),
+                SearchFilter::Value(comparison, operand) => comparison.test(value, operand),
+                SearchFilter::ChangedBy(delta) => value - previous == delta,
+            }
+        }).collect();
+
+        self.snapshots.push(current);
+        self.candidates.len()
+    }
+
+    pub fn candidate_count(&self) -> usize {
+        self.candidates.len()
+    }
+
+    pub fn is_candidate(&self, memory: MemoryType, offset: usize) -> bool {
+        self.candidates.binary_search_by(|&(m, o)| (m as u8, o).cmp(&(memory as u8, offset))).is_ok()
+    }
+
+    // candidates with their current values read from the console
+    pub fn candidates<C: Console>(&self, console: &mut C) -> Vec<Candidate> {
+        let current = Snapshot::take(console);
+        self.candidates.iter().map(|&(memory, offset)| Candidate {
+            memory,
+            offset,
+            value: self.value(&current, memory, offset),
+            history: self.snapshots.iter().map(|s| self.value(s, memory, offset)).collect(),
+        }).collect()
+    }
+
+    // last offset a value can start at, 16 bit values need the next byte
+    fn last_offset(&self, memory: MemoryType) -> Option<usize> {
+        let width = if self.size == ValueSize::Bits16 { 2 } else { 1 };
+        let len = self.snapshots.first().map_or(0, |s| s.memory(memory).len());
+        len.checked_sub(width)
+    }
+
+    fn value(&self, snapshot: &Snapshot, memory: MemoryType, offset: usize) -> i64 {
+        let data = snapshot.memory(memory);
+        let lo = data.get(offset).copied().unwrap_or(0);
+        match (self.size, self.signed) {
+            (ValueSize::Bits8, false) => lo as i64,
+            (ValueSize::Bits8, true) => lo as i8 as i64,
+            (ValueSize::Bits16, signed) => {
+                let word = u16::from_le_bytes([lo, data.get(offset + 1).copied().unwrap_or(0)]);
+                if signed { word as i16 as i64 } else { word as i64 }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::consoles::nes_ntsc::NesNtsc;
+    use std::path::Path;
+
+    fn console() -> NesNtsc {
+        let test_roms = Path::new(env!("CARGO_MANIFEST_DIR")).join("../rustnes-dev/test_roms");
+        let mut nes = NesNtsc::new();
+        nes.load_rom(test_roms.join("nestest.nes"));
+        for i in 0..0x800 {
+            nes.poke_memory(MemoryType::CpuRam, i, 0);
+        }
+        nes
+    }
+
+    #[test]
+    fn test_search_8bit() {
+        let mut nes = console();
+        let mut search = RamSearch::new(ValueSize::Bits8, false);
+        nes.poke_memory(MemoryType::CpuRam, 0x075A, 3);
+        nes.poke_memory(MemoryType::PrgRam, 0x0010, 3);
+        search.start(&mut nes);
+        assert_eq!(search.candidate_count(), 0x800 + 0x2000);
+
+        nes.poke_memory(MemoryType::CpuRam, 0x075A, 2);
+        nes.poke_memory(MemoryType::PrgRam, 0x0010, 4);
+        assert_eq!(search.filter(&mut nes, SearchFilter::Previous(Comparison::NotEqual)), 2);
+        assert_eq!(search.filter(&mut nes, SearchFilter::Value(Comparison::Less, 3)), 1);
+
+        nes.poke_memory(MemoryType::CpuRam, 0x075A, 1);
+        assert_eq!(search.filter(&mut nes, SearchFilter::ChangedBy(-1)), 1);
+
+        let candidates = search.candidates(&mut nes);
+        assert_eq!(candidates[0].cpu_address(), 0x075A);
+        assert_eq!(candidates[0].value, 1);
+        assert_eq!(candidates[0].history, [3, 2, 2, 1]);
+        assert!(search.is_candidate(MemoryType::CpuRam, 0x075A));
+    }
+
+    #[test]
+    fn test_search_16bit_signed() {
+        let mut nes = console();
+        let mut search = RamSearch::new(ValueSize::Bits16, true);
+        search.start(&mut nes);
+        assert_eq!(search.candidate_count(), 0x7FF + 0x1FFF);
+
+        // $0300 = -2
+        nes.poke_memory(MemoryType::CpuRam, 0x0300, 0xFE);
+        nes.poke_memory(MemoryType::CpuRam, 0x0301, 0xFF);
+        search.filter(&mut nes, SearchFilter::Value(Comparison::Less, 0));
+        let candidates = search.candidates(&mut nes);
+        assert_eq!(candidates.iter().map(|c| (c.offset, c.value)).collect::<Vec<_>>(), [(0x02FF, -512), (0x0300, -2)]);
+
+        // 0xFE01 unsigned
+        search.set_mode(ValueSize::Bits16, false);
+        nes.poke_memory(MemoryType::CpuRam, 0x02FF, 0x01);
+        search.filter(&mut nes, SearchFilter::Previous(Comparison::Greater));
+        assert_eq!(search.candidates(&mut nes)[0].value, 0xFE01);
+    }
+}
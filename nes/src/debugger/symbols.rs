@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use crate::consoles::EmuError;
+use crate::mappers::Mapper;
+use super::disassembler;
+
+const INES_HEADER_SIZE: u32 = 16;
+// FCEUX numbers .nl files by 16K bank
+const FCEUX_BANK_SIZE: usize = 0x4000;
+
+// rom labels are kept by PRG-ROM offset so they follow the bank switching
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolAddress {
+    Cpu(u16),
+    Prg(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceLine {
+    pub file: String,
+    pub line: u32,
+}
+
+/*
+    Labels and comments from assembler and debugger symbol files
+        ca65 .dbg   ld65 --dbgfile, labels and source lines, equates are left out
+        FCEUX .nl   <rom>.nes.<bank>.nl per 16K bank and <rom>.nes.ram.nl
+        Mesen .mlb  P: R: W: S: G: prefixes and the NesPrgRom: style of newer versions
+*/
+pub struct SymbolTable {
+    labels: HashMap<SymbolAddress, String>,
+    comments: HashMap<SymbolAddress, String>,
+    names: HashMap<String, SymbolAddress>,
+    lines: HashMap<usize, SourceLine>,
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        SymbolTable {
+            labels: HashMap::new(),
+            comments: HashMap::new(),
+            names: HashMap::new(),
+            lines: HashMap::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.labels.clear();
+        self.comments.clear();
+        self.names.clear();
+        self.lines.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty() && self.comments.is_empty() && self.lines.is_empty()
+    }
+
+    // cheap local labels (@loop) give way to the other names at the same address
+    pub fn add_label(&mut self, address: SymbolAddress, name: &str) {
+        let replace = match self.labels.get(&address) {
+            Some(existing) => existing.starts_with('@') && !name.starts_with('@'),
+            None => true,
+        };
+        if replace {
+            self.labels.insert(address, name.to_string());
+        }
+        self.names.entry(name.to_string()).or_insert(address);
+    }
+
+    pub fn add_comment(&mut self, address: SymbolAddress, comment: &str) {
+        self.comments.insert(address, comment.to_string());
+    }
+
+    pub fn add_source_line(&mut self, prg_offset: usize, line: SourceLine) {
+        self.lines.insert(prg_offset, line);
+    }
+
+    // prg_offset is where the cpu address is mapped when it's in PRG-ROM
+    pub fn label(&self, address: u16, prg_offset: Option<usize>) -> Option<&str> {
+        self.labels.get(&symbol_address(address, prg_offset)).map(|s| s.as_str())
+    }
+
+    pub fn comment(&self, address: u16, prg_offset: Option<usize>) -> Option<&str> {
+        self.comments.get(&symbol_address(address, prg_offset)).map(|s| s.as_str())
+    }
+
+    pub fn source_line(&self, prg_offset: usize) -> Option<&SourceLine> {
+        self.lines.get(&prg_offset)
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<SymbolAddress> {
+        self.names.get(name).copied()
+    }
+
+    // cpu address of a label, rom labels only resolve while their bank is mapped
+    pub(crate) fn resolve(&self, name: &str, mapper: &dyn Mapper) -> Option<u16> {
+        match self.lookup(name)? {
+            SymbolAddress::Cpu(address) => Some(address),
+            SymbolAddress::Prg(offset) => disassembler::cpu_address(mapper, offset),
+        }
+    }
+
+    pub fn load_ca65_dbg<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, EmuError> {
+        let text = fs::read_to_string(path).map_err(|_| EmuError::LoadSymbolsError)?;
+        self.parse_ca65_dbg(&text)
+    }
+
+    // the bank comes from the file name, game.nes.1.nl is the second 16K bank
+    pub fn load_fceux_nl<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, EmuError> {
+        let path = path.as_ref();
+        let bank = match path.file_stem().and_then(|s| Path::new(s).extension()).and_then(|s| s.to_str()) {
+            Some("ram") => None,
+            Some(bank) => Some(usize::from_str_radix(bank, 16).map_err(|_| EmuError::LoadSymbolsError)?),
+            None => return Err(EmuError::LoadSymbolsError),
+        };
+
+        let text = fs::read_to_string(path).map_err(|_| EmuError::LoadSymbolsError)?;
+        Ok(self.parse_fceux_nl(&text, bank))
+    }
+
+    pub fn load_mesen_mlb<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, EmuError> {
+        let text = fs::read_to_string(path).map_err(|_| EmuError::LoadSymbolsError)?;
+        Ok(self.parse_mesen_mlb(&text))
+    }
+
+    // returns the number of labels, ld65 places rom segments after a 16 byte ines header
+    pub fn parse_ca65_dbg(&mut self, text: &str) -> Result<usize, EmuError> {
+        let mut version = false;
+        let mut segs = HashMap::new();
+        let mut spans = HashMap::new();
+        let mut files = HashMap::new();
+        let mut lines = Vec::new();
+        let mut syms = Vec::new();
+
+        for line in text.lines() {
+            let (record, fields) = match line.find(char::is_whitespace) {
+                Some(i) => (&line[..i], dbg_fields(&line[i..])),
+                None => continue,
+            };
+            let number = |key: &str| fields.get(key).and_then(|v| parse_dbg_number(v));
+
+            match record {
+                "version" => version = true,
+                "file" => { files.insert(number("id"), fields.get("name").cloned().unwrap_or_default()); }
+                // only segments written to the rom have an output offset
+                "seg" => { segs.insert(number("id"), (number("start").unwrap_or(0), number("ooffs"))); }
+                "span" => { spans.insert(number("id"), (number("seg"), number("start").unwrap_or(0))); }
+                "line" => {
+                    let span_ids: Vec<Option<u32>> = fields.get("span").map(|s| s.split('+').map(parse_dbg_number).collect()).unwrap_or_default();
+                    lines.push((number("file"), number("line").unwrap_or(0), number("type").unwrap_or(0), span_ids));
+                }
+                "sym" if fields.get("type").map(|t| t.as_str()) == Some("lab") => {
+                    syms.push((fields.get("name").cloned().unwrap_or_default(), number("val"), number("seg")));
+                }
+                _ => { }
+            }
+        }
+
+        if !version {
+            return Err(EmuError::LoadSymbolsError);
+        }
+
+        let prg_offset = |seg: Option<u32>, address: u32| -> Option<usize> {
+            let (start, ooffs) = segs.get(&seg)?;
+            let ooffs = (*ooffs)?;
+            Some((ooffs + address.wrapping_sub(*start)).checked_sub(INES_HEADER_SIZE)? as usize)
+        };
+
+        let mut count = 0;
+        for (name, val, seg) in syms.iter() {
+            let val = match val {
+                Some(val) if *val <= 0xFFFF => *val,
+                _ => continue,
+            };
+            let address = match prg_offset(*seg, val) {
+                Some(offset) => SymbolAddress::Prg(offset),
+                None => SymbolAddress::Cpu(val as u16),
+            };
+            self.add_label(address, name);
+            count += 1;
+        }
+
+        // macro expansions (type 2) only fill in lines the source itself doesn't cover
+        for (file, line, kind, span_ids) in lines.iter() {
+            let file = match files.get(file) {
+                Some(file) => file,
+                None => continue,
+            };
+            for span in span_ids.iter() {
+                let offset = spans.get(span).and_then(|(seg, start)| {
+                    let (seg_start, _) = segs.get(seg)?;
+                    prg_offset(*seg, seg_start + start)
+                });
+                if let Some(offset) = offset {
+                    if *kind != 2 || !self.lines.contains_key(&offset) {
+                        self.add_source_line(offset, SourceLine { file: file.clone(), line: *line });
+                    }
+                }
+            }
+        }
+
+        Ok(count)
+    }
+
+    // bank is None for the ram file, returns the number of labels
+    pub fn parse_fceux_nl(&mut self, text: &str, bank: Option<usize>) -> usize {
+        let mut count = 0;
+        for line in text.lines() {
+            // $C000#Reset#comment, arrays are $0300/10#buffer#
+            let mut parts = line.splitn(3, '#');
+            let address = match parts.next().and_then(|a| a.trim().strip_prefix('$')) {
+                Some(a) => a.split('/').next().unwrap_or(""),
+                None => continue,
+            };
+            let address = match u16::from_str_radix(address, 16) {
+                Ok(address) => address,
+                Err(_) => continue,
+            };
+
+            let symbol = match bank {
+                Some(bank) if address >= 0x8000 => SymbolAddress::Prg(bank * FCEUX_BANK_SIZE + (address as usize & (FCEUX_BANK_SIZE - 1))),
+                _ => SymbolAddress::Cpu(address),
+            };
+
+            let name = parts.next().unwrap_or("").trim();
+            if !name.is_empty() {
+                self.add_label(symbol, name);
+                count += 1;
+            }
+            // multi line comments are joined with '\'
+            let comment = parts.next().unwrap_or("").trim_end_matches('#').replace('\\', "\n");
+            if !comment.trim().is_empty() {
+                self.add_comment(symbol, comment.trim());
+            }
+        }
+
+        count
+    }
+
+    // returns the number of labels
+    pub fn parse_mesen_mlb(&mut self, text: &str) -> usize {
+        let mut count = 0;
+        for line in text.lines() {
+            // P:1234:label:comment, ranges are P:1234-1237:table
+            let mut parts = line.splitn(4, ':');
+            let (kind, offset) = match (parts.next(), parts.next()) {
+                (Some(kind), Some(offset)) => (kind.trim(), offset.split('-').next().unwrap_or("")),
+                _ => continue,
+            };
+            let offset = match usize::from_str_radix(offset, 16) {
+                Ok(offset) => offset,
+                Err(_) => continue,
+            };
+
+            let symbol = match kind {
+                "P" | "NesPrgRom" => SymbolAddress::Prg(offset),
+                "R" | "NesInternalRam" => SymbolAddress::Cpu((offset & 0x7FF) as u16),
+                "S" | "W" | "NesSaveRam" | "NesWorkRam" => SymbolAddress::Cpu(0x6000 | (offset & 0x1FFF) as u16),
+                "G" | "NesMemory" => SymbolAddress::Cpu(offset as u16),
+                _ => continue,
+            };
+
+            let name = parts.next().unwrap_or("").trim();
+            if !name.is_empty() {
+                self.add_label(symbol, name);
+                count += 1;
+            }
+            let comment = parts.next().unwrap_or("").replace("\\n", "\n");
+            if !comment.trim().is_empty() {
+                self.add_comment(symbol, comment.trim());
+            }
+        }
+
+        count
+    }
+}
+
+impl Default for SymbolTable {
+    fn default() -> Self {
+        SymbolTable::new()
+    }
+}
+
+fn symbol_address(address: u16, prg_offset: Option<usize>) -> SymbolAddress {
+    match prg_offset {
+        Some(offset) => SymbolAddress::Prg(offset),
+        None => SymbolAddress::Cpu(address),
+    }
+}
+
+// key=value pairs separated by commas, values may be quoted
+fn dbg_fields(text: &str) -> HashMap<String, String> {
+    let mut fields = HashMap::new();
+    let mut field = String::new();
+    let mut quoted = false;
+
+    for c in text.trim().chars().chain(std::iter::once(',')) {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                if let Some(i) = field.find('=') {
+                    fields.insert(field[..i].to_string(), field[i + 1..].to_string());
+                }
+                field.clear();
+            }
+            _ => field.push(c),
+        }
+    }
+
+    fields
+}
+
+fn parse_dbg_number(text: &str) -> Option<u32> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DBG: &str = "version\tmajor=2,minor=0
+file\tid=0,name=\"src/main, game.s\",size=1000,mtime=0x5F000000,mod=0
+seg\tid=0,name=\"HEADER\",start=0x000000,size=0x0010,addrsize=absolute,type=ro,oname=\"game.nes\",ooffs=0
+seg\tid=1,name=\"CODE\",start=0x00C000,size=0x4000,addrsize=absolute,type=ro,oname=\"game.nes\",ooffs=16
+seg\tid=2,name=\"BSS\",start=0x000300,size=0x0100,addrsize=absolute,type=rw
+span\tid=0,seg=1,start=0,size=2
+span\tid=1,seg=1,start=2,size=3,type=1
+line\tid=0,file=0,line=12,span=0
+line\tid=1,file=0,line=40,type=2,span=0+1
+sym\tid=0,name=\"reset\",addrsize=absolute,scope=0,def=0,val=0xC000,seg=1,type=lab
+sym\tid=1,name=\"buffer\",addrsize=absolute,scope=0,def=1,val=0x300,seg=2,type=lab
+sym\tid=2,name=\"SPEED\",addrsize=zeropage,scope=0,def=2,val=0x3,type=equ
+";
+
+    #[test]
+    fn test_ca65_dbg() {
+        let mut symbols = SymbolTable::new();
+        assert_eq!(symbols.parse_ca65_dbg(DBG).ok(), Some(2));
+        assert_eq!(symbols.lookup("reset"), Some(SymbolAddress::Prg(0)));
+        assert_eq!(symbols.label(0x0300, None), Some("buffer"));
+        assert_eq!(symbols.lookup("SPEED"), None);
+
+        assert_eq!(symbols.source_line(0), Some(&SourceLine { file: "src/main, game.s".to_string(), line: 12 }));
+        assert_eq!(symbols.source_line(2).map(|l| l.line), Some(40));
+        assert!(SymbolTable::new().parse_ca65_dbg("sym\tname=\"x\"").is_err());
+    }
+
+    #[test]
+    fn test_fceux_nl() {
+        let mut symbols = SymbolTable::new();
+        let count = symbols.parse_fceux_nl("$C000#Reset#power on\\and reset\n$0300/10#Buffer#\n$6000#Save#\nnot a label\n", Some(1));
+        assert_eq!(count, 3);
+        assert_eq!(symbols.label(0xC000, Some(0x4000)), Some("Reset"));
+        assert_eq!(symbols.comment(0xC000, Some(0x4000)), Some("power on\nand reset"));
+        assert_eq!(symbols.label(0xC000, Some(0)), None);
+        assert_eq!(symbols.label(0x0300, None), Some("Buffer"));
+
+        symbols.parse_fceux_nl("$00FF#temp#", None);
+        assert_eq!(symbols.lookup("temp"), Some(SymbolAddress::Cpu(0xFF)));
+    }
+
+    #[test]
+    fn test_mesen_mlb() {
+        let mut symbols = SymbolTable::new();
+        let count = symbols.parse_mesen_mlb("P:4000:Reset:comment\nR:0010-0011:ptr\nNesWorkRam:0020:save\nG:2000:PPUCTRL\nP:4002::only a comment\n");
+        assert_eq!(count, 4);
+        assert_eq!(symbols.lookup("Reset"), Some(SymbolAddress::Prg(0x4000)));
+        assert_eq!(symbols.label(0x0010, None), Some("ptr"));
+        assert_eq!(symbols.label(0x6020, None), Some("save"));
+        assert_eq!(symbols.label(0x2000, None), Some("PPUCTRL"));
+        assert_eq!(symbols.comment(0x8002, Some(0x4002)), Some("only a comment"));
+    }
+}
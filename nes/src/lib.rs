@@ -1,5 +1,8 @@
 pub mod consoles;
 pub mod utils;
+pub mod video;
+pub mod debugger;
+pub mod cheats;
 
 mod palette;
 mod dma;
@@ -8,7 +11,10 @@ mod bus;
 mod ppu;
 mod controllers;
 
-pub use controllers::JoypadInput;
+pub use controllers::{JoypadInput, CoinSlot};
+pub use ppu::PpuModel;
+pub use ppu::viewers as ppu_viewers;
+pub use palette::{Palette, PaletteSettings, PaletteSource};
 
 
 #[macro_use]
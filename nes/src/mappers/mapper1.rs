@@ -20,6 +20,8 @@ pub struct Mapper1 {
     pub chr_bank_mode: ChrBankMode,
     pub shift_register: u8,
     pub shift_count: u8,
+    // control, chr bank 0, chr bank 1, prg bank
+    pub registers: [u8; 4],
     pub cpu_cycle: u64,
     pub last_write_cpu_cycle: u64,
     pub ram_enable: bool,
@@ -34,6 +36,7 @@ impl Mapper1 {
             chr_bank_mode: ChrBankMode::Switch8K,
             shift_register: 0,
             shift_count: 0,
+            registers: [0x0C, 0, 0, 0],
             cpu_cycle: 0,
             last_write_cpu_cycle: 0,
             ram_enable: false,
@@ -136,6 +139,17 @@ impl Mapper1 {
         if ram_enable == 0x10 { self.ram_enable = true; }
     }
 
+    pub fn register_handler(&mut self, reg_index: usize, data: u8) {
+        self.registers[reg_index] = data;
+        match reg_index {
+            0 => { self.ctrl_handler(data); }
+            1 => { self.chr_bank0_handler(data); }
+            2 => { self.chr_bank1_handler(data); }
+            3 => { self.prg_bank_handler(data); }
+            _ => panic!("mmc1 register out of bounds")
+        }
+    }
+
     pub fn write_handler(&mut self, pinout: mos::Pinout) {
         if (pinout.data & 0x80) > 0 {
             self.clear_shift();
@@ -148,15 +162,7 @@ impl Mapper1 {
         // every fifth write
         if self.shift_count == 5 {
             let reg_index = ((pinout.address & 0x6000) >> 13) as u8 & 0x0F;
-
-            match reg_index {
-                0 => { self.ctrl_handler(self.shift_register); }
-                1 => { self.chr_bank0_handler(self.shift_register); }
-                2 => { self.chr_bank1_handler(self.shift_register); }
-                3 => { self.prg_bank_handler(self.shift_register); }
-                _ => panic!("mmc1 register out of bounds")
-            }
-
+            self.register_handler(reg_index as usize, self.shift_register);
             self.clear_shift();
         }
     }
@@ -257,6 +263,27 @@ impl Mapper for Mapper1 {
     fn ppu_tick(&mut self, pinout: ppu::Pinout) -> ppu::Pinout {
         pinout
     }
+
+    fn get_context(&self) -> &Context {
+        &self.context
+    }
+
+    fn get_context_mut(&mut self) -> &mut Context {
+        &mut self.context
+    }
+
+    fn get_registers(&self) -> Vec<u8> {
+        self.registers.to_vec()
+    }
+
+    fn set_register(&mut self, index: usize, data: u8) -> bool {
+        if index >= self.registers.len() {
+            return false;
+        }
+
+        self.register_handler(index, data & 0x1F);
+        true
+    }
 }
 
 
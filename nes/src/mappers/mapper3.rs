@@ -5,12 +5,14 @@ use super::ppu;
 
 pub struct Mapper3 {
     pub context: Context,
+    pub chr_bank: u8,
 }
 
 impl Mapper3 {
     pub fn new() -> Mapper3 {
         Mapper3 {
             context: Context::new(),
+            chr_bank: 0,
         }
     }
 
@@ -45,8 +47,10 @@ impl Mapper3 {
 
     pub fn write_handler(&mut self, pinout: mos::Pinout) {
         // CNROM only implements the lowest 2 bits, capping it at 32 KiB CHR. Other boards may implement 4 or more bits for larger CHR
-        let bank_index = pinout.data as usize;
-        self.context.chr_addr_mapper.set_banking_region(0, bank_index, SIZE_8K);
+        // bits past the chr rom size are ignored
+        let chr_bank_count = self.context.chr.len() / SIZE_8K;
+        self.chr_bank = (pinout.data as usize % chr_bank_count) as u8;
+        self.context.chr_addr_mapper.set_banking_region(0, self.chr_bank as usize, SIZE_8K);
     }
 }
 
@@ -123,4 +127,50 @@ impl Mapper for Mapper3 {
     fn ppu_tick(&mut self, pinout: ppu::Pinout) -> ppu::Pinout {
         pinout
     }
-}
\ No newline at end of file
+
+    fn get_context(&self) -> &Context {
+        &self.context
+    }
+
+    fn get_context_mut(&mut self) -> &mut Context {
+        &mut self.context
+    }
+
+    fn get_registers(&self) -> Vec<u8> {
+        vec![self.chr_bank]
+    }
+
+    fn set_register(&mut self, index: usize, data: u8) -> bool {
+        if index != 0 {
+            return false;
+        }
+
+        let mut pinout = mos::Pinout::new();
+        pinout.address = 0x8000;
+        pinout.data = data;
+        self.write_handler(pinout);
+        true
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_chr_bank_wraps_to_rom_size() {
+        let mut mapper3 = Mapper3::new();
+        mapper3.context.chr = (0..4u8).flat_map(|bank| vec![bank; SIZE_8K]).collect();
+
+        assert!(mapper3.set_register(0, 5));
+        assert_eq!(mapper3.get_registers(), vec![1]);
+        let pinout = mapper3.read_ppu_chr(ppu::Pinout::new());
+        assert_eq!(pinout.data, 1);
+
+        let mut pinout = mos::Pinout::new();
+        pinout.address = 0x8000;
+        pinout.data = 0xFF;
+        mapper3.write_cpu_prg(pinout);
+        assert_eq!(mapper3.get_registers(), vec![3]);
+        assert!(!mapper3.set_register(1, 0));
+    }
+}
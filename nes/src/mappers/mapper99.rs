@@ -0,0 +1,188 @@
+use ::nes_rom::ines;
+
+use super::*;
+use super::ppu;
+
+const SIZE_40K: usize = SIZE_32K + SIZE_8K;
+
+/*
+    Vs. System mapper, banks are selected by bit 2 of writes to $4016 (the same write that strobes the joypads)
+    CHR - 8K bank at $0000
+    PRG - 8K bank at $8000 for the 40K Vs. Gumshoe, otherwise fixed 32K
+    WRAM - 2K at $6000 mirrored to $7FFF, shared between cpus on dual systems
+*/
+pub struct Mapper99 {
+    pub context: Context,
+    pub bank_select: bool,
+}
+
+impl Mapper99 {
+    pub fn new() -> Mapper99 {
+        Mapper99 {
+            context: Context::new(),
+            bank_select: false,
+        }
+    }
+
+    pub fn from_ines(rom: &ines::Ines) -> Mapper99 {
+        let mut mapper99 = Mapper99::new();
+
+        mapper99.context.prg_rom = rom.prg_data.clone();
+        mapper99.context.chr = rom.chr_data.clone();
+        mapper99.context.prg_ram = vec![0; SIZE_2K];
+
+        if mapper99.context.chr.is_empty() {
+            // mapper99 only support chr rom
+            panic!("mapper99 - chr  rom size is invalid ");
+        }
+
+        match rom.prg_rom_size as usize {
+            SIZE_16K => {
+                mapper99.context.prg_addr_mapper.set_banking_region(0, 0, SIZE_16K);
+                mapper99.context.prg_addr_mapper.set_banking_region(1, 0, SIZE_16K);
+            }
+            SIZE_32K => {
+                mapper99.context.prg_addr_mapper.set_banking_region(0, 0, SIZE_32K);
+            }
+            SIZE_40K => {
+                mapper99.context.prg_addr_mapper.set_banking_region(0, 0, SIZE_8K);
+                mapper99.context.prg_addr_mapper.set_banking_region(1, 1, SIZE_8K);
+                mapper99.context.prg_addr_mapper.set_banking_region(2, 2, SIZE_8K);
+                mapper99.context.prg_addr_mapper.set_banking_region(3, 3, SIZE_8K);
+            }
+            _ => panic!("prg rom size is invalid - {:#X}", rom.prg_rom_size)
+        };
+
+        for i in 0..4 {
+            mapper99.context.wram_addr_mapper.set_banking_region(i, 0, SIZE_2K);
+        }
+
+        mapper99.context.chr_addr_mapper.set_banking_region(0, 0, SIZE_8K);
+        set_nametable_from_mirroring_type(&mut mapper99.context, rom.nametable_mirroring);
+
+        mapper99
+    }
+
+    pub fn write_handler(&mut self, pinout: mos::Pinout) {
+        let bank_select = (pinout.data & 0x04) != 0;
+        if bank_select == self.bank_select {
+            return;
+        }
+
+        self.bank_select = bank_select;
+        let chr_bank_count = self.context.chr.len() / SIZE_8K;
+        let chr_bank = if bank_select { 1 % chr_bank_count } else { 0 };
+        self.context.chr_addr_mapper.set_banking_region(0, chr_bank, SIZE_8K);
+
+        if self.context.prg_rom.len() == SIZE_40K {
+            let prg_bank = if bank_select { 4 } else { 0 };
+            self.context.prg_addr_mapper.set_banking_region(0, prg_bank, SIZE_8K);
+        }
+    }
+}
+
+impl Mapper for Mapper99 {
+    // cpu
+    fn read_cpu_internal_ram(&mut self, mut pinout: mos::Pinout) -> mos::Pinout {
+        pinout.data = self.context.sys_ram[(pinout.address & 0x7FF) as usize];
+        pinout
+    }
+
+    fn read_cpu_exp(&mut self, pinout: mos::Pinout) -> mos::Pinout {
+        // open bus
+        pinout
+    }
+
+    fn read_cpu_wram(&mut self, mut pinout: mos::Pinout) -> mos::Pinout {
+        let internal_address = self.context.wram_addr_mapper.translate_address(pinout.address);
+        pinout.data = self.context.prg_ram[internal_address as usize];
+        pinout
+    }
+
+    fn read_cpu_prg(&mut self, mut pinout: mos::Pinout) -> mos::Pinout {
+        let internal_address = self.context.prg_addr_mapper.translate_address(pinout.address);
+        pinout.data = self.context.prg_rom[internal_address as usize];
+        pinout
+    }
+
+    fn write_cpu_internal_ram(&mut self, pinout: mos::Pinout) -> mos::Pinout {
+        self.context.sys_ram[(pinout.address & 0x7FF) as usize] = pinout.data;
+        pinout
+    }
+
+    fn write_cpu_exp(&mut self, pinout: mos::Pinout) -> mos::Pinout {
+        // open bus
+        pinout
+    }
+
+    fn write_cpu_wram(&mut self, pinout: mos::Pinout) -> mos::Pinout {
+        let internal_address = self.context.wram_addr_mapper.translate_address(pinout.address);
+        self.context.prg_ram[internal_address as usize] = pinout.data;
+        pinout
+    }
+
+    fn write_cpu_prg(&mut self, pinout: mos::Pinout) -> mos::Pinout {
+        pinout
+    }
+
+    // ppu
+    fn read_ppu_chr(&mut self, mut pinout: ppu::Pinout) -> ppu::Pinout {
+        let internal_address = self.context.chr_addr_mapper.translate_address(pinout.address);
+        pinout.data = self.context.chr[internal_address as usize];
+        pinout
+    }
+
+    fn read_ppu_nt(&mut self, mut pinout: ppu::Pinout) -> ppu::Pinout {
+        let internal_address = self.context.nt_addr_mapper.translate_address(pinout.address & 0x2fff);
+        pinout.data = self.context.vram[internal_address as usize];
+        pinout
+    }
+
+    fn  write_ppu_chr(&mut self, pinout: ppu::Pinout) -> ppu::Pinout {
+        pinout
+    }
+
+    fn  write_ppu_nt(&mut self, pinout: ppu::Pinout) -> ppu::Pinout {
+        let internal_address = self.context.nt_addr_mapper.translate_address(pinout.address & 0x2fff);
+        self.context.vram[internal_address as usize] = pinout.data;
+        pinout
+    }
+
+    fn cpu_tick(&mut self, pinout: mos::Pinout) -> mos::Pinout {
+        // bank select is wired to the joypad strobe port
+        if pinout.address == 0x4016 && !pinout.ctrl.contains(mos::Ctrl::RW) {
+            self.write_handler(pinout);
+        }
+
+        pinout
+    }
+
+    fn ppu_tick(&mut self, pinout: ppu::Pinout) -> ppu::Pinout {
+        pinout
+    }
+
+    fn get_context(&self) -> &Context {
+        &self.context
+    }
+
+    fn get_context_mut(&mut self) -> &mut Context {
+        &mut self.context
+    }
+
+    fn get_registers(&self) -> Vec<u8> {
+        // bit 2 of the last $4016 write
+        vec![if self.bank_select { 0x04 } else { 0x00 }]
+    }
+
+    fn set_register(&mut self, index: usize, data: u8) -> bool {
+        if index != 0 {
+            return false;
+        }
+
+        let mut pinout = mos::Pinout::new();
+        pinout.address = 0x4016;
+        pinout.data = data;
+        self.write_handler(pinout);
+        true
+    }
+}
@@ -131,5 +131,22 @@ impl Mapper for MapperDebug {
     fn ppu_tick(&mut self, pinout: ppu::Pinout) -> ppu::Pinout {
         pinout
     }
+
+    fn get_context(&self) -> &Context {
+        &self.context
+    }
+
+    fn get_context_mut(&mut self) -> &mut Context {
+        &mut self.context
+    }
+
+    fn get_registers(&self) -> Vec<u8> {
+        // no registers
+        Vec::new()
+    }
+
+    fn set_register(&mut self, _index: usize, _data: u8) -> bool {
+        false
+    }
 }
 
@@ -2,13 +2,16 @@ use std::unimplemented;
 
 use super::*;
 
-pub struct MapperNull;
+// no cartridge, the cpu reads open bus and the debugger sees empty memories
+pub struct MapperNull {
+    pub context: Context,
+}
 
 impl Mapper for MapperNull {
-      fn read_cpu_internal_ram(&mut self, _pinout: mos::Pinout) -> mos::Pinout { unimplemented!(); }
+      fn read_cpu_internal_ram(&mut self, mut pinout: mos::Pinout) -> mos::Pinout { pinout.data = self.context.sys_ram[pinout.address as usize & 0x7FF]; pinout }
       fn read_cpu_exp(&mut self, _pinout: mos::Pinout) -> mos::Pinout { unimplemented!(); } 
-      fn read_cpu_wram(&mut self, _pinout: mos::Pinout) -> mos::Pinout { unimplemented!(); }
-      fn read_cpu_prg(&mut self, _pinout: mos::Pinout) -> mos::Pinout { unimplemented!(); }
+      fn read_cpu_wram(&mut self, pinout: mos::Pinout) -> mos::Pinout { pinout }
+      fn read_cpu_prg(&mut self, pinout: mos::Pinout) -> mos::Pinout { pinout }
 
       fn write_cpu_internal_ram(&mut self, _pinout: mos::Pinout) -> mos::Pinout { unimplemented!(); }
       fn write_cpu_exp(&mut self, _pinout: mos::Pinout) -> mos::Pinout { unimplemented!(); } 
@@ -23,4 +26,9 @@ impl Mapper for MapperNull {
 
       fn cpu_tick(&mut self, _pinout: mos::Pinout) -> mos::Pinout { unimplemented!(); }
       fn ppu_tick(&mut self, _pinout: ppu::Pinout) -> ppu::Pinout { unimplemented!(); }
+
+      fn get_context(&self) -> &Context { &self.context }
+      fn get_context_mut(&mut self) -> &mut Context { &mut self.context }
+      fn get_registers(&self) -> Vec<u8> { Vec::new() }
+      fn set_register(&mut self, _index: usize, _data: u8) -> bool { false }
 }
\ No newline at end of file
@@ -2,6 +2,7 @@ mod mapper_null;
 mod mapper_nrom;
 mod mapper1;
 mod mapper3;
+mod mapper99;
 pub mod mapper_debug;
 
 use super::ppu;
@@ -9,6 +10,7 @@ use super::utils::paging::*;
 use mapper_nrom::MapperNrom;
 use mapper1::Mapper1;
 use mapper3::Mapper3;
+use mapper99::Mapper99;
 use mapper_null::MapperNull;
 use ::nes_rom::ines;
 
@@ -141,10 +143,44 @@ pub trait Mapper {
     // used to monitor cpu and ppu buses for complex behaivor e.g. mmc5
     fn cpu_tick(&mut self, pinout: mos::Pinout) -> mos::Pinout;
     fn ppu_tick(&mut self, pinout: ppu::Pinout) -> ppu::Pinout;
+
+    // memory and bank configuration for the debugger
+    fn get_context(&self) -> &Context;
+    fn get_context_mut(&mut self) -> &mut Context;
+    // last values written to the bank registers, setting one applies it like a cpu write would
+    fn get_registers(&self) -> Vec<u8>;
+    fn set_register(&mut self, index: usize, data: u8) -> bool;
+}
+
+// cpu address space without side effects, registers and open bus read as 0
+pub fn peek_cpu(mapper: &mut dyn Mapper, address: u16) -> u8 {
+    let mut pinout = mos::Pinout::new();
+    pinout.address = address;
+    match address {
+        0x0000..=0x1FFF => mapper.read_cpu_internal_ram(pinout).data,
+        0x6000..=0x7FFF => mapper.read_cpu_wram(pinout).data,
+        0x8000..=0xFFFF => mapper.read_cpu_prg(pinout).data,
+        _ => 0,
+    }
+}
+
+// ppu address space through the current chr banks and nametable mirroring, $3F00-$3FFF reads the nametable under it
+pub fn peek_ppu(mapper: &dyn Mapper, address: u16) -> u8 {
+    let context = mapper.get_context();
+    let (memory, address) = match address & 0x3FFF {
+        0x0000..=0x1FFF => (&context.chr, address & 0x1FFF),
+        a => (&context.vram, 0x2000 | (a & 0x0FFF)),
+    };
+    let (size, index) = match address {
+        0x0000..=0x1FFF => context.chr_addr_mapper.address_bank_details(address),
+        _ => context.nt_addr_mapper.address_bank_details(address),
+    };
+
+    memory.get(index * size + (address as usize & (size - 1))).copied().unwrap_or(0)
 }
 
 pub fn create_mapper_null() -> Box<dyn Mapper> {
-    Box::new(MapperNull {})
+    Box::new(MapperNull { context: Context::new() })
 }
 
 pub fn create_mapper(rom: &ines::Ines) -> Box<dyn Mapper> {
@@ -158,6 +194,9 @@ pub fn create_mapper(rom: &ines::Ines) -> Box<dyn Mapper> {
         3 => {
             Box::new(Mapper3::from_ines(rom))
         }
+        99 => {
+            Box::new(Mapper99::from_ines(rom))
+        }
         // TODO: add error handling instead of panicking like a monster
         _ => { panic!("mapper {} implementation not found", rom.mapper); }
     }
@@ -1,20 +1,217 @@
+use crate::consoles::EmuError;
+use std::path::Path;
+
 /*
     The NES doesn't output an RGB signal; it directly outputs analog video signal, hence
     there is a multitude of ways of interpreting the colors it generates.
 
     Implementation based on https://wiki.nesdev.com/w/index.php/NTSC_video
+
+    A Palette can also be loaded from a .pal file (64 or 512 RGB triplets) or generated from the
+    YIQ decoding of the NTSC signal.
+
+    The RGB PPUs (2C03, 2C04, 2C05) used in the Vs. System, PlayChoice-10 and Famicom Titler
+    output RGB directly from a fixed 3 bit per channel palette. The 2C04 variants use the same
+    colors as the 2C03 but scramble the order of the palette as a form of copy protection.
 */
 
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PaletteSource {
     Ppu_2c02,
-    //Ppu_2c03,
-    //Ppu_2c05,
+    Ppu_2c03,
+    Ppu_2c04_0001,
+    Ppu_2c04_0002,
+    Ppu_2c04_0003,
+    Ppu_2c04_0004,
+    Ppu_2c05,
 }
 
+impl PaletteSource {
+    pub const fn is_rgb(&self) -> bool {
+        !matches!(self, PaletteSource::Ppu_2c02)
+    }
+}
+
+// emphasis bits make up bits 6,7,8 of PPU color index
+const EMPHASIS_RED: u16 = 0x40;
+const EMPHASIS_GREEN: u16 = 0x80;
+const EMPHASIS_BLUE: u16 = 0x100;
+
 #[inline]
 pub const fn palette_color(pindex: u16, psource: PaletteSource) -> u32 {
+    let color = (pindex & 0x3F) as usize;
     match psource {
-        PaletteSource::Ppu_2c02 => PALETTE_2C02[pindex as usize],
+        PaletteSource::Ppu_2c02 => PALETTE_2C02_EMPHASIS[(pindex & 0x1FF) as usize],
+        PaletteSource::Ppu_2c03 | PaletteSource::Ppu_2c05 => rgb_emphasis(PALETTE_2C03[color], pindex),
+        PaletteSource::Ppu_2c04_0001 => rgb_emphasis(PALETTE_2C03[RP2C04_0001_LUT[color] as usize], pindex),
+        PaletteSource::Ppu_2c04_0002 => rgb_emphasis(PALETTE_2C03[RP2C04_0002_LUT[color] as usize], pindex),
+        PaletteSource::Ppu_2c04_0003 => rgb_emphasis(PALETTE_2C03[RP2C04_0003_LUT[color] as usize], pindex),
+        PaletteSource::Ppu_2c04_0004 => rgb_emphasis(PALETTE_2C03[RP2C04_0004_LUT[color] as usize], pindex),
+    }
+}
+
+// RGB PPUs don't attenuate the other channels like the 2C02, emphasis instead
+// drives the selected channel to full brightness
+#[inline]
+const fn rgb_emphasis(mut color: u32, pindex: u16) -> u32 {
+    if (pindex & EMPHASIS_RED) != 0 { color |= 0xFF0000; }
+    if (pindex & EMPHASIS_GREEN) != 0 { color |= 0x00FF00; }
+    if (pindex & EMPHASIS_BLUE) != 0 { color |= 0x0000FF; }
+    color
+}
+
+// 2C02 emphasis attenuates the channels that are not emphasized to about 81.6%,
+// color indices $xE/$xF output black and are unaffected
+const EMPHASIS_ATTENUATION: u32 = 209;  // out of 256
+
+const fn attenuate_channel(color: u32, shift: u32) -> u32 {
+    ((((color >> shift) & 0xFF) * EMPHASIS_ATTENUATION) >> 8) << shift
+}
+
+const fn build_2c02_emphasis_palette(base: &[u32; 64]) -> [u32; 512] {
+    let mut palette = [0u32; 512];
+    let mut i = 0;
+    while i < 512 {
+        let color = base[i & 0x3F];
+        let emphasis = (i as u16) & (EMPHASIS_RED | EMPHASIS_GREEN | EMPHASIS_BLUE);
+        if emphasis == 0 || (i & 0x0F) >= 0x0E {
+            palette[i] = color;
+        }
+        else {
+            let red = if (emphasis & EMPHASIS_RED) != 0 { color & 0xFF0000 } else { attenuate_channel(color, 16) };
+            let green = if (emphasis & EMPHASIS_GREEN) != 0 { color & 0x00FF00 } else { attenuate_channel(color, 8) };
+            let blue = if (emphasis & EMPHASIS_BLUE) != 0 { color & 0x0000FF } else { attenuate_channel(color, 0) };
+            // with every bit set all channels are attenuated
+            palette[i] = if emphasis == (EMPHASIS_RED | EMPHASIS_GREEN | EMPHASIS_BLUE) {
+                attenuate_channel(color, 16) | attenuate_channel(color, 8) | attenuate_channel(color, 0)
+            }
+            else {
+                red | green | blue
+            };
+        }
+        i += 1;
+    }
+
+    palette
+}
+
+// 64 colors x 8 emphasis combinations indexed by the 9 bit PPU color index
+const PALETTE_2C02_EMPHASIS: [u32; 512] = build_2c02_emphasis_palette(&PALETTE_2C02);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaletteSettings {
+    pub hue: f32,           // degrees
+    pub saturation: f32,
+    pub contrast: f32,
+    pub brightness: f32,
+    pub gamma: f32,
+}
+
+impl Default for PaletteSettings {
+    fn default() -> Self {
+        PaletteSettings {
+            hue: 0.0,
+            saturation: 1.0,
+            contrast: 1.0,
+            brightness: 1.0,
+            gamma: 1.8,
+        }
+    }
+}
+
+// 512 colors indexed by the 9 bit PPU color index
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    colors: Vec<u32>,
+}
+
+impl Palette {
+    pub fn from_source(psource: PaletteSource) -> Palette {
+        Palette {
+            colors: (0..512).map(|i| palette_color(i, psource)).collect(),
+        }
+    }
+
+    // 64 color files get the 2C02 emphasis attenuation applied
+    pub fn from_pal_bytes(data: &[u8]) -> Result<Palette, EmuError> {
+        let colors: Vec<u32> = data.chunks_exact(3)
+            .map(|c| ((c[0] as u32) << 16) | ((c[1] as u32) << 8) | (c[2] as u32))
+            .collect();
+
+        match data.len() {
+            192 => {
+                let mut base = [0u32; 64];
+                base.copy_from_slice(&colors);
+                Ok(Palette { colors: build_2c02_emphasis_palette(&base).to_vec() })
+            }
+            1536 => Ok(Palette { colors: colors }),
+            _ => Err(EmuError::LoadPaletteError),
+        }
+    }
+
+    pub fn from_pal_file<P: AsRef<Path>>(path: P) -> Result<Palette, EmuError> {
+        let data = std::fs::read(path).map_err(|_| EmuError::LoadPaletteError)?;
+        Palette::from_pal_bytes(&data)
+    }
+
+    /*
+        Decodes one subcarrier cycle of the NTSC signal for each color into YIQ
+        Based on Bisqwit's palette generator https://wiki.nesdev.com/w/index.php/NTSC_video
+    */
+    pub fn generate(settings: PaletteSettings) -> Palette {
+        const LEVELS: [f32; 8] = [0.350, 0.518, 0.962, 1.550, 1.094, 1.506, 1.962, 1.962];
+        const BLACK: f32 = 0.518;
+        const WHITE: f32 = 1.962;
+        const ATTENUATION: f32 = 0.746;
+
+        let in_color_phase = |p: usize, color: usize| (color + p + 8) % 12 < 6;
+        let gamma_fix = |v: f32| if v <= 0.0 { 0.0 } else { v.powf(2.2 / settings.gamma) };
+        let to_channel = |v: f32| (255.95 * gamma_fix(v)).clamp(0.0, 255.0) as u32;
+        let hue = settings.hue.to_radians();
+
+        let colors = (0..512usize).map(|pindex| {
+            let color = pindex & 0x0F;
+            let level = if color > 0x0D { 1 } else { (pindex >> 4) & 0x03 };
+            let lo_and_hi = [
+                LEVELS[level + 4 * (color == 0x00) as usize],
+                LEVELS[level + 4 * (color < 0x0D) as usize],
+            ];
+
+            let (mut y, mut i, mut q) = (0.0f32, 0.0f32, 0.0f32);
+            for p in 0..12 {
+                let mut spot = lo_and_hi[in_color_phase(p, color) as usize];
+                if ((pindex & 0x40) != 0 && in_color_phase(p, 12)) ||
+                   ((pindex & 0x80) != 0 && in_color_phase(p, 4)) ||
+                   ((pindex & 0x100) != 0 && in_color_phase(p, 8)) {
+                    spot *= ATTENUATION;
+                }
+
+                let mut v = (spot - BLACK) / (WHITE - BLACK);
+                v = (v - 0.5) * settings.contrast + 0.5;
+                v *= settings.brightness / 12.0;
+
+                let angle = std::f32::consts::PI / 6.0 * p as f32 + hue;
+                y += v;
+                i += v * angle.cos();
+                q += v * angle.sin();
+            }
+
+            i *= settings.saturation;
+            q *= settings.saturation;
+
+            let r = to_channel(y + 0.946882 * i + 0.623557 * q);
+            let g = to_channel(y - 0.274788 * i - 0.635691 * q);
+            let b = to_channel(y - 1.108545 * i + 1.709007 * q);
+            (r << 16) | (g << 8) | b
+        }).collect();
+
+        Palette { colors: colors }
+    }
+
+    #[inline]
+    pub fn color(&self, pindex: u16) -> u32 {
+        self.colors[(pindex & 0x1FF) as usize]
     }
 }
 
@@ -29,5 +226,124 @@ const PALETTE_2C02: [u32; 64] = [
     0xE4E594, 0xCFEF96, 0xBDF4AB, 0xB3F3CC, 0xB5EBF2, 0xB8B8B8, 0x000000, 0x000000,
   ];
 
+// 3 bit per channel RGB palette, each channel scaled to 8 bits
+const PALETTE_2C03: [u32; 64] = [
+    0x6D6D6D, 0x002491, 0x0000DA, 0x6D48DA, 0x91006D, 0xB6006D, 0xB62400, 0x914800,
+    0x6D4800, 0x244800, 0x006D24, 0x009100, 0x004848, 0x000000, 0x000000, 0x000000,
+    0xB6B6B6, 0x006DDA, 0x0048FF, 0x9100FF, 0xB600FF, 0xFF0091, 0xFF0000, 0xDA6D00,
+    0x916D00, 0x249100, 0x009100, 0x00B66D, 0x009191, 0x000000, 0x000000, 0x000000,
+    0xFFFFFF, 0x6DB6FF, 0x9191FF, 0xDA6DFF, 0xFF00FF, 0xFF6DFF, 0xFF9100, 0xFFB600,
+    0xDADA00, 0x6DDA00, 0x00FF00, 0x48FFDA, 0x00FFFF, 0x000000, 0x000000, 0x000000,
+    0xFFFFFF, 0xB6DAFF, 0xDAB6FF, 0xFFB6FF, 0xFF91FF, 0xFFB6B6, 0xFFDA91, 0xFFFF48,
+    0xFFFF6D, 0xB6FF48, 0x91FF6D, 0x48FFDA, 0x91DAFF, 0x000000, 0x000000, 0x000000,
+  ];
+
+// 2C04 palette index -> 2C03 palette index
+const RP2C04_0001_LUT: [u8; 64] = [
+    0x35, 0x23, 0x16, 0x22, 0x1C, 0x09, 0x1D, 0x15, 0x20, 0x00, 0x27, 0x05, 0x04, 0x28, 0x08, 0x20,
+    0x21, 0x3E, 0x1F, 0x29, 0x3C, 0x32, 0x36, 0x12, 0x3F, 0x2B, 0x2E, 0x1E, 0x3D, 0x2D, 0x24, 0x01,
+    0x0E, 0x31, 0x33, 0x2A, 0x2C, 0x0C, 0x1B, 0x14, 0x2E, 0x07, 0x34, 0x06, 0x13, 0x02, 0x26, 0x2E,
+    0x2E, 0x19, 0x10, 0x0A, 0x39, 0x03, 0x37, 0x17, 0x0F, 0x11, 0x0B, 0x0D, 0x38, 0x25, 0x18, 0x3A,
+  ];
+
+const RP2C04_0002_LUT: [u8; 64] = [
+    0x2E, 0x27, 0x18, 0x39, 0x3A, 0x25, 0x1C, 0x31, 0x16, 0x13, 0x38, 0x34, 0x20, 0x23, 0x3C, 0x0B,
+    0x0F, 0x21, 0x06, 0x3D, 0x1B, 0x29, 0x1E, 0x22, 0x1D, 0x24, 0x0E, 0x2B, 0x32, 0x08, 0x2E, 0x03,
+    0x04, 0x36, 0x26, 0x33, 0x11, 0x1F, 0x10, 0x02, 0x14, 0x3F, 0x00, 0x09, 0x12, 0x2E, 0x28, 0x20,
+    0x3E, 0x0D, 0x2A, 0x17, 0x0C, 0x01, 0x15, 0x19, 0x2E, 0x2C, 0x07, 0x37, 0x35, 0x05, 0x0A, 0x2D,
+  ];
+
+const RP2C04_0003_LUT: [u8; 64] = [
+    0x14, 0x25, 0x3A, 0x10, 0x0B, 0x20, 0x31, 0x09, 0x01, 0x2E, 0x36, 0x08, 0x15, 0x3D, 0x3E, 0x3C,
+    0x22, 0x1C, 0x05, 0x12, 0x19, 0x18, 0x17, 0x1B, 0x00, 0x03, 0x2E, 0x02, 0x16, 0x06, 0x34, 0x35,
+    0x23, 0x0F, 0x0E, 0x37, 0x0D, 0x27, 0x26, 0x20, 0x29, 0x04, 0x21, 0x24, 0x11, 0x2D, 0x2E, 0x1F,
+    0x2C, 0x1E, 0x39, 0x33, 0x07, 0x2A, 0x28, 0x1D, 0x0A, 0x2E, 0x32, 0x38, 0x13, 0x2B, 0x3F, 0x0C,
+  ];
+
+const RP2C04_0004_LUT: [u8; 64] = [
+    0x18, 0x03, 0x1C, 0x28, 0x2E, 0x35, 0x01, 0x17, 0x10, 0x1F, 0x2A, 0x0E, 0x36, 0x37, 0x0B, 0x39,
+    0x25, 0x1E, 0x12, 0x34, 0x2E, 0x1D, 0x06, 0x26, 0x3E, 0x1B, 0x22, 0x19, 0x04, 0x2E, 0x3A, 0x21,
+    0x05, 0x0A, 0x07, 0x02, 0x13, 0x14, 0x00, 0x15, 0x0C, 0x3D, 0x11, 0x0F, 0x0D, 0x38, 0x2D, 0x24,
+    0x33, 0x20, 0x08, 0x16, 0x3F, 0x2B, 0x20, 0x3C, 0x2E, 0x27, 0x23, 0x31, 0x29, 0x32, 0x2C, 0x09,
+  ];
+
+#[cfg(test)]
+mod test {
+    use super::*;
 
+    #[test]
+    fn test_2c04_palettes_are_scrambled_2c03() {
+        // every 2C04 variant can display the full set of 2C03 colors
+        for lut in [RP2C04_0001_LUT, RP2C04_0002_LUT, RP2C04_0003_LUT, RP2C04_0004_LUT].iter() {
+            for c in PALETTE_2C03.iter() {
+                assert!(lut.iter().any(|i| PALETTE_2C03[*i as usize] == *c));
+            }
+        }
+
+        assert_eq!(palette_color(0x09, PaletteSource::Ppu_2c04_0001), palette_color(0x00, PaletteSource::Ppu_2c03));
+        assert_eq!(palette_color(0x2A, PaletteSource::Ppu_2c04_0002), palette_color(0x00, PaletteSource::Ppu_2c03));
+    }
+
+    #[test]
+    fn test_rgb_emphasis() {
+        assert_eq!(palette_color(0x00, PaletteSource::Ppu_2c03), 0x6D6D6D);
+        // red emphasis
+        assert_eq!(palette_color(0x40, PaletteSource::Ppu_2c03), 0xFF6D6D);
+        // green + blue emphasis
+        assert_eq!(palette_color(0x180, PaletteSource::Ppu_2c05), 0x6DFFFF);
+        // all channels saturate to white
+        assert_eq!(palette_color(0x1C0 | 0x0F, PaletteSource::Ppu_2c03), 0xFFFFFF);
+    }
+
+    #[test]
+    fn test_2c02_emphasis() {
+        assert_eq!(palette_color(0x30, PaletteSource::Ppu_2c02), 0xFFFEFF);
+        // red emphasis attenuates green and blue
+        assert_eq!(palette_color(0x40 | 0x30, PaletteSource::Ppu_2c02), 0xFFCFD0);
+        // all emphasis bits attenuate every channel
+        assert_eq!(palette_color(0x1C0 | 0x30, PaletteSource::Ppu_2c02), 0xD0CFD0);
+        // black is not affected
+        assert_eq!(palette_color(0x1C0 | 0x0F, PaletteSource::Ppu_2c02), 0x000000);
+        assert_eq!(palette_color(0x180 | 0x00, PaletteSource::Ppu_2c02), 0x536666);
+    }
 
+    #[test]
+    fn test_pal_files() {
+        let mut data = vec![0u8; 192];
+        data[0x30 * 3] = 0xFF;
+        data[0x30 * 3 + 1] = 0xFF;
+        data[0x30 * 3 + 2] = 0xFF;
+        let palette = Palette::from_pal_bytes(&data).unwrap();
+        assert_eq!(palette.color(0x30), 0xFFFFFF);
+        assert_eq!(palette.color(0x40 | 0x30), 0xFFD0D0);
+
+        let data: Vec<u8> = (0..1536).map(|i| (i / 3) as u8).collect();
+        let palette = Palette::from_pal_bytes(&data).unwrap();
+        assert_eq!(palette.color(0x1FF), 0xFFFFFF);
+        assert_eq!(palette.color(0x101), 0x010101);
+
+        assert!(Palette::from_pal_bytes(&[0u8; 100]).is_err());
+        assert_eq!(Palette::from_source(PaletteSource::Ppu_2c02).color(0x40 | 0x30), 0xFFCFD0);
+    }
+
+    #[test]
+    fn test_generated_palette() {
+        let palette = Palette::generate(PaletteSettings::default());
+        let channels = |c: u32| ((c >> 16) & 0xFF, (c >> 8) & 0xFF, c & 0xFF);
+
+        assert_eq!(palette.color(0x0F), 0x000000);
+        assert_eq!(palette.color(0x1D), 0x000000);
+        let (r, g, b) = channels(palette.color(0x30));
+        assert!(r > 0xF0 && g > 0xF0 && b > 0xF0);
+        let (r, g, b) = channels(palette.color(0x16));
+        assert!(r > g && r > b);
+        let (r, g, b) = channels(palette.color(0x12));
+        assert!(b > r && b > g);
+
+        // emphasis darkens, no saturation gives grays
+        assert!(palette.color(0x1C0 | 0x30) < palette.color(0x30));
+        let gray = Palette::generate(PaletteSettings { saturation: 0.0, ..PaletteSettings::default() });
+        let (r, g, b) = channels(gray.color(0x16));
+        assert!(r == g && g == b);
+    }
+}
@@ -10,6 +10,9 @@ mod scanline_prerender;
 mod scanline_render;
 mod scanline_postrender;
 mod scanline_vblank;
+pub mod viewers;
+
+use crate::palette::PaletteSource;
 
 bitflags! {
     pub struct Ctrl: u8 {
@@ -25,6 +28,59 @@ impl Default for Ctrl {
     }
 }
 
+/*
+    PPU revisions
+    RP2C02 - NTSC composite video
+    RP2C03 - RGB (PlayChoice-10, Famicom Titler)
+    RP2C04 - RGB (Vs. System) with scrambled palettes
+    RC2C05 - RGB (Vs. System) with PPUCTRL/PPUMASK swapped and an id in the low bits of PPUSTATUS
+*/
+#[allow(non_camel_case_types)]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PpuModel {
+    Rp2c02,
+    Rp2c03,
+    Rp2c04_0001,
+    Rp2c04_0002,
+    Rp2c04_0003,
+    Rp2c04_0004,
+    Rc2c05_01,
+    Rc2c05_02,
+    Rc2c05_03,
+    Rc2c05_04,
+    Rc2c05_05,
+}
+
+impl PpuModel {
+    pub fn palette_source(&self) -> PaletteSource {
+        match self {
+            PpuModel::Rp2c02 => PaletteSource::Ppu_2c02,
+            PpuModel::Rp2c03 => PaletteSource::Ppu_2c03,
+            PpuModel::Rp2c04_0001 => PaletteSource::Ppu_2c04_0001,
+            PpuModel::Rp2c04_0002 => PaletteSource::Ppu_2c04_0002,
+            PpuModel::Rp2c04_0003 => PaletteSource::Ppu_2c04_0003,
+            PpuModel::Rp2c04_0004 => PaletteSource::Ppu_2c04_0004,
+            PpuModel::Rc2c05_01 | PpuModel::Rc2c05_02 | PpuModel::Rc2c05_03 |
+            PpuModel::Rc2c05_04 | PpuModel::Rc2c05_05 => PaletteSource::Ppu_2c05,
+        }
+    }
+
+    pub fn swaps_ctrl_mask(&self) -> bool {
+        matches!(self, PpuModel::Rc2c05_01 | PpuModel::Rc2c05_02 | PpuModel::Rc2c05_03 |
+            PpuModel::Rc2c05_04 | PpuModel::Rc2c05_05)
+    }
+
+    // value returned in the lower 6 bits of PPUSTATUS instead of the io latch
+    pub fn status_id(&self) -> Option<u8> {
+        match self {
+            PpuModel::Rc2c05_01 | PpuModel::Rc2c05_04 => Some(0x1B),
+            PpuModel::Rc2c05_02 => Some(0x3D),
+            PpuModel::Rc2c05_03 => Some(0x1C),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Pinout {
     pub ctrl: Ctrl,
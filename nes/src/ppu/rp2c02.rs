@@ -1,6 +1,6 @@
 use std::{borrow::Borrow, thread::sleep};
 
-use super::{Context, Pinout};
+use super::{Context, Pinout, PpuModel};
 use super::bus::Bus;
 use super::palette_ram::PaletteRam;
 use super::background::Background;
@@ -13,34 +13,47 @@ use super::ppu_operations::*;
 use crate::mappers::Mapper;
 
 const WRITE_BLOCK_CYCLES: u64 = 29658 * 3;
+// NTSC color subcarrier is 12 phases long and each PPU dot spans 8 of them
+const DOT_SUBCARRIER_PHASES: u64 = 8;
+const SUBCARRIER_PHASES: u64 = 12;
 
 #[derive(Clone, Copy)]
 pub struct Rp2c02 {
+    model: PpuModel,
     context: Context,
     bus: Bus,
     palette_ram: PaletteRam,
     bg: Background,
     sp: Sprites,
+    scanline_phases: [u8; 240],
 }
 
 impl Rp2c02 {
     pub fn from_power_on() -> Rp2c02 {
+        Rp2c02::from_power_on_as(PpuModel::Rp2c02)
+    }
+
+    pub fn from_power_on_as(model: PpuModel) -> Rp2c02 {
         Rp2c02 {
+            model: model,
             context: Context::new(),
             bus: Bus::new(),
             palette_ram: PaletteRam::from_power_on(),
             bg: Background::new(),
             sp: Sprites::new(),
+            scanline_phases: [0; 240],
         }
     }
 
     pub fn from_reset(&self) -> Rp2c02 {
         let mut rp2c02 = Rp2c02 {
+            model: self.model,
             context: Context::new(),
             bus: Bus::new(),
             palette_ram: self.palette_ram.from_reset(),
             bg: Background::new(),
             sp: Sprites::new(),
+            scanline_phases: [0; 240],
         };
 
         // ppuaddr is unchanged after reset
@@ -49,6 +62,10 @@ impl Rp2c02 {
         rp2c02
     }
 
+    pub fn model(&self) -> PpuModel {
+        self.model
+    }
+
     pub fn frame_number(&self) -> u64 {
         self.context.frame
     }
@@ -57,12 +74,26 @@ impl Rp2c02 {
         self.context.last_frame_cycle
     }
 
+    // subcarrier phase (0-11) at the first pixel of each visible scanline, used for NTSC artifacts
+    pub fn scanline_phases(&self) -> &[u8] {
+        &self.scanline_phases
+    }
+
     pub fn read_port(&self, mut pinout: mos::Pinout) -> mos::Pinout {
         pinout.data =  self.context.io_db;
         pinout
     }
 
     pub fn write_ppuctrl(&mut self, pinout: mos::Pinout) -> mos::Pinout {
+        // 2C05 has PPUCTRL at 0x2001 and PPUMASK at 0x2000
+        if self.model.swaps_ctrl_mask() { self.ppumask_write(pinout) } else { self.ppuctrl_write(pinout) }
+    }
+
+    pub fn write_ppumask(&mut self, pinout: mos::Pinout) -> mos::Pinout {
+        if self.model.swaps_ctrl_mask() { self.ppuctrl_write(pinout) } else { self.ppumask_write(pinout) }
+    }
+
+    fn ppuctrl_write(&mut self, pinout: mos::Pinout) -> mos::Pinout {
         self.context.io_db = pinout.data;
         if self.context.write_block {
             return pinout;
@@ -74,7 +105,7 @@ impl Rp2c02 {
         pinout
     }
 
-    pub fn write_ppumask(&mut self, pinout: mos::Pinout) -> mos::Pinout{
+    fn ppumask_write(&mut self, pinout: mos::Pinout) -> mos::Pinout {
         self.context.io_db = pinout.data;
         if self.context.write_block {
             return pinout;
@@ -88,6 +119,10 @@ impl Rp2c02 {
         self.context.read_2002_cycle = self.context.cycle;
         self.context.addr_reg.io_read_2002();
         pinout.data = self.context.status_reg.io_read(self.context.io_db);
+        if let Some(id) = self.model.status_id() {
+            // 2C05 returns an id in place of the open bus bits
+            pinout.data = (pinout.data & 0xC0) | id;
+        }
         pinout
     }
 
@@ -175,6 +210,32 @@ impl Rp2c02 {
         pinout
     }
 
+    // visible and pre-render scanlines with background or sprites enabled
+    pub fn is_rendering(&mut self) -> bool {
+        is_rendering(&mut self.context)
+    }
+
+    // palette ram, oam and secondary oam without the side effects of the ports
+    pub fn peek_palette(&self, index: usize) -> u8 {
+        self.palette_ram.read(index as u16)
+    }
+
+    pub fn poke_palette(&mut self, index: usize, data: u8) {
+        self.palette_ram.write(index as u16, data);
+    }
+
+    pub fn oam(&mut self) -> &mut [u8; 256] {
+        self.sp.primary_oam()
+    }
+
+    pub fn secondary_oam(&mut self) -> &mut [u8; 32] {
+        self.sp.secondary_oam()
+    }
+
+    pub fn vram_address(&self) -> u16 {
+        self.context.addr_reg.vram_address()
+    }
+
     pub fn get_context(&self) -> Context {
         self.context
     }
@@ -194,6 +255,10 @@ impl Rp2c02 {
             self.context.write_block = false;
         }
 
+        if self.context.vpos < 240 && self.context.hpos == 1 {
+            self.scanline_phases[self.context.vpos as usize] = ((self.context.cycle * DOT_SUBCARRIER_PHASES) % SUBCARRIER_PHASES) as u8;
+        }
+
         match self.context.vpos {
             261 if self.context.mask_reg.rendering_enabled() => { scanline_prerender_tick(&mut self.context, &mut self.bus, &mut self.bg, &mut self.sp, mapper); }
             261 => { scanline_prerender_nonvisible_tick(&mut self.context, &mut self.bus, mapper); }
@@ -216,6 +281,7 @@ mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
     use crate::mappers::*;
+    use crate::ppu::ppu_registers::StatusRegister;
     use mos::Pinout;
 
     #[test]
@@ -225,4 +291,33 @@ mod tests {
         let mut cpu_pinout = Pinout::new();
         
     }
+
+    #[test]
+    fn test_2c05_registers() {
+        let mut ppu = Rp2c02::from_power_on_as(PpuModel::Rc2c05_02);
+        ppu.context.write_block = false;
+        let mut cpu_pinout = Pinout::new();
+
+        // 0x2000 is PPUMASK
+        cpu_pinout.data = 0x1E;
+        cpu_pinout = ppu.write_ppuctrl(cpu_pinout);
+        assert_eq!(ppu.context.mask_reg.bits(), 0x1E);
+        assert_eq!(ppu.context.control_reg.bits(), 0x00);
+
+        // 0x2001 is PPUCTRL
+        cpu_pinout.data = 0x80;
+        cpu_pinout = ppu.write_ppumask(cpu_pinout);
+        assert_eq!(ppu.context.control_reg.bits(), 0x80);
+        assert_eq!(ppu.context.mask_reg.bits(), 0x1E);
+
+        ppu.context.status_reg.set(StatusRegister::VBLANK_STARTED, true);
+        cpu_pinout = ppu.read_ppustatus(cpu_pinout);
+        assert_eq!(cpu_pinout.data, 0x80 | 0x3D);
+
+        let mut ppu = Rp2c02::from_power_on();
+        cpu_pinout.data = 0xFF;
+        cpu_pinout = ppu.write_ppustatus(cpu_pinout);
+        cpu_pinout = ppu.read_ppustatus(cpu_pinout);
+        assert_eq!(cpu_pinout.data, 0x1F);
+    }
 }
@@ -118,6 +118,15 @@ impl Sprites {
 		}
 	}
 
+	// direct access for the debugger
+	pub fn primary_oam(&mut self) -> &mut [u8; 256] {
+		&mut self.primary_oam
+	}
+
+	pub fn secondary_oam(&mut self) -> &mut [u8; 32] {
+		&mut self.secondary_oam
+	}
+
 	pub fn write_oamaddr_reg(&mut self, data: u8) {
 		self.oam_addr = data as usize;
 	}
@@ -296,7 +305,7 @@ impl Sprites {
 			self.sprites[sprite_index].xpos_counter = self.secondary_oam[i+3];
 			self.sprites[sprite_index].attribute = self.secondary_oam[i+2];
 			self.sprites[sprite_index].tile_index = self.secondary_oam[i+1];
-			self.sprites[sprite_index].sprite_line = (context.vpos - (self.secondary_oam[i+0] as u16)) as u8;
+			self.sprites[sprite_index].sprite_line = context.vpos.wrapping_sub(self.secondary_oam[i+0] as u16) as u8;
 			self.sprites[sprite_index].valid_sprite = if sprite_index < (self.sprite_count as usize) {
 				 true
 			}
@@ -0,0 +1,206 @@
+use super::rp2c02::Rp2c02;
+use crate::mappers::{self, Mapper};
+use crate::palette::Palette;
+
+/*
+    Debug views of the ppu memory as 0RGB buffers, read through the mapper without clocking anything
+    Nametables    - 512x480, the four nametables through the current mirroring, scroll window outlined
+    Pattern tables - 256x128, $0000 and $1000 side by side with one of the 8 palettes
+    OAM           - 128x128, the 64 sprites in an 8x8 grid of 16x16 cells, flips applied
+    Palette RAM   - 256x32, the background palettes on top and the sprite palettes below
+*/
+pub const NAMETABLES_WIDTH: usize = 512;
+pub const NAMETABLES_HEIGHT: usize = 480;
+pub const PATTERN_TABLES_WIDTH: usize = 256;
+pub const PATTERN_TABLES_HEIGHT: usize = 128;
+pub const OAM_WIDTH: usize = 128;
+pub const OAM_HEIGHT: usize = 128;
+pub const PALETTE_RAM_WIDTH: usize = 256;
+pub const PALETTE_RAM_HEIGHT: usize = 32;
+
+const SCROLL_OVERLAY_COLOR: u32 = 0xFF00FF;
+const OAM_BACKGROUND_COLOR: u32 = 0x202020;
+const OAM_CELL: usize = 16;
+const PALETTE_CELL: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OamSprite {
+    pub index: u8,
+    pub x: u8,
+    // sprites are drawn a line below y
+    pub y: u8,
+    pub tile: u8,
+    // 0-3, sprite palettes start at $3F10
+    pub palette: u8,
+    pub behind_background: bool,
+    pub hflip: bool,
+    pub vflip: bool,
+}
+
+pub fn oam_sprites(ppu: &mut Rp2c02) -> Vec<OamSprite> {
+    ppu.oam().chunks(4).enumerate().map(|(index, sprite)| OamSprite {
+        index: index as u8,
+        x: sprite[3],
+        y: sprite[0],
+        tile: sprite[1],
+        palette: sprite[2] & 0x03,
+        behind_background: sprite[2] & 0x20 != 0,
+        hflip: sprite[2] & 0x40 != 0,
+        vflip: sprite[2] & 0x80 != 0,
+    }).collect()
+}
+
+// 2 bit pixel of a tile row
+fn tile_pixel(mapper: &dyn Mapper, tile_address: u16, x: usize, y: usize) -> u8 {
+    let lo = mappers::peek_ppu(mapper, tile_address + y as u16);
+    let hi = mappers::peek_ppu(mapper, tile_address + y as u16 + 8);
+    let shift = 7 - x;
+    (((hi >> shift) & 1) << 1) | ((lo >> shift) & 1)
+}
+
+// pixel 0 is the backdrop color whatever the palette
+fn pixel_color(ppu: &Rp2c02, palette: &Palette, palette_index: u8, pixel: u8) -> u32 {
+    let entry = if pixel == 0 { 0 } else { (palette_index as usize) * 4 + pixel as usize };
+    palette.color(ppu.peek_palette(entry) as u16)
+}
+
+pub fn render_nametables(ppu: &mut Rp2c02, mapper: &dyn Mapper, palette: &Palette, buffer: &mut [u32], scroll_overlay: bool) {
+    let context = ppu.get_context();
+    let pattern_table = context.control_reg.background_table_address();
+
+    for y in 0..NAMETABLES_HEIGHT {
+        for x in 0..NAMETABLES_WIDTH {
+            let nametable = 0x2000 + (((y / 240) << 1) | (x / 256)) as u16 * 0x400;
+            let (tx, ty) = ((x % 256) / 8, (y % 240) / 8);
+            let tile = mappers::peek_ppu(mapper, nametable + (ty * 32 + tx) as u16);
+            let attribute = mappers::peek_ppu(mapper, nametable + 0x3C0 + ((ty / 4) * 8 + tx / 4) as u16);
+            let palette_index = (attribute >> (((ty & 2) << 1) | (tx & 2))) & 0x03;
+
+            let pixel = tile_pixel(mapper, pattern_table + tile as u16 * 16, x % 8, y % 8);
+            buffer[y * NAMETABLES_WIDTH + x] = pixel_color(ppu, palette, palette_index, pixel);
+        }
+    }
+
+    if scroll_overlay {
+        // t holds the scroll the next frame starts with
+        let t = context.addr_reg.t as usize;
+        let scroll_x = ((t & 0x1F) << 3 | context.addr_reg.x as usize) + ((t >> 10) & 1) * 256;
+        let scroll_y = (((t >> 5) & 0x1F) << 3 | (t >> 12) & 0x07) + ((t >> 11) & 1) * 240;
+
+        for i in 0..256 {
+            let x = (scroll_x + i) % NAMETABLES_WIDTH;
+            buffer[(scroll_y % NAMETABLES_HEIGHT) * NAMETABLES_WIDTH + x] = SCROLL_OVERLAY_COLOR;
+            buffer[((scroll_y + 239) % NAMETABLES_HEIGHT) * NAMETABLES_WIDTH + x] = SCROLL_OVERLAY_COLOR;
+        }
+        for i in 0..240 {
+            let y = (scroll_y + i) % NAMETABLES_HEIGHT;
+            buffer[y * NAMETABLES_WIDTH + scroll_x % NAMETABLES_WIDTH] = SCROLL_OVERLAY_COLOR;
+            buffer[y * NAMETABLES_WIDTH + (scroll_x + 255) % NAMETABLES_WIDTH] = SCROLL_OVERLAY_COLOR;
+        }
+    }
+}
+
+// palette_index 0-3 are the background palettes and 4-7 the sprite palettes
+pub fn render_pattern_tables(ppu: &mut Rp2c02, mapper: &dyn Mapper, palette: &Palette, buffer: &mut [u32], palette_index: u8) {
+    let palette_index = palette_index & 0x07;
+    for y in 0..PATTERN_TABLES_HEIGHT {
+        for x in 0..PATTERN_TABLES_WIDTH {
+            let table = (x / 128) as u16 * 0x1000;
+            let tile = (((y / 8) * 16) + (x % 128) / 8) as u16;
+            let pixel = tile_pixel(mapper, table + tile * 16, x % 8, y % 8);
+            buffer[y * PATTERN_TABLES_WIDTH + x] = pixel_color(ppu, palette, palette_index, pixel);
+        }
+    }
+}
+
+pub fn render_oam(ppu: &mut Rp2c02, mapper: &dyn Mapper, palette: &Palette, buffer: &mut [u32]) {
+    let context = ppu.get_context();
+    let height = context.control_reg.sprite_size() as usize;
+    buffer[..OAM_WIDTH * OAM_HEIGHT].iter_mut().for_each(|p| *p = OAM_BACKGROUND_COLOR);
+
+    for sprite in oam_sprites(ppu) {
+        let cell_x = (sprite.index as usize % 8) * OAM_CELL + (OAM_CELL - 8) / 2;
+        let cell_y = (sprite.index as usize / 8) * OAM_CELL + (OAM_CELL - height) / 2;
+
+        for y in 0..height {
+            let row = if sprite.vflip { height - 1 - y } else { y };
+            let tile_address = match height {
+                // bit 0 of the tile selects the pattern table of 8x16 sprites
+                16 => ((sprite.tile as u16 & 1) * 0x1000) + ((sprite.tile & 0xFE) as u16 + (row / 8) as u16) * 16,
+                _ => context.control_reg.sprite_table_address() + sprite.tile as u16 * 16,
+            };
+
+            for x in 0..8 {
+                let column = if sprite.hflip { 7 - x } else { x };
+                let pixel = tile_pixel(mapper, tile_address, column, row % 8);
+                if pixel != 0 {
+                    buffer[(cell_y + y) * OAM_WIDTH + cell_x + x] = pixel_color(ppu, palette, 4 + sprite.palette, pixel);
+                }
+            }
+        }
+    }
+}
+
+pub fn render_palette_ram(ppu: &mut Rp2c02, palette: &Palette, buffer: &mut [u32]) {
+    for y in 0..PALETTE_RAM_HEIGHT {
+        for x in 0..PALETTE_RAM_WIDTH {
+            let entry = (y / PALETTE_CELL) * 16 + x / PALETTE_CELL;
+            buffer[y * PALETTE_RAM_WIDTH + x] = palette.color(ppu.peek_palette(entry) as u16);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mappers::mapper_debug::MapperDebug;
+    use crate::palette::PaletteSource;
+
+    #[test]
+    fn test_viewers() {
+        let mut mapper = MapperDebug::new();
+        let mut ppu = Rp2c02::from_power_on();
+        let palette = Palette::from_source(PaletteSource::Ppu_2c02);
+
+        // tile 1 is a solid block of color 3, horizontal mirroring puts $2400 over $2000
+        for i in 0..8 { mapper.poke_chr(0x10 + i, 0xFF); mapper.poke_chr(0x18 + i, 0xFF); }
+        mapper.poke_nt(0x0021, 0x01);
+        mapper.poke_nt(0x03C0, 0x02);
+        ppu.poke_palette(0x00, 0x0F);
+        ppu.poke_palette(0x0B, 0x30);
+        ppu.poke_palette(0x13, 0x16);
+
+        let mut nametables = vec![0; NAMETABLES_WIDTH * NAMETABLES_HEIGHT];
+        render_nametables(&mut ppu, &mapper, &palette, &mut nametables, false);
+        assert_eq!(nametables[8 * NAMETABLES_WIDTH + 8], palette.color(0x30));
+        assert_eq!(nametables[8 * NAMETABLES_WIDTH + 256 + 8], palette.color(0x30));
+        assert_eq!(nametables[0], palette.color(0x0F));
+
+        // scroll at 0,0 outlines the first nametable
+        render_nametables(&mut ppu, &mapper, &palette, &mut nametables, true);
+        assert_eq!(nametables[0], SCROLL_OVERLAY_COLOR);
+        assert_eq!(nametables[239 * NAMETABLES_WIDTH + 255], SCROLL_OVERLAY_COLOR);
+        assert_ne!(nametables[240 * NAMETABLES_WIDTH + 256], SCROLL_OVERLAY_COLOR);
+
+        let mut pattern_tables = vec![0; PATTERN_TABLES_WIDTH * PATTERN_TABLES_HEIGHT];
+        render_pattern_tables(&mut ppu, &mapper, &palette, &mut pattern_tables, 2);
+        assert_eq!(pattern_tables[8], palette.color(0x30));
+        assert_eq!(pattern_tables[0], palette.color(0x0F));
+
+        // sprite 1 uses tile 1 with sprite palette 0
+        ppu.oam()[4..8].copy_from_slice(&[0x10, 0x01, 0xC0, 0x20]);
+        let sprites = oam_sprites(&mut ppu);
+        assert_eq!((sprites[1].x, sprites[1].y, sprites[1].tile), (0x20, 0x10, 0x01));
+        assert!(sprites[1].hflip && sprites[1].vflip && !sprites[1].behind_background);
+
+        let mut oam = vec![0; OAM_WIDTH * OAM_HEIGHT];
+        render_oam(&mut ppu, &mapper, &palette, &mut oam);
+        assert_eq!(oam[4 * OAM_WIDTH + OAM_CELL + 4], palette.color(0x16));
+        assert_eq!(oam[0], OAM_BACKGROUND_COLOR);
+
+        let mut palette_ram = vec![0; PALETTE_RAM_WIDTH * PALETTE_RAM_HEIGHT];
+        render_palette_ram(&mut ppu, &palette, &mut palette_ram);
+        assert_eq!(palette_ram[11 * PALETTE_CELL], palette.color(0x30));
+        assert_eq!(palette_ram[PALETTE_CELL * PALETTE_RAM_WIDTH + 3 * PALETTE_CELL], palette.color(0x16));
+    }
+}
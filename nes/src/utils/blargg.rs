@@ -0,0 +1,156 @@
+use crate::consoles::{Console, EmuError};
+use crate::consoles::nes_ntsc::NesNtsc;
+
+use std::path::Path;
+
+/*
+    Runs test roms that report through blargg's $6000 protocol
+    $6000 - status, $80 while running, $81 when the console needs a reset, otherwise the result code (0 is a pass)
+    $6001-$6003 - DE B0 61 once the status is valid
+    $6004 - zero terminated text output
+*/
+
+const STATUS_RUNNING: u8 = 0x80;
+const STATUS_NEEDS_RESET: u8 = 0x81;
+const SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+// reset has to be held at least 100ms
+const RESET_DELAY_FRAMES: u64 = 6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlarggResult {
+    Passed(String),
+    Failed(u8, String),
+    TimedOut(String),
+    CpuJammed(u16, String),
+}
+
+pub fn run_blargg_rom<P: AsRef<Path>>(rom_path: P, max_frames: u64) -> BlarggResult {
+    let mut nes = NesNtsc::new();
+    nes.load_rom(rom_path);
+
+    let mut reset_frame = None;
+    for frame in 0..max_frames {
+        if let Err(EmuError::CpuJammed { pc, .. }) = nes.execute_frame() {
+            return BlarggResult::CpuJammed(pc, read_message(&mut nes));
+        }
+
+        if !has_signature(&mut nes) {
+            continue;
+        }
+
+        match nes.read_wram(0x6000) {
+            STATUS_RUNNING => { }
+            STATUS_NEEDS_RESET => {
+                let start = *reset_frame.get_or_insert(frame);
+                if frame - start >= RESET_DELAY_FRAMES {
+                    nes.restart_console();
+                    reset_frame = None;
+                }
+            }
+            0 => return BlarggResult::Passed(read_message(&mut nes)),
+            code => return BlarggResult::Failed(code, read_message(&mut nes)),
+        }
+    }
+
+    BlarggResult::TimedOut(read_message(&mut nes))
+}
+
+fn has_signature(nes: &mut NesNtsc) -> bool {
+    SIGNATURE.iter().enumerate().all(|(i, b)| nes.read_wram(0x6001 + i as u16) == *b)
+}
+
+fn read_message(nes: &mut NesNtsc) -> String {
+    if !has_signature(nes) {
+        return String::new();
+    }
+
+    (0x6004..0x8000u16)
+        .map(|address| nes.read_wram(address))
+        .take_while(|c| *c != 0)
+        .map(|c| c as char)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // about a minute of emulated time, the longest single roms finish in under 30 seconds
+    const MAX_FRAMES: u64 = 3600;
+
+    fn run(rom: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../rustnes-dev/test_roms").join(rom);
+        match run_blargg_rom(&path, MAX_FRAMES) {
+            BlarggResult::Passed(_) => { }
+            BlarggResult::Failed(code, message) => panic!("{} failed with code {}\n{}", rom, code, message),
+            BlarggResult::TimedOut(message) => panic!("{} timed out\n{}", rom, message),
+            BlarggResult::CpuJammed(pc, message) => panic!("{} jammed the cpu at ${:04X}\n{}", rom, pc, message),
+        }
+    }
+
+    macro_rules! blargg_tests {
+        ($($(#[$attr:meta])* $name:ident: $rom:expr,)*) => {
+            $(
+                #[test]
+                $(#[$attr])*
+                fn $name() {
+                    run($rom);
+                }
+            )*
+        }
+    }
+
+    // ignored roms are known failures, run them with --ignored to track accuracy work
+    blargg_tests! {
+        apu_reset_4015_cleared: "apu_reset/4015_cleared.nes",
+        apu_reset_irq_flag_cleared: "apu_reset/irq_flag_cleared.nes",
+        #[ignore = "no apu"] apu_reset_4017_written: "apu_reset/4017_written.nes",
+        #[ignore = "no apu"] apu_test_1_len_ctr: "apu_test/rom_singles/1-len_ctr.nes",
+        #[ignore = "no apu"] apu_test_3_irq_flag: "apu_test/rom_singles/3-irq_flag.nes",
+        #[ignore = "no apu"] apu_test_7_dmc_basics: "apu_test/rom_singles/7-dmc_basics.nes",
+
+        cpu_dummy_writes_oam: "cpu_dummy_writes/cpu_dummy_writes_oam.nes",
+        cpu_dummy_writes_ppumem: "cpu_dummy_writes/cpu_dummy_writes_ppumem.nes",
+        #[ignore = "apu registers don't return open bus"] cpu_exec_space_apu: "cpu_exec_space/test_cpu_exec_space_apu.nes",
+        cpu_exec_space_ppuio: "cpu_exec_space/test_cpu_exec_space_ppuio.nes",
+        cpu_reset_ram_after_reset: "cpu_reset/ram_after_reset.nes",
+        #[ignore = "A is $AA at power"] cpu_reset_registers: "cpu_reset/registers.nes",
+        #[ignore = "no apu frame irq"] cpu_interrupts_1_cli_latency: "cpu_interrupts_v2/rom_singles/1-cli_latency.nes",
+        #[ignore = "nmi hijacking brk"] cpu_interrupts_2_nmi_and_brk: "cpu_interrupts_v2/rom_singles/2-nmi_and_brk.nes",
+
+        instr_misc_01_abs_x_wrap: "instr_misc/rom_singles/01-abs_x_wrap.nes",
+        instr_misc_02_branch_wrap: "instr_misc/rom_singles/02-branch_wrap.nes",
+        #[ignore = "ROL abs,x dummy read"] instr_misc_03_dummy_reads: "instr_misc/rom_singles/03-dummy_reads.nes",
+
+        instr_test_01_basics: "instr_test-v5/rom_singles/01-basics.nes",
+        instr_test_02_implied: "instr_test-v5/rom_singles/02-implied.nes",
+        instr_test_03_immediate: "instr_test-v5/rom_singles/03-immediate.nes",
+        instr_test_04_zero_page: "instr_test-v5/rom_singles/04-zero_page.nes",
+        instr_test_05_zp_xy: "instr_test-v5/rom_singles/05-zp_xy.nes",
+        instr_test_06_absolute: "instr_test-v5/rom_singles/06-absolute.nes",
+        instr_test_07_abs_xy: "instr_test-v5/rom_singles/07-abs_xy.nes",
+        instr_test_08_ind_x: "instr_test-v5/rom_singles/08-ind_x.nes",
+        instr_test_09_ind_y: "instr_test-v5/rom_singles/09-ind_y.nes",
+        instr_test_10_branches: "instr_test-v5/rom_singles/10-branches.nes",
+        instr_test_11_stack: "instr_test-v5/rom_singles/11-stack.nes",
+        instr_test_12_jmp_jsr: "instr_test-v5/rom_singles/12-jmp_jsr.nes",
+        instr_test_13_rts: "instr_test-v5/rom_singles/13-rts.nes",
+        instr_test_14_rti: "instr_test-v5/rom_singles/14-rti.nes",
+        instr_test_15_brk: "instr_test-v5/rom_singles/15-brk.nes",
+        instr_test_16_special: "instr_test-v5/rom_singles/16-special.nes",
+        #[ignore = "needs apu length counter"] instr_timing_1_instr_timing: "instr_timing/rom_singles/1-instr_timing.nes",
+
+        oam_read: "oam_read/oam_read.nes",
+        #[ignore = "no open bus decay"] ppu_open_bus: "ppu_open_bus/ppu_open_bus.nes",
+        #[ignore = "transparent sprites hit"] ppu_sprite_hit_01_basics: "ppu_sprite_hit/rom_singles/01-basics.nes",
+        ppu_sprite_overflow_01_basics: "ppu_sprite_overflow/rom_singles/01-basics.nes",
+        #[ignore = "flag set for Y = 240"] ppu_sprite_overflow_02_details: "ppu_sprite_overflow/rom_singles/02-details.nes",
+        ppu_sprite_overflow_04_obscure: "ppu_sprite_overflow/rom_singles/04-obscure.nes",
+        ppu_vbl_nmi_01_vbl_basics: "ppu_vbl_nmi/rom_singles/01-vbl_basics.nes",
+        #[ignore = "vbl set one clock early"] ppu_vbl_nmi_02_vbl_set_time: "ppu_vbl_nmi/rom_singles/02-vbl_set_time.nes",
+        ppu_vbl_nmi_03_vbl_clear_time: "ppu_vbl_nmi/rom_singles/03-vbl_clear_time.nes",
+        #[ignore = "odd frame skip"] ppu_vbl_nmi_09_even_odd_frames: "ppu_vbl_nmi/rom_singles/09-even_odd_frames.nes",
+    }
+}
@@ -1,6 +1,7 @@
 use mos::{Pinout, Ctrl};
 use mos::core::*;
 use std::io::Write;
+use crate::debugger::symbols::SymbolTable;
 
 // log large enough to cover NTSC and PAL
 const LOG_SIZE: usize = 38000;
@@ -32,15 +33,22 @@ fn address_to_device(addr: u16, rw: bool) -> &'static str {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CpuTraceFormat {
+    Cycles,     // every bus cycle
+    Nestest,    // one line per instruction, see nestest_trace_logger
+}
+
+// PRG-ROM offset of the bus address is kept for the labels, banks can switch during the frame
 pub struct CpuTraceLogger {
-    cpu_cache: Vec<(Context, Pinout)>,
+    cpu_cache: Vec<(Context, Pinout, Option<usize>)>,
     size: usize,
 }
 
 impl CpuTraceLogger {
     pub fn new() -> CpuTraceLogger {
         CpuTraceLogger {
-            cpu_cache: vec![(Context::new(), Pinout::new()); LOG_SIZE],
+            cpu_cache: vec![(Context::new(), Pinout::new(), None); LOG_SIZE],
             size: 0,
         }
     }
@@ -50,16 +58,20 @@ impl CpuTraceLogger {
         self.size = 0;
     }
 
-    pub fn log(&mut self, context: Context, pinout: Pinout) {
+    pub fn log(&mut self, context: Context, pinout: Pinout, prg_offset: Option<usize>) {
         if self.size < LOG_SIZE {
-            self.cpu_cache[self.size] = (context, pinout);
+            self.cpu_cache[self.size] = (context, pinout, prg_offset);
             self.size += 1;
         }
     }
 
     pub fn output_log<W: Write>(&self, w: &mut W) {
-        
-        for (i, (c, p)) in self.cpu_cache.iter().enumerate() {
+        self.output_log_with_symbols(w, &SymbolTable::new());
+    }
+
+    // the label of the bus address is added after the device
+    pub fn output_log_with_symbols<W: Write>(&self, w: &mut W, symbols: &SymbolTable) {
+        for (i, (c, p, prg_offset)) in self.cpu_cache.iter().enumerate() {
             if self.size == 0 || i >= self.size {
                 break;
             }
@@ -80,7 +92,12 @@ impl CpuTraceLogger {
                 true => "   "
             };
 
-            writeln!(w, "{} {} {:04X} {:02X} {} {:04X}{}{:02X} {}\t\tA:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC: {}",
+            let label = match symbols.label(p.address, *prg_offset) {
+                Some(label) => format!(" {}", label),
+                None => String::new(),
+            };
+
+            writeln!(w, "{} {} {:04X} {:02X} {} {:04X}{}{:02X} {}{}\t\tA:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC: {}",
                 sync_str,
                 halt_str,
                 u16::from(c.pc),
@@ -90,6 +107,7 @@ impl CpuTraceLogger {
                 rw_str,
                 p.data,
                 address_to_device(p.address, rw),
+                label,
                 c.a,
                 c.x,
                 c.y,
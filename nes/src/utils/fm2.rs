@@ -0,0 +1,116 @@
+use crate::controllers::JoypadInput;
+use crate::consoles::EmuError;
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/*
+    FCEUX FM2 input movies, only the input log is used. Each input line looks like
+    |commands|RLDUTSBA|RLDUTSBA||
+    where any character other than '.' or ' ' is a pressed button
+*/
+
+bitflags! {
+    pub struct MovieCommand: u8 {
+        const SOFT_RESET         = 0b00000001;
+        const HARD_RESET         = 0b00000010;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MovieFrame {
+    pub commands: MovieCommand,
+    pub joypad1: JoypadInput,
+    pub joypad2: JoypadInput,
+}
+
+impl Default for MovieFrame {
+    fn default() -> Self {
+        MovieFrame {
+            commands: MovieCommand::empty(),
+            joypad1: JoypadInput::empty(),
+            joypad2: JoypadInput::empty(),
+        }
+    }
+}
+
+pub struct Fm2Movie {
+    frames: Vec<MovieFrame>,
+}
+
+impl Fm2Movie {
+    pub fn from_reader<R: BufRead>(r: R) -> Result<Fm2Movie, EmuError> {
+        let mut frames = Vec::new();
+        for line in r.lines() {
+            let line = line.map_err(|_| EmuError::LoadMovieError)?;
+            // header lines are "key value"
+            if !line.starts_with('|') {
+                continue;
+            }
+
+            let mut fields = line.split('|').skip(1);
+            let commands = fields.next().ok_or(EmuError::LoadMovieError)?;
+            let commands = commands.trim().parse::<u8>().map_err(|_| EmuError::LoadMovieError)?;
+
+            frames.push(MovieFrame {
+                commands: MovieCommand::from_bits_truncate(commands),
+                joypad1: parse_joypad(fields.next().unwrap_or(""))?,
+                joypad2: parse_joypad(fields.next().unwrap_or(""))?,
+            });
+        }
+
+        Ok(Fm2Movie { frames })
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Fm2Movie, EmuError> {
+        let file = File::open(path).map_err(|_| EmuError::LoadMovieError)?;
+        Fm2Movie::from_reader(BufReader::new(file))
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    // no input once the movie has ended
+    pub fn frame(&self, frame: usize) -> MovieFrame {
+        self.frames.get(frame).copied().unwrap_or_default()
+    }
+}
+
+fn parse_joypad(field: &str) -> Result<JoypadInput, EmuError> {
+    // empty for unconnected ports
+    if field.is_empty() {
+        return Ok(JoypadInput::empty());
+    }
+
+    if field.len() != 8 {
+        return Err(EmuError::LoadMovieError);
+    }
+
+    // RLDUTSBA is the JoypadInput bit order from high to low
+    let bits = field.chars().fold(0u8, |bits, c| (bits << 1) | (c != '.' && c != ' ') as u8);
+    Ok(JoypadInput::from_bits_truncate(bits))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fm2_input_log() {
+        let fm2 = "version 3\nromFilename donkey_kong\n|2|........|........||\n|0|...U...A|........||\n|0|R...T...|.L...SB.||\n|1|        |        ||\n";
+        let movie = Fm2Movie::from_reader(fm2.as_bytes()).unwrap();
+        assert_eq!(movie.frame_count(), 4);
+
+        assert_eq!(movie.frame(0).commands, MovieCommand::HARD_RESET);
+        assert_eq!(movie.frame(1).joypad1, JoypadInput::UP | JoypadInput::A);
+        assert_eq!(movie.frame(2).joypad1, JoypadInput::RIGHT | JoypadInput::START);
+        assert_eq!(movie.frame(2).joypad2, JoypadInput::LEFT | JoypadInput::SELECT | JoypadInput::B);
+        assert_eq!(movie.frame(3).commands, MovieCommand::SOFT_RESET);
+        assert_eq!(movie.frame(3).joypad1, JoypadInput::empty());
+        assert_eq!(movie.frame(10), MovieFrame::default());
+
+        assert!(Fm2Movie::from_reader("|0|..|........||\n".as_bytes()).is_err());
+    }
+}
@@ -2,4 +2,8 @@ pub mod cpu_trace_logger;
 pub mod ppu_trace_logger;
 pub mod frame_limiter;
 pub mod average_duration;
-pub mod paging;
\ No newline at end of file
+pub mod paging;
+pub mod fm2;
+pub mod blargg;
+pub mod nestest_trace_logger;
+pub mod visual_regression;
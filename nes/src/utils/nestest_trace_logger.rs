@@ -0,0 +1,274 @@
+use std::io::Write;
+use mos::disassembler::{disassemble, Mode::*};
+
+/*
+    One line per instruction in the format of nestest.log
+    C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+    Registers are the state before the instruction executes, unofficial opcodes are prefixed with '*'
+*/
+
+fn peek16<F: FnMut(u16) -> u8>(peek: &mut F, lo: u16, hi: u16) -> u16 {
+    u16::from_le_bytes([peek(lo), peek(hi)])
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CpuState {
+    pub pc: u16,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub p: u8,
+    pub sp: u8,
+    pub scanline: u16,
+    pub dot: u16,
+    pub cycle: u64,
+}
+
+// peek must not have side effects, the values shown are the ones before the instruction executes
+pub fn format_nestest_line<F: FnMut(u16) -> u8>(state: &CpuState, mut peek: F) -> String {
+    let pc = state.pc;
+    let instruction = disassemble(pc, &mut peek);
+    let hex = instruction.bytes().iter().map(|b| format!("{:02X}", b)).collect::<Vec<String>>().join(" ");
+    let lo = instruction.operand as u8;
+    let addr = instruction.operand;
+
+    let operand = match instruction.mode {
+        Imp => String::new(),
+        Acc => "A".to_string(),
+        Imm => format!("#${:02X}", lo),
+        Zp => format!("${:02X} = {:02X}", lo, peek(lo as u16)),
+        Zpx => {
+            let ea = lo.wrapping_add(state.x);
+            format!("${:02X},X @ {:02X} = {:02X}", lo, ea, peek(ea as u16))
+        }
+        Zpy => {
+            let ea = lo.wrapping_add(state.y);
+            format!("${:02X},Y @ {:02X} = {:02X}", lo, ea, peek(ea as u16))
+        }
+        Abs if instruction.target().is_some() => format!("${:04X}", addr),
+        Abs => format!("${:04X} = {:02X}", addr, peek(addr)),
+        Abx => {
+            let ea = addr.wrapping_add(state.x as u16);
+            format!("${:04X},X @ {:04X} = {:02X}", addr, ea, peek(ea))
+        }
+        Aby => {
+            let ea = addr.wrapping_add(state.y as u16);
+            format!("${:04X},Y @ {:04X} = {:02X}", addr, ea, peek(ea))
+        }
+        Ind => {
+            // high byte doesn't cross the page
+            let target = peek16(&mut peek, addr, (addr & 0xFF00) | (addr.wrapping_add(1) & 0x00FF));
+            format!("(${:04X}) = {:04X}", addr, target)
+        }
+        Izx => {
+            let ptr = lo.wrapping_add(state.x);
+            let ea = peek16(&mut peek, ptr as u16, ptr.wrapping_add(1) as u16);
+            format!("(${:02X},X) @ {:02X} = {:04X} = {:02X}", lo, ptr, ea, peek(ea))
+        }
+        Izy => {
+            let base = peek16(&mut peek, lo as u16, lo.wrapping_add(1) as u16);
+            let ea = base.wrapping_add(state.y as u16);
+            format!("(${:02X}),Y = {:04X} @ {:04X} = {:02X}", lo, base, ea, peek(ea))
+        }
+        Rel => instruction.operand_text(),
+    };
+
+    let disassembly = if operand.is_empty() { instruction.mnemonic.to_string() }
+        else { format!("{} {}", instruction.mnemonic, operand) };
+    let official = if instruction.unofficial { '*' } else { ' ' };
+
+    format!("{:04X}  {:<8} {}{:<32}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+        pc, hex, official, disassembly,
+        state.a, state.x, state.y, state.p | 0x20, state.sp,
+        state.scanline, state.dot, state.cycle)
+}
+
+// 341 dots on each of the 262 scanlines, rendering is off for all of nestest so there is no short frame
+const FRAME_DOTS: i64 = 341 * 262;
+
+fn ppu_dots(line: &str) -> Option<i64> {
+    let ppu = line.find("PPU:")?;
+    let (scanline, dot) = line.get(ppu + 4..ppu + 11)?.split_once(',')?;
+    Some(scanline.trim().parse::<i64>().ok()? * 341 + dot.trim().parse::<i64>().ok()?)
+}
+
+/*
+    The PPU starts in a random phase relative to the CPU on hardware, nestest.log starts at scanline 0
+    and this PPU in the pre-render line. Dots to add to the actual PPU position, taken from the first line.
+*/
+pub fn nestest_ppu_offset(expected: &str, actual: &str) -> Option<i64> {
+    Some((ppu_dots(expected)? - ppu_dots(actual)?).rem_euclid(FRAME_DOTS))
+}
+
+// everything but the operand annotations, the actual PPU position is moved by ppu_offset dots
+pub fn compare_nestest_lines(expected: &str, actual: &str, ppu_offset: i64) -> bool {
+    fn fields(line: &str) -> Option<(&str, &str, &str, &str, &str)> {
+        let registers = line.find("A:")?;
+        let ppu = line.find("PPU:")?;
+        let cycle = line.find("CYC:")?;
+        Some((
+            line.get(0..4)?,
+            line.get(6..14)?.trim(),
+            line.get(15..19)?.trim(),
+            line.get(registers..ppu)?.trim(),
+            line.get(cycle..)?.trim(),
+        ))
+    }
+
+    let ppu = match (ppu_dots(expected), ppu_dots(actual)) {
+        (Some(e), Some(a)) => e == (a + ppu_offset).rem_euclid(FRAME_DOTS),
+        _ => false,
+    };
+
+    match (fields(expected), fields(actual)) {
+        (Some(e), Some(a)) => ppu && e == a,
+        _ => false,
+    }
+}
+
+pub struct NestestTraceLogger {
+    lines: Vec<String>,
+}
+
+impl NestestTraceLogger {
+    pub fn new() -> NestestTraceLogger {
+        NestestTraceLogger {
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    pub fn log(&mut self, line: String) {
+        self.lines.push(line);
+    }
+
+    pub fn output_log<W: Write>(&self, w: &mut W) {
+        for line in self.lines.iter() {
+            writeln!(w, "{}", line).unwrap();
+        }
+    }
+}
+
+impl Default for NestestTraceLogger {
+    fn default() -> Self {
+        NestestTraceLogger::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::consoles::Console;
+    use crate::consoles::nes_ntsc::NesNtsc;
+    use crate::utils::cpu_trace_logger::CpuTraceFormat;
+    use std::path::Path;
+
+    // automation mode ends with an RTS to $0000 from $C66E
+    const END_PC: u16 = 0xC66E;
+    const MAX_INSTRUCTIONS: usize = 10000;
+
+    // start of nestest.log, used when the full log isn't next to the rom
+    const EXPECTED_START: [&str; 9] = [
+        "C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7",
+        "C5F5  A2 00     LDX #$00                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 30 CYC:10",
+        "C5F7  86 00     STX $00 = 00                    A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 36 CYC:12",
+        "C5F9  86 10     STX $10 = 00                    A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 45 CYC:15",
+        "C5FB  86 11     STX $11 = 00                    A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 54 CYC:18",
+        "C5FD  20 2D C7  JSR $C72D                       A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 63 CYC:21",
+        "C72D  EA        NOP                             A:00 X:00 Y:00 P:26 SP:FB PPU:  0, 81 CYC:27",
+        "C72E  38        SEC                             A:00 X:00 Y:00 P:26 SP:FB PPU:  0, 87 CYC:29",
+        "C72F  B0 04     BCS $C735                       A:00 X:00 Y:00 P:27 SP:FB PPU:  0, 93 CYC:31",
+    ];
+
+    fn run_nestest(test_roms: &Path) -> (NesNtsc, Vec<String>) {
+        let mut nes = NesNtsc::new();
+        nes.load_rom(test_roms.join("nestest.nes"));
+        nes.power_on_console_at(0xC000);
+        nes.set_cpu_trace_format(CpuTraceFormat::Nestest);
+        nes.set_cpu_trace(true);
+
+        let mut pc = 0xC000;
+        for _ in 0..MAX_INSTRUCTIONS {
+            if pc == END_PC { break; }
+            pc = match nes.step_instruction() {
+                Ok(pc) => pc,
+                Err(_) => panic!("cpu jammed after ${:04X}", pc),
+            };
+        }
+        assert_eq!(pc, END_PC, "nestest didn't finish");
+
+        let mut log = Vec::new();
+        nes.output_cpu_log(&mut log);
+        let lines = String::from_utf8(log).unwrap().lines().map(|l| l.to_string()).collect();
+        (nes, lines)
+    }
+
+    // the ppu phase is taken from the first line
+    fn first_divergence(expected: &[String], actual: &[String]) -> Option<String> {
+        let ppu_offset = match (expected.first(), actual.first()) {
+            (Some(e), Some(a)) => nestest_ppu_offset(e, a).unwrap_or(0),
+            _ => 0,
+        };
+
+        for (i, (e, a)) in expected.iter().zip(actual.iter()).enumerate() {
+            if !compare_nestest_lines(e, a, ppu_offset) {
+                return Some(format!("line {} (ppu offset {} dots)\nexpected: {}\nactual:   {}", i + 1, ppu_offset, e, a));
+            }
+        }
+
+        if actual.len() < expected.len() {
+            return Some(format!("line {}\nexpected: {}\nactual:   end of trace", actual.len() + 1, expected[actual.len()]));
+        }
+        None
+    }
+
+    #[test]
+    fn test_nestest_log_start() {
+        let test_roms = Path::new(env!("CARGO_MANIFEST_DIR")).join("../rustnes-dev/test_roms");
+        let (mut nes, actual) = run_nestest(&test_roms);
+
+        let expected: Vec<String> = EXPECTED_START.iter().map(|l| l.to_string()).collect();
+        if let Some(divergence) = first_divergence(&expected, &actual) {
+            panic!("nestest trace diverges at {}", divergence);
+        }
+
+        // $02 holds the first failed official opcode test, $03 the unofficial ones
+        assert_eq!(nes.peek_cpu(0x0002), 0, "official opcodes failed");
+    }
+
+    #[test]
+    #[ignore = "needs nestest.log, see rustnes-dev/test_roms/fetch_nestest_log.sh"]
+    fn test_nestest_log() {
+        let test_roms = Path::new(env!("CARGO_MANIFEST_DIR")).join("../rustnes-dev/test_roms");
+        let log = std::fs::read_to_string(test_roms.join("nestest.log"))
+            .expect("nestest.log not found, run rustnes-dev/test_roms/fetch_nestest_log.sh");
+        let expected: Vec<String> = log.lines().map(|l| l.to_string()).collect();
+
+        let (mut nes, actual) = run_nestest(&test_roms);
+        if let Some(divergence) = first_divergence(&expected, &actual) {
+            panic!("nestest trace diverges at {}", divergence);
+        }
+        assert_eq!(nes.peek_cpu(0x0003), 0, "unofficial opcodes failed");
+    }
+
+    #[test]
+    fn test_nestest_format() {
+        let state = CpuState { pc: 0xC5F7, a: 0, x: 0, y: 0, p: 0x06, sp: 0xFD, scanline: 0, dot: 36, cycle: 12 };
+        let line = format_nestest_line(&state, |address| match address {
+            0xC5F7 => 0x86,
+            0xC5F8 => 0x00,
+            _ => 0,
+        });
+        assert_eq!(line, EXPECTED_START[2]);
+
+        // pre-render line 261 dot 340 is one dot before 0, 0
+        let shifted = line.replace("PPU:  0, 36", "PPU:261,305");
+        assert_eq!(nestest_ppu_offset(EXPECTED_START[2], &shifted), Some(72));
+        assert!(compare_nestest_lines(EXPECTED_START[2], &shifted, 72));
+        assert!(!compare_nestest_lines(EXPECTED_START[2], &shifted, 0));
+        assert!(!compare_nestest_lines(EXPECTED_START[2], &line.replace("CYC:12", "CYC:13"), 0));
+    }
+}
@@ -0,0 +1,151 @@
+use crate::consoles::{Console, EmuError};
+use crate::consoles::nes_ntsc::NesNtsc;
+use crate::video::screenshot;
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/*
+    Regression checks for test roms without text output. The rom runs headless for a number of frames,
+    then the index buffer is compared with a crc32 or the rendered frame with a reference png.
+    On a mismatch the actual frame is written next to a diff image (reference png only) so the
+    failure can be looked at, an actual frame that is correct can be checked in as the new reference.
+*/
+
+const WIDTH: usize = 256;
+const HEIGHT: usize = 240;
+// diff image, matching pixels are dimmed so the differences stand out
+const DIFF_COLOR: u32 = 0xFF0000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VisualReference<'a> {
+    Hash(u32),
+    Png(&'a Path),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VisualResult {
+    Matched,
+    HashMismatch(u32),
+    PixelMismatch(usize),
+}
+
+pub fn run_visual_rom<P: AsRef<Path>>(rom_path: P, frames: u64) -> NesNtsc {
+    let mut nes = NesNtsc::new();
+    nes.load_rom(rom_path);
+    for _ in 0..frames {
+        // a jammed cpu shows up as a mismatch
+        if nes.execute_frame().is_err() { break; }
+    }
+    nes
+}
+
+pub fn compare_frame(nes: &mut NesNtsc, reference: VisualReference) -> Result<VisualResult, EmuError> {
+    match reference {
+        VisualReference::Hash(expected) => {
+            let hash = screenshot::index_buffer_hash(nes.get_index_buffer());
+            Ok(if hash == expected { VisualResult::Matched } else { VisualResult::HashMismatch(hash) })
+        }
+        VisualReference::Png(path) => {
+            let expected = load_reference(path)?;
+            let actual = render_frame(nes)?;
+            let diff_pixels = expected.iter().zip(actual.iter()).filter(|(e, a)| e != a).count();
+            Ok(if diff_pixels == 0 { VisualResult::Matched } else { VisualResult::PixelMismatch(diff_pixels) })
+        }
+    }
+}
+
+// writes <name>-actual.png and for png references that exist <name>-diff.png into dir, returns the written paths
+pub fn write_failure_images(nes: &mut NesNtsc, reference: VisualReference, dir: &Path, name: &str) -> Result<Vec<PathBuf>, EmuError> {
+    fs::create_dir_all(dir).map_err(|_| EmuError::ScreenshotError)?;
+    let actual = render_frame(nes)?;
+
+    let mut paths = vec![dir.join(format!("{}-actual.png", name))];
+    write_png(&paths[0], &actual, nes)?;
+
+    if let Some(expected) = match reference { VisualReference::Png(path) => load_reference(path).ok(), _ => None } {
+        let diff: Vec<u32> = expected.iter().zip(actual.iter())
+            .map(|(e, a)| if e == a { (a >> 2) & 0x3F3F3F } else { DIFF_COLOR })
+            .collect();
+
+        paths.push(dir.join(format!("{}-diff.png", name)));
+        write_png(&paths[1], &diff, nes)?;
+    }
+
+    Ok(paths)
+}
+
+fn render_frame(nes: &mut NesNtsc) -> Result<Vec<u32>, EmuError> {
+    let mut frame = vec![0u32; WIDTH * HEIGHT];
+    nes.output_pixel_buffer(&mut frame)?;
+    Ok(frame)
+}
+
+fn load_reference(path: &Path) -> Result<Vec<u32>, EmuError> {
+    let file = File::open(path).map_err(|_| EmuError::ScreenshotError)?;
+    match screenshot::decode_png(file)? {
+        (frame, WIDTH, HEIGHT) => Ok(frame),
+        _ => Err(EmuError::ScreenshotError),
+    }
+}
+
+fn write_png(path: &Path, frame: &[u32], nes: &NesNtsc) -> Result<(), EmuError> {
+    let file = File::create(path).map_err(|_| EmuError::ScreenshotError)?;
+    screenshot::encode_png(BufWriter::new(file), frame, WIDTH, HEIGHT, nes.get_rom_crc(), nes.get_frame_number())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run(name: &str, rom: &str, frames: u64, reference: VisualReference) {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let mut nes = run_visual_rom(manifest_dir.join("../rustnes-dev/test_roms").join(rom), frames);
+
+        // a missing reference png is a failure too, the actual frame is the starting point for one
+        let result = match compare_frame(&mut nes, reference) {
+            Ok(VisualResult::Matched) => return,
+            Ok(result) => format!("{:?}", result),
+            Err(_) => "unable to load the reference".to_string(),
+        };
+        let paths = write_failure_images(&mut nes, reference, &manifest_dir.join("target/visual_regression"), name).ok().unwrap_or_default();
+        panic!("{} doesn't match the reference, {}\n{:?}", rom, result, paths);
+    }
+
+    fn reference_png(file: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("test_references").join(file)
+    }
+
+    macro_rules! visual_tests {
+        ($($(#[$attr:meta])* $name:ident: $rom:expr, $frames:expr, $reference:expr,)*) => {
+            $(
+                #[test]
+                $(#[$attr])*
+                fn $name() {
+                    run(stringify!($name), $rom, $frames, $reference);
+                }
+            )*
+        }
+    }
+
+    /*
+        The sprite hit roms print their name then PASSED or FAILED #n, every one of them passes on hardware.
+        Roms that pass here are checked against their own frame. The references of the failing ones are
+        their frame with the FAILED line replaced by the PASSED line of 01.basics, the ignore reason is
+        the failure the rom reports.
+    */
+    visual_tests! {
+        sprite_hit_01_basics: "sprite_hit_tests_2005.10.05/01.basics.nes", 120, VisualReference::Png(&reference_png("sprite_hit_01_basics.png")),
+        #[ignore = "#5 should miss right side of bg tile"] sprite_hit_02_alignment: "sprite_hit_tests_2005.10.05/02.alignment.nes", 120, VisualReference::Png(&reference_png("sprite_hit_02_alignment.png")),
+        #[ignore = "#2 lower-right pixel should hit"] sprite_hit_03_corners: "sprite_hit_tests_2005.10.05/03.corners.nes", 120, VisualReference::Png(&reference_png("sprite_hit_03_corners.png")),
+        #[ignore = "#2 horizontal flipping doesn't work"] sprite_hit_04_flip: "sprite_hit_tests_2005.10.05/04.flip.nes", 120, VisualReference::Png(&reference_png("sprite_hit_04_flip.png")),
+        sprite_hit_05_left_clip: "sprite_hit_tests_2005.10.05/05.left_clip.nes", 120, VisualReference::Hash(0x25CDAEA5),
+        #[ignore = "#2 should always miss when x = 255"] sprite_hit_06_right_edge: "sprite_hit_tests_2005.10.05/06.right_edge.nes", 120, VisualReference::Png(&reference_png("sprite_hit_06_right_edge.png")),
+        #[ignore = "#5 should hit, sprite pixel is at y = 238"] sprite_hit_07_screen_bottom: "sprite_hit_tests_2005.10.05/07.screen_bottom.nes", 120, VisualReference::Png(&reference_png("sprite_hit_07_screen_bottom.png")),
+        #[ignore = "#3 lower sprite tile should hit bottom of bg tile"] sprite_hit_08_double_height: "sprite_hit_tests_2005.10.05/08.double_height.nes", 120, VisualReference::Png(&reference_png("sprite_hit_08_double_height.png")),
+        #[ignore = "#4 upper-right corner too soon"] sprite_hit_09_timing_basics: "sprite_hit_tests_2005.10.05/09.timing_basics.nes", 120, VisualReference::Png(&reference_png("sprite_hit_09_timing_basics.png")),
+        #[ignore = "#7 lower-left corner too late"] sprite_hit_10_timing_order: "sprite_hit_tests_2005.10.05/10.timing_order.nes", 120, VisualReference::Png(&reference_png("sprite_hit_10_timing_order.png")),
+        sprite_hit_11_edge_timing: "sprite_hit_tests_2005.10.05/11.edge_timing.nes", 120, VisualReference::Hash(0x60A8C261),
+    }
+}
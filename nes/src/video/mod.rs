@@ -0,0 +1,5 @@
+pub mod ntsc;
+pub mod scalers;
+pub mod output;
+pub mod screenshot;
+pub mod recorder;
@@ -0,0 +1,233 @@
+/*
+    NTSC composite video filter, port of misc/ntsc_small.c
+
+    Each PPU dot is generated as 8 samples of a square wave at the color subcarrier frequency
+    (12 samples per subcarrier cycle), then decoded back into YIQ like a TV would. Sharp edges
+    between colors leak into the chroma and produce artifact colors, and the starting phase of
+    each scanline shifts every line and frame which causes dot crawl.
+
+    Implementation based on https://wiki.nesdev.com/w/index.php/NTSC_video
+*/
+
+use std::f32::consts::PI;
+
+const SAMPLES_PER_DOT: usize = 8;
+const SUBCARRIER_PHASES: usize = 12;
+const SIGNAL_BUFFER_WIDTH: usize = 24;
+// phase of the decoder relative to the signal at phase 0
+const DECODE_PHASE_OFFSET: usize = 20;
+// black level correction from the reference implementation
+const Y_OFFSET: f32 = -6.0;
+const DEEMPHASIS_ATTENUATION: f32 = 0.746;
+
+// low and high voltage levels for each of the 4 luma rows
+const VOLTAGES: [[f32; 2]; 4] = [
+    [0.350, 1.090],
+    [0.518, 1.500],
+    [0.962, 1.960],
+    [1.550, 1.960],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NtscSettings {
+    pub hue: f32,           // degrees
+    pub saturation: f32,    // 1.0 is unchanged
+    pub sharpness: f32,     // 0.0 (soft) to 1.0 (sharp, more chroma leaks into luma)
+    pub output_width: usize,
+}
+
+impl Default for NtscSettings {
+    fn default() -> Self {
+        NtscSettings {
+            hue: 0.0,
+            saturation: 1.0,
+            sharpness: 0.0,
+            output_width: 640,
+        }
+    }
+}
+
+pub struct NtscFilter {
+    settings: NtscSettings,
+    i_table: [f32; SIGNAL_BUFFER_WIDTH],
+    q_table: [f32; SIGNAL_BUFFER_WIDTH],
+    y_window: usize,
+}
+
+impl NtscFilter {
+    pub fn new(settings: NtscSettings) -> Self {
+        let mut filter = NtscFilter {
+            settings,
+            i_table: [0.0; SIGNAL_BUFFER_WIDTH],
+            q_table: [0.0; SIGNAL_BUFFER_WIDTH],
+            y_window: SUBCARRIER_PHASES,
+        };
+
+        filter.set_settings(settings);
+        filter
+    }
+
+    pub fn settings(&self) -> NtscSettings {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: NtscSettings) {
+        self.settings = settings;
+
+        let hue = settings.hue * PI / 180.0;
+        for (i, (it, qt)) in self.i_table.iter_mut().zip(self.q_table.iter_mut()).enumerate() {
+            *it = ((i as f32 + 3.0 - 1.9) * PI / 6.0 + hue).cos() * settings.saturation;
+            *qt = ((i as f32 + 0.0 - 1.9) * PI / 6.0 + hue).cos() * settings.saturation;
+        }
+
+        // shorter luma window no longer cancels out the subcarrier
+        let sharpness = settings.sharpness.clamp(0.0, 1.0);
+        self.y_window = SUBCARRIER_PHASES - (sharpness * 6.0).round() as usize;
+    }
+
+    pub fn output_width(&self) -> usize {
+        self.settings.output_width
+    }
+
+    // index_buffer is 256x240 9 bit color indices, output is output_width x 240 0RGB
+    pub fn filter_frame(&self, index_buffer: &[u16], scanline_phases: &[u8], output: &mut [u32]) {
+        let width = self.settings.output_width;
+        for (y, (line, out_line)) in index_buffer.chunks(256).zip(output.chunks_mut(width)).enumerate() {
+            let phase = scanline_phases.get(y).copied().unwrap_or(0);
+            self.filter_scanline(line, phase, out_line);
+        }
+    }
+
+    pub fn filter_scanline(&self, line: &[u16], phase: u8, output: &mut [u32]) {
+        let width = self.settings.output_width;
+        let mut signal_history = [0.0f32; SIGNAL_BUFFER_WIDTH];
+        let (mut sum_y, mut sum_i, mut sum_q) = (0.0f32, 0.0f32, 0.0f32);
+
+        let mut cycles = phase as usize % SUBCARRIER_PHASES;
+        // decoder stays locked to the color burst so only artifacts move with the phase
+        let mut decode_phase = (SIGNAL_BUFFER_WIDTH + DECODE_PHASE_OFFSET - cycles) % SIGNAL_BUFFER_WIDTH;
+        let y_scale = SUBCARRIER_PHASES as f32 / self.y_window as f32;
+
+        let mut pixel_carry = 0;
+        let mut hpos = 0;
+
+        for color in line.iter() {
+            let color = *color as usize;
+            let mut brightness = (color >> 4) & 3;
+            let mut index = color & 0x0F;
+            // xE and xF are treated as 1D
+            if index >= 0x0E {
+                brightness = 1;
+                index = 0x0D;
+            }
+
+            let mut low = VOLTAGES[brightness][0];
+            let mut high = VOLTAGES[brightness][1];
+            // grays only use one level
+            if index == 0x0D { high = low; } else if index == 0x00 { low = high; }
+
+            let mut phase_bit: u32 = 1 << ((cycles + 2 * SUBCARRIER_PHASES - index) % SUBCARRIER_PHASES);
+
+            let mut deemp: u32 = 0;
+            if (color & 0x040) != 0 { deemp |= 0x3F03F; }
+            if (color & 0x080) != 0 { deemp |= 0x3F03F / 0x100; }
+            if (color & 0x100) != 0 { deemp |= 0x3F03F / 0x010; }
+
+            for _ in 0..SAMPLES_PER_DOT {
+                let mut sample = high;
+
+                phase_bit <<= 1;
+                if phase_bit >= (1 << SUBCARRIER_PHASES) {
+                    phase_bit = 1;
+                }
+                else if phase_bit >= (1 << 6) {
+                    sample = low;
+                }
+
+                if (phase_bit & deemp) != 0 {
+                    sample *= DEEMPHASIS_ATTENUATION;
+                }
+                cycles = (cycles + 1) % SUBCARRIER_PHASES;
+
+                // decode
+                decode_phase = if decode_phase == 0 { SIGNAL_BUFFER_WIDTH - 1 } else { decode_phase - 1 };
+                let old_y = signal_history[(decode_phase + SIGNAL_BUFFER_WIDTH - self.y_window) % SIGNAL_BUFFER_WIDTH];
+                let old_iq = signal_history[decode_phase];
+                signal_history[decode_phase] = sample;
+                sum_y += sample - old_y;
+                sum_i += (sample - old_iq) * self.i_table[decode_phase];
+                sum_q += (sample - old_iq) * self.q_table[decode_phase];
+
+                pixel_carry += width;
+                while pixel_carry >= 256 * SAMPLES_PER_DOT {
+                    pixel_carry -= 256 * SAMPLES_PER_DOT;
+
+                    let y = sum_y * y_scale + Y_OFFSET;
+                    let r = (y + sum_i * 0.946882 + sum_q * 0.623557) / SIGNAL_BUFFER_WIDTH as f32;
+                    let g = (y + sum_i * -0.274788 + sum_q * -0.635691) / SIGNAL_BUFFER_WIDTH as f32;
+                    let b = (y + sum_i * -1.108545 + sum_q * 1.709007) / SIGNAL_BUFFER_WIDTH as f32;
+
+                    if hpos < output.len() {
+                        output[hpos] = (to_channel(r) << 16) | (to_channel(g) << 8) | to_channel(b);
+                    }
+                    hpos += 1;
+                }
+            }
+        }
+    }
+}
+
+#[inline]
+fn to_channel(v: f32) -> u32 {
+    ((v * 256.0) as i32).clamp(0, 255) as u32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn channels(c: u32) -> (i32, i32, i32) {
+        (((c >> 16) & 0xFF) as i32, ((c >> 8) & 0xFF) as i32, (c & 0xFF) as i32)
+    }
+
+    #[test]
+    fn test_ntsc_gray_has_no_chroma() {
+        let filter = NtscFilter::new(NtscSettings::default());
+        let line = [0x10u16; 256];
+        let mut out = [0u32; 640];
+        filter.filter_scanline(&line, 0, &mut out);
+
+        // skip the filter warming up at the left edge
+        for c in out[32..].iter() {
+            let (r, g, b) = channels(*c);
+            assert!((r - g).abs() <= 2 && (g - b).abs() <= 2, "{:06X}", c);
+            assert!(r > 0x20);
+        }
+    }
+
+    #[test]
+    fn test_ntsc_colors_and_phase() {
+        let filter = NtscFilter::new(NtscSettings::default());
+        let mut out = [0u32; 640];
+
+        // red is mostly red for every phase of the decoder
+        for phase in [0u8, 4, 8].iter() {
+            filter.filter_scanline(&[0x16u16; 256], *phase, &mut out);
+            let (r, g, b) = channels(out[320]);
+            assert!(r > g + 0x30 && r > b + 0x30, "phase {} {:06X}", phase, out[320]);
+        }
+
+        // artifacts at sharp edges move with the phase
+        let mut line = [0x0Fu16; 256];
+        for x in (0..256).step_by(2) { line[x] = 0x30; }
+        let mut out2 = [0u32; 640];
+        filter.filter_scanline(&line, 0, &mut out);
+        filter.filter_scanline(&line, 4, &mut out2);
+        assert_ne!(&out[32..], &out2[32..]);
+
+        // emphasis darkens
+        filter.filter_scanline(&[0x30u16 | 0x1C0; 256], 0, &mut out2);
+        filter.filter_scanline(&[0x30u16; 256], 0, &mut out);
+        assert!(channels(out2[320]).0 < channels(out[320]).0);
+    }
+}
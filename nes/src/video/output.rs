@@ -0,0 +1,178 @@
+/*
+    Final stage of the video path, crops the overscan area TVs hid behind the bezel and
+    stretches the picture horizontally to the console's pixel aspect ratio.
+    Works on any buffer holding a whole 256x240 frame, including scaled and NTSC filtered ones.
+*/
+
+const NES_WIDTH: usize = 256;
+const NES_HEIGHT: usize = 240;
+
+// lines/columns removed from each edge, in NES pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Overscan {
+    pub top: usize,
+    pub bottom: usize,
+    pub left: usize,
+    pub right: usize,
+}
+
+impl Overscan {
+    pub const NONE: Overscan = Overscan { top: 0, bottom: 0, left: 0, right: 0 };
+
+    // typical NTSC TV
+    pub const fn ntsc() -> Overscan {
+        Overscan { top: 8, bottom: 8, left: 0, right: 0 }
+    }
+
+    pub fn cropped_width(&self) -> usize {
+        NES_WIDTH.saturating_sub(self.left + self.right)
+    }
+
+    pub fn cropped_height(&self) -> usize {
+        NES_HEIGHT.saturating_sub(self.top + self.bottom)
+    }
+}
+
+impl Default for Overscan {
+    fn default() -> Self {
+        Overscan::NONE
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PixelAspect {
+    #[default]
+    Square, // no correction
+    Ntsc,   // 8:7
+    Pal,    // 2950000:2128137
+}
+
+impl PixelAspect {
+    pub fn ratio(&self) -> f64 {
+        match self {
+            PixelAspect::Square => 1.0,
+            PixelAspect::Ntsc => 8.0 / 7.0,
+            PixelAspect::Pal => 2950000.0 / 2128137.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct VideoOutput {
+    pub overscan: Overscan,
+    pub aspect: PixelAspect,
+}
+
+impl VideoOutput {
+    pub fn new(overscan: Overscan, aspect: PixelAspect) -> Self {
+        VideoOutput {
+            overscan,
+            aspect,
+        }
+    }
+
+    // size of the processed frame for a width x height source
+    pub fn output_size(&self, width: usize, height: usize) -> (usize, usize) {
+        let (cw, ch) = self.crop_size(width, height);
+        if self.aspect == PixelAspect::Square {
+            return (cw, ch);
+        }
+
+        // width relative to the vertical scale of the source
+        let scale_y = height as f64 / NES_HEIGHT as f64;
+        let ow = (self.overscan.cropped_width() as f64 * self.aspect.ratio() * scale_y).round() as usize;
+        (ow.max(1), ch)
+    }
+
+    // returns size of frame written to dst
+    pub fn process(&self, src: &[u32], width: usize, height: usize, dst: &mut Vec<u32>) -> (usize, usize) {
+        let (left, top) = (self.overscan.left * width / NES_WIDTH, self.overscan.top * height / NES_HEIGHT);
+        let (cw, ch) = self.crop_size(width, height);
+        let (ow, oh) = self.output_size(width, height);
+
+        dst.clear();
+        dst.reserve(ow * oh);
+
+        for y in 0..ch {
+            let line = &src[(top + y) * width + left..(top + y) * width + left + cw];
+            if ow == cw {
+                dst.extend_from_slice(line);
+                continue;
+            }
+
+            // linear resample in 16.16 fixed point
+            let step = ((cw as u64) << 16) / ow as u64;
+            for x in 0..ow {
+                let pos = (x as u64 * step) + (step >> 1);
+                let pos = pos.saturating_sub(1 << 15);
+                let i = ((pos >> 16) as usize).min(cw - 1);
+                let frac = (pos & 0xFFFF) as u32;
+                let c0 = line[i];
+                let c1 = line[(i + 1).min(cw - 1)];
+                dst.push(lerp(c0, c1, frac));
+            }
+        }
+
+        (ow, oh)
+    }
+
+    fn crop_size(&self, width: usize, height: usize) -> (usize, usize) {
+        (self.overscan.cropped_width() * width / NES_WIDTH, self.overscan.cropped_height() * height / NES_HEIGHT)
+    }
+}
+
+#[inline]
+fn lerp(c0: u32, c1: u32, frac: u32) -> u32 {
+    let channel = |shift: u32| {
+        let a = (c0 >> shift) & 0xFF;
+        let b = (c1 >> shift) & 0xFF;
+        ((a * (0x10000 - frac) + b * frac) >> 16) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn frame(width: usize, height: usize) -> Vec<u32> {
+        (0..width * height).map(|i| (((i / width) as u32) << 8) | (i % width) as u32 & 0xFF).collect()
+    }
+
+    #[test]
+    fn test_overscan_crop() {
+        let src = frame(256, 240);
+        let mut dst = Vec::new();
+        let out = VideoOutput::new(Overscan { top: 8, bottom: 8, left: 4, right: 2 }, PixelAspect::Square);
+
+        assert_eq!(out.process(&src, 256, 240, &mut dst), (250, 224));
+        assert_eq!(dst.len(), 250 * 224);
+        assert_eq!(dst[0], (8 << 8) | 4);
+        assert_eq!(dst[250 * 224 - 1], (231 << 8) | 253);
+
+        // overscan is in NES pixels and scaled with the buffer
+        let src = frame(512, 480);
+        assert_eq!(out.process(&src, 512, 480, &mut dst), (500, 448));
+        assert_eq!(dst[0], (16 << 8) | 8);
+    }
+
+    #[test]
+    fn test_pixel_aspect() {
+        let src = vec![0x808080; 256 * 240];
+        let mut dst = Vec::new();
+
+        let out = VideoOutput::new(Overscan::NONE, PixelAspect::Ntsc);
+        assert_eq!(out.process(&src, 256, 240, &mut dst), (293, 240));
+        assert!(dst.iter().all(|c| *c == 0x808080));
+
+        let out = VideoOutput::new(Overscan::ntsc(), PixelAspect::Pal);
+        assert_eq!(out.output_size(256, 240), (355, 224));
+        assert_eq!(out.output_size(512, 480), (710, 448));
+
+        // NTSC filter output is already wider than 256
+        let out = VideoOutput::new(Overscan::NONE, PixelAspect::Ntsc);
+        assert_eq!(out.output_size(640, 240), (293, 240));
+        let out = VideoOutput::new(Overscan::NONE, PixelAspect::Square);
+        assert_eq!(out.output_size(640, 240), (640, 240));
+    }
+}
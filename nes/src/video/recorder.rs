@@ -0,0 +1,426 @@
+/*
+    Lossless frame recorders for bug reports, no external encoder needed.
+
+    Y4M - YUV4MPEG2 with 4:4:4 chroma so no color detail is lost, any length
+    AVI - uncompressed 24 bit RGB with an optional 16 bit PCM audio track, RIFF sizes are
+          32 bit so recordings stop being playable around 4GB (~6 hours at 256x240)
+
+    Both run at the NTSC frame rate of 39375000 / 655171 (~60.0988) fps
+*/
+
+use crate::consoles::EmuError;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+pub const FRAME_RATE_NUM: u32 = 39375000;
+pub const FRAME_RATE_DEN: u32 = 655171;
+
+// BT.601 limited range
+#[inline]
+fn rgb_to_ycbcr(c: u32) -> (u8, u8, u8) {
+    let r = ((c >> 16) & 0xFF) as i32;
+    let g = ((c >> 8) & 0xFF) as i32;
+    let b = (c & 0xFF) as i32;
+
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let cb = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let cr = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+    (y as u8, cb as u8, cr as u8)
+}
+
+pub struct Y4mWriter<W: Write> {
+    w: W,
+    width: usize,
+    height: usize,
+    planes: Vec<u8>,
+    frame_count: u64,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    pub fn new(mut w: W, width: usize, height: usize) -> io::Result<Self> {
+        writeln!(w, "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C444", width, height, FRAME_RATE_NUM, FRAME_RATE_DEN)?;
+        Ok(Y4mWriter {
+            w,
+            width,
+            height,
+            planes: vec![0; width * height * 3],
+            frame_count: 0,
+        })
+    }
+
+    // frame is width x height 0RGB
+    pub fn write_frame(&mut self, frame: &[u32]) -> io::Result<()> {
+        let size = self.width * self.height;
+        for (i, c) in frame[..size].iter().enumerate() {
+            let (y, cb, cr) = rgb_to_ycbcr(*c);
+            self.planes[i] = y;
+            self.planes[size + i] = cb;
+            self.planes[size * 2 + i] = cr;
+        }
+
+        self.w.write_all(b"FRAME\n")?;
+        self.w.write_all(&self.planes)?;
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.w.flush()?;
+        Ok(self.w)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+// offsets of header fields patched once the recording is finished
+const RIFF_SIZE_OFFSET: u64 = 4;
+const AVIH_TOTAL_FRAMES_OFFSET: u64 = 48;
+const VIDEO_STRH_LENGTH_OFFSET: u64 = 140;
+const AUDIO_STRH_LENGTH_OFFSET: u64 = 264;
+
+const AVIF_HASINDEX: u32 = 0x10;
+const AVIIF_KEYFRAME: u32 = 0x10;
+
+pub struct AviWriter<W: Write + Seek> {
+    w: W,
+    width: usize,
+    height: usize,
+    audio: Option<AudioFormat>,
+    start: u64,
+    movi_offset: u64,
+    movi_size: u32,
+    index: Vec<(&'static [u8; 4], u32, u32)>,
+    row: Vec<u8>,
+    frame_count: u32,
+    audio_block_count: u32,
+}
+
+impl<W: Write + Seek> AviWriter<W> {
+    pub fn new(mut w: W, width: usize, height: usize, audio: Option<AudioFormat>) -> io::Result<Self> {
+        let start = w.stream_position()?;
+        let stride = (width * 3 + 3) & !3;
+        let frame_size = (stride * height) as u32;
+
+        let mut strl_video = Vec::new();
+        chunk(&mut strl_video, b"strh", &stream_header(b"vids", b"DIB ", FRAME_RATE_DEN, FRAME_RATE_NUM, frame_size, 0, (width, height)));
+        let mut bih = Vec::new();
+        put_u32(&mut bih, 40);
+        put_u32(&mut bih, width as u32);
+        put_u32(&mut bih, height as u32); // bottom up
+        put_u16(&mut bih, 1);
+        put_u16(&mut bih, 24);
+        put_u32(&mut bih, 0); // BI_RGB
+        put_u32(&mut bih, frame_size);
+        bih.extend_from_slice(&[0; 16]);
+        chunk(&mut strl_video, b"strf", &bih);
+
+        let mut hdrl = Vec::new();
+        let mut avih = Vec::new();
+        put_u32(&mut avih, (1_000_000u64 * FRAME_RATE_DEN as u64 / FRAME_RATE_NUM as u64) as u32);
+        put_u32(&mut avih, 0);
+        put_u32(&mut avih, 0);
+        put_u32(&mut avih, AVIF_HASINDEX);
+        put_u32(&mut avih, 0); // total frames
+        put_u32(&mut avih, 0);
+        put_u32(&mut avih, if audio.is_some() { 2 } else { 1 });
+        put_u32(&mut avih, frame_size);
+        put_u32(&mut avih, width as u32);
+        put_u32(&mut avih, height as u32);
+        avih.extend_from_slice(&[0; 16]);
+        chunk(&mut hdrl, b"avih", &avih);
+        list(&mut hdrl, b"strl", &strl_video);
+
+        if let Some(format) = audio {
+            let block_align = format.channels as u32 * 2;
+            let mut strl_audio = Vec::new();
+            chunk(&mut strl_audio, b"strh", &stream_header(b"auds", &[0; 4], block_align, format.sample_rate * block_align, 0, block_align, (0, 0)));
+            let mut wfx = Vec::new();
+            put_u16(&mut wfx, 1); // PCM
+            put_u16(&mut wfx, format.channels);
+            put_u32(&mut wfx, format.sample_rate);
+            put_u32(&mut wfx, format.sample_rate * block_align);
+            put_u16(&mut wfx, block_align as u16);
+            put_u16(&mut wfx, 16);
+            put_u16(&mut wfx, 0);
+            chunk(&mut strl_audio, b"strf", &wfx);
+            list(&mut hdrl, b"strl", &strl_audio);
+        }
+
+        let mut header = Vec::new();
+        header.extend_from_slice(b"RIFF");
+        put_u32(&mut header, 0);
+        header.extend_from_slice(b"AVI ");
+        list(&mut header, b"hdrl", &hdrl);
+        header.extend_from_slice(b"LIST");
+        put_u32(&mut header, 0);
+        let movi_offset = start + header.len() as u64;
+        header.extend_from_slice(b"movi");
+        w.write_all(&header)?;
+
+        Ok(AviWriter {
+            w,
+            width,
+            height,
+            audio,
+            start,
+            movi_offset,
+            movi_size: 4,
+            index: Vec::new(),
+            row: vec![0; stride],
+            frame_count: 0,
+            audio_block_count: 0,
+        })
+    }
+
+    // frame is width x height 0RGB
+    pub fn write_frame(&mut self, frame: &[u32]) -> io::Result<()> {
+        let size = (self.row.len() * self.height) as u32;
+        self.begin_chunk(b"00db", size)?;
+
+        // rows are stored bottom up as BGR
+        for line in frame[..self.width * self.height].chunks(self.width).rev() {
+            for (c, bgr) in line.iter().zip(self.row.chunks_mut(3)) {
+                bgr[0] = *c as u8;
+                bgr[1] = (*c >> 8) as u8;
+                bgr[2] = (*c >> 16) as u8;
+            }
+            self.w.write_all(&self.row)?;
+        }
+
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    // interleaved 16 bit samples, ignored if the recorder has no audio track
+    pub fn write_audio(&mut self, samples: &[i16]) -> io::Result<()> {
+        let channels = match self.audio {
+            Some(format) => format.channels as usize,
+            None => return Ok(()),
+        };
+
+        let size = (samples.len() * 2) as u32;
+        self.begin_chunk(b"01wb", size)?;
+        for s in samples.iter() {
+            self.w.write_all(&s.to_le_bytes())?;
+        }
+        if size & 1 != 0 {
+            self.w.write_all(&[0])?;
+        }
+
+        self.audio_block_count += (samples.len() / channels) as u32;
+        Ok(())
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count as u64
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        let mut idx1 = Vec::with_capacity(self.index.len() * 16);
+        for (id, offset, size) in self.index.iter() {
+            idx1.extend_from_slice(*id);
+            put_u32(&mut idx1, AVIIF_KEYFRAME);
+            put_u32(&mut idx1, *offset);
+            put_u32(&mut idx1, *size);
+        }
+        let mut tail = Vec::new();
+        chunk(&mut tail, b"idx1", &idx1);
+        self.w.write_all(&tail)?;
+
+        let end = self.w.stream_position()?;
+        self.patch(RIFF_SIZE_OFFSET, (end - self.start - 8) as u32)?;
+        self.patch(self.movi_offset - self.start - 4, self.movi_size)?;
+        self.patch(AVIH_TOTAL_FRAMES_OFFSET, self.frame_count)?;
+        self.patch(VIDEO_STRH_LENGTH_OFFSET, self.frame_count)?;
+        if self.audio.is_some() {
+            self.patch(AUDIO_STRH_LENGTH_OFFSET, self.audio_block_count)?;
+        }
+
+        self.w.seek(SeekFrom::Start(end))?;
+        self.w.flush()?;
+        Ok(self.w)
+    }
+
+    fn begin_chunk(&mut self, id: &'static [u8; 4], size: u32) -> io::Result<()> {
+        // idx1 offsets are relative to the movi fourcc
+        self.index.push((id, self.movi_size, size));
+        self.movi_size += 8 + ((size + 1) & !1);
+
+        self.w.write_all(id)?;
+        self.w.write_all(&size.to_le_bytes())
+    }
+
+    fn patch(&mut self, offset: u64, value: u32) -> io::Result<()> {
+        self.w.seek(SeekFrom::Start(self.start + offset))?;
+        self.w.write_all(&value.to_le_bytes())
+    }
+}
+
+fn stream_header(fcc_type: &[u8; 4], handler: &[u8; 4], scale: u32, rate: u32, buffer_size: u32, sample_size: u32, frame: (usize, usize)) -> Vec<u8> {
+    let mut strh = Vec::new();
+    strh.extend_from_slice(fcc_type);
+    strh.extend_from_slice(handler);
+    put_u32(&mut strh, 0);
+    put_u32(&mut strh, 0); // priority and language
+    put_u32(&mut strh, 0);
+    put_u32(&mut strh, scale);
+    put_u32(&mut strh, rate);
+    put_u32(&mut strh, 0);
+    put_u32(&mut strh, 0); // length
+    put_u32(&mut strh, buffer_size);
+    put_u32(&mut strh, 0xFFFFFFFF);
+    put_u32(&mut strh, sample_size);
+    put_u16(&mut strh, 0);
+    put_u16(&mut strh, 0);
+    put_u16(&mut strh, frame.0 as u16);
+    put_u16(&mut strh, frame.1 as u16);
+    strh
+}
+
+fn chunk(out: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(id);
+    put_u32(out, data.len() as u32);
+    out.extend_from_slice(data);
+    if data.len() & 1 != 0 {
+        out.push(0);
+    }
+}
+
+fn list(out: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(b"LIST");
+    put_u32(out, data.len() as u32 + 4);
+    out.extend_from_slice(id);
+    out.extend_from_slice(data);
+}
+
+fn put_u16(out: &mut Vec<u8>, v: u16) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+// picks the container from the file extension, .avi or anything else as .y4m
+pub enum VideoRecorder {
+    Y4m(Y4mWriter<BufWriter<File>>),
+    Avi(AviWriter<BufWriter<File>>),
+}
+
+impl VideoRecorder {
+    pub fn create<P: AsRef<Path>>(path: P, width: usize, height: usize, audio: Option<AudioFormat>) -> Result<Self, EmuError> {
+        let is_avi = path.as_ref().extension().is_some_and(|e| e.eq_ignore_ascii_case("avi"));
+        let file = BufWriter::new(File::create(path).map_err(|_| EmuError::RecordError)?);
+
+        let recorder = if is_avi {
+            VideoRecorder::Avi(AviWriter::new(file, width, height, audio).map_err(|_| EmuError::RecordError)?)
+        }
+        else {
+            VideoRecorder::Y4m(Y4mWriter::new(file, width, height).map_err(|_| EmuError::RecordError)?)
+        };
+
+        Ok(recorder)
+    }
+
+    pub fn write_frame(&mut self, frame: &[u32]) -> Result<(), EmuError> {
+        match self {
+            VideoRecorder::Y4m(w) => w.write_frame(frame),
+            VideoRecorder::Avi(w) => w.write_frame(frame),
+        }.map_err(|_| EmuError::RecordError)
+    }
+
+    pub fn write_audio(&mut self, samples: &[i16]) -> Result<(), EmuError> {
+        match self {
+            VideoRecorder::Y4m(_) => Ok(()),
+            VideoRecorder::Avi(w) => w.write_audio(samples).map_err(|_| EmuError::RecordError),
+        }
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        match self {
+            VideoRecorder::Y4m(w) => w.frame_count(),
+            VideoRecorder::Avi(w) => w.frame_count(),
+        }
+    }
+
+    pub fn finish(self) -> Result<(), EmuError> {
+        match self {
+            VideoRecorder::Y4m(w) => w.finish().map(|_| ()),
+            VideoRecorder::Avi(w) => w.finish().map(|_| ()),
+        }.map_err(|_| EmuError::RecordError)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    fn read_u32(d: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes([d[offset], d[offset + 1], d[offset + 2], d[offset + 3]])
+    }
+
+    #[test]
+    fn test_y4m() {
+        let frame = vec![0xFFFFFF; 8 * 4];
+        let mut w = Y4mWriter::new(Vec::new(), 8, 4).unwrap();
+        w.write_frame(&frame).unwrap();
+        w.write_frame(&frame).unwrap();
+        let d = w.finish().unwrap();
+
+        let header = b"YUV4MPEG2 W8 H4 F39375000:655171 Ip A1:1 C444\n";
+        assert_eq!(&d[..header.len()], &header[..]);
+        assert_eq!(d.len(), header.len() + 2 * (6 + 8 * 4 * 3));
+        assert_eq!(&d[header.len()..header.len() + 6], b"FRAME\n");
+        // white
+        assert_eq!(&d[header.len() + 6..header.len() + 9], &[235, 235, 235]);
+        assert_eq!(d[header.len() + 6 + 32], 128);
+    }
+
+    #[test]
+    fn test_avi() {
+        let mut frame = vec![0; 6 * 2];
+        frame[0] = 0x112233;
+        let audio = AudioFormat { sample_rate: 44100, channels: 1 };
+        let mut w = AviWriter::new(Cursor::new(Vec::new()), 6, 2, Some(audio)).unwrap();
+        for _ in 0..3 {
+            w.write_frame(&frame).unwrap();
+            w.write_audio(&[1, 2, 3]).unwrap();
+        }
+        let d = w.finish().unwrap().into_inner();
+
+        assert_eq!(&d[0..4], b"RIFF");
+        assert_eq!(read_u32(&d, 4) as usize, d.len() - 8);
+        assert_eq!(&d[8..12], b"AVI ");
+        assert_eq!(read_u32(&d, AVIH_TOTAL_FRAMES_OFFSET as usize), 3);
+        assert_eq!(&d[VIDEO_STRH_LENGTH_OFFSET as usize - 32..VIDEO_STRH_LENGTH_OFFSET as usize - 28], b"vids");
+        assert_eq!(read_u32(&d, VIDEO_STRH_LENGTH_OFFSET as usize), 3);
+        assert_eq!(&d[AUDIO_STRH_LENGTH_OFFSET as usize - 32..AUDIO_STRH_LENGTH_OFFSET as usize - 28], b"auds");
+        assert_eq!(read_u32(&d, AUDIO_STRH_LENGTH_OFFSET as usize), 9);
+
+        // walk the movi list, the first pixel of the frame is stored in the last (bottom up) row
+        let movi = d.windows(4).position(|w| w == b"movi").unwrap();
+        let movi_size = read_u32(&d, movi - 4) as usize;
+        let stride = 20;
+        assert_eq!(&d[movi + 4..movi + 8], b"00db");
+        assert_eq!(read_u32(&d, movi + 8) as usize, stride * 2);
+        assert_eq!(&d[movi + 12 + stride..movi + 15 + stride], &[0x33, 0x22, 0x11]);
+
+        let idx1 = movi + movi_size;
+        assert_eq!(&d[idx1..idx1 + 4], b"idx1");
+        assert_eq!(read_u32(&d, idx1 + 4), 6 * 16);
+        // second entry is the audio chunk following the first frame
+        assert_eq!(&d[idx1 + 24..idx1 + 28], b"01wb");
+        assert_eq!(read_u32(&d, idx1 + 32) as usize, 4 + 8 + stride * 2);
+        assert_eq!(read_u32(&d, idx1 + 36), 6);
+    }
+}
@@ -0,0 +1,430 @@
+/*
+    Pixel art upscalers, operate on the 0RGB buffer from output_pixel_buffer
+
+    Scale2x/Scale3x - https://www.scale2x.it/algorithm
+    xBRZ 2x/3x - corner blending and line detection from xBRZ by Zenju
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scaler {
+    None,
+    Scale2x,
+    Scale3x,
+    Xbrz2x,
+    Xbrz3x,
+}
+
+impl Scaler {
+    pub fn factor(&self) -> usize {
+        match self {
+            Scaler::None => 1,
+            Scaler::Scale2x | Scaler::Xbrz2x => 2,
+            Scaler::Scale3x | Scaler::Xbrz3x => 3,
+        }
+    }
+}
+
+// dst must be (width * factor) x (height * factor)
+pub fn scale(scaler: Scaler, src: &[u32], width: usize, height: usize, dst: &mut [u32]) {
+    assert!(src.len() >= width * height);
+    assert!(dst.len() >= width * height * scaler.factor() * scaler.factor());
+
+    match scaler {
+        Scaler::None => { dst[..width * height].copy_from_slice(&src[..width * height]); }
+        Scaler::Scale2x => scale2x(src, width, height, dst),
+        Scaler::Scale3x => scale3x(src, width, height, dst),
+        Scaler::Xbrz2x => xbrz(src, width, height, dst, 2),
+        Scaler::Xbrz3x => xbrz(src, width, height, dst, 3),
+    }
+}
+
+/*
+    3x3 neighborhood of e, edges are clamped
+    a b c
+    d e f
+    g h i
+*/
+#[inline]
+fn kernel3x3(src: &[u32], width: usize, height: usize, x: usize, y: usize) -> [u32; 9] {
+    let xl = x.saturating_sub(1);
+    let xr = (x + 1).min(width - 1);
+    let yt = y.saturating_sub(1);
+    let yb = (y + 1).min(height - 1);
+    [
+        src[yt * width + xl], src[yt * width + x], src[yt * width + xr],
+        src[y * width + xl],  src[y * width + x],  src[y * width + xr],
+        src[yb * width + xl], src[yb * width + x], src[yb * width + xr],
+    ]
+}
+
+//====================================================
+// Scale2x / Scale3x
+//====================================================
+fn scale2x(src: &[u32], width: usize, height: usize, dst: &mut [u32]) {
+    let dst_width = width * 2;
+    for y in 0..height {
+        for x in 0..width {
+            let [_, b, _, d, e, f, _, h, _] = kernel3x3(src, width, height, x, y);
+            let mut out = [e; 4];
+            if b != h && d != f {
+                if d == b { out[0] = d; }
+                if b == f { out[1] = f; }
+                if d == h { out[2] = d; }
+                if h == f { out[3] = f; }
+            }
+
+            let o = (y * 2) * dst_width + x * 2;
+            dst[o] = out[0];
+            dst[o + 1] = out[1];
+            dst[o + dst_width] = out[2];
+            dst[o + dst_width + 1] = out[3];
+        }
+    }
+}
+
+fn scale3x(src: &[u32], width: usize, height: usize, dst: &mut [u32]) {
+    let dst_width = width * 3;
+    for y in 0..height {
+        for x in 0..width {
+            let [a, b, c, d, e, f, g, h, i] = kernel3x3(src, width, height, x, y);
+            let mut out = [e; 9];
+            if b != h && d != f {
+                if d == b { out[0] = d; }
+                if (d == b && e != c) || (b == f && e != a) { out[1] = b; }
+                if b == f { out[2] = f; }
+                if (d == b && e != g) || (d == h && e != a) { out[3] = d; }
+                if (b == f && e != i) || (h == f && e != c) { out[5] = f; }
+                if d == h { out[6] = d; }
+                if (d == h && e != i) || (h == f && e != g) { out[7] = h; }
+                if h == f { out[8] = f; }
+            }
+
+            for (n, p) in out.iter().enumerate() {
+                dst[(y * 3 + n / 3) * dst_width + x * 3 + n % 3] = *p;
+            }
+        }
+    }
+}
+
+//====================================================
+// xBRZ
+//====================================================
+const XBRZ_LUMINANCE_WEIGHT: f64 = 1.0;
+const XBRZ_EQUAL_COLOR_TOLERANCE: f64 = 30.0;
+const XBRZ_DOMINANT_DIRECTION_THRESHOLD: f64 = 3.6;
+const XBRZ_STEEP_DIRECTION_THRESHOLD: f64 = 2.2;
+
+const BLEND_NONE: u8 = 0;
+const BLEND_NORMAL: u8 = 1;
+const BLEND_DOMINANT: u8 = 2;
+
+// kernel positions after rotating the 3x3 kernel 90 degrees
+const ROTATE_KERNEL_90: [usize; 9] = [6, 3, 0, 7, 4, 1, 8, 5, 2];
+
+fn xbrz_dist(c1: u32, c2: u32) -> f64 {
+    if c1 == c2 {
+        return 0.0;
+    }
+
+    let r = ((c1 >> 16) & 0xFF) as f64 - ((c2 >> 16) & 0xFF) as f64;
+    let g = ((c1 >> 8) & 0xFF) as f64 - ((c2 >> 8) & 0xFF) as f64;
+    let b = (c1 & 0xFF) as f64 - (c2 & 0xFF) as f64;
+
+    // ITU-R BT.709
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let cb = 0.5 / (1.0 - 0.0722) * (b - y);
+    let cr = 0.5 / (1.0 - 0.2126) * (r - y);
+    ((XBRZ_LUMINANCE_WEIGHT * y).powi(2) + cb.powi(2) + cr.powi(2)).sqrt()
+}
+
+#[inline]
+fn xbrz_eq(c1: u32, c2: u32) -> bool {
+    xbrz_dist(c1, c2) < XBRZ_EQUAL_COLOR_TOLERANCE
+}
+
+// blend info per pixel corner: top left bits 0-1, top right 2-3, bottom right 4-5, bottom left 6-7
+#[inline]
+fn bottom_r(b: u8) -> u8 { (b >> 4) & 0x3 }
+#[inline]
+fn bottom_l(b: u8) -> u8 { (b >> 6) & 0x3 }
+#[inline]
+fn top_r(b: u8) -> u8 { (b >> 2) & 0x3 }
+
+#[inline]
+fn rotate_blend_info(b: u8) -> u8 {
+    b.rotate_left(2)
+}
+
+#[inline]
+fn alpha_blend(dst: &mut u32, col: u32, m: u32, n: u32) {
+    let blend = |d: u32, c: u32| (c * m + d * (n - m)) / n;
+    let r = blend((*dst >> 16) & 0xFF, (col >> 16) & 0xFF);
+    let g = blend((*dst >> 8) & 0xFF, (col >> 8) & 0xFF);
+    let b = blend(*dst & 0xFF, col & 0xFF);
+    *dst = (r << 16) | (g << 8) | b;
+}
+
+/*
+    Corner between the 4 center pixels of a 4x4 block
+    a b c d
+    e f g h
+    i j k l
+    m n o p
+    returns blend types for corners of f, g, j, k
+*/
+fn xbrz_preprocess_corners(k: &[u32; 16]) -> (u8, u8, u8, u8) {
+    let [_, b, c, _, e, f, g, h, i, j, kk, l, _, n, o, _] = *k;
+    let mut blend = (BLEND_NONE, BLEND_NONE, BLEND_NONE, BLEND_NONE);
+
+    if (f == g && j == kk) || (f == j && g == kk) {
+        return blend;
+    }
+
+    let weight = 4.0;
+    let jg = xbrz_dist(i, f) + xbrz_dist(f, c) + xbrz_dist(n, kk) + xbrz_dist(kk, h) + weight * xbrz_dist(j, g);
+    let fk = xbrz_dist(e, j) + xbrz_dist(j, o) + xbrz_dist(b, g) + xbrz_dist(g, l) + weight * xbrz_dist(f, kk);
+
+    if jg < fk {
+        let blend_type = if XBRZ_DOMINANT_DIRECTION_THRESHOLD * jg < fk { BLEND_DOMINANT } else { BLEND_NORMAL };
+        if f != g && f != j { blend.0 = blend_type; }
+        if kk != j && kk != g { blend.3 = blend_type; }
+    }
+    else if fk < jg {
+        let blend_type = if XBRZ_DOMINANT_DIRECTION_THRESHOLD * fk < jg { BLEND_DOMINANT } else { BLEND_NORMAL };
+        if j != f && j != kk { blend.2 = blend_type; }
+        if g != f && g != kk { blend.1 = blend_type; }
+    }
+
+    blend
+}
+
+// maps (row, col) of the rotated output block back to the unrotated block
+#[inline]
+fn rotate_output(mut row: usize, mut col: usize, factor: usize, rot: usize) -> usize {
+    for _ in 0..rot {
+        let r = factor - 1 - col;
+        col = row;
+        row = r;
+    }
+    row * factor + col
+}
+
+fn xbrz_blend_pixel(ker: &[u32; 9], blend_info: u8, out: &mut [u32], factor: usize, rot: usize) {
+    let [_, b, c, d, e, f, g, h, i] = *ker;
+    let blend = bottom_r(blend_info);
+    if blend < BLEND_NORMAL {
+        return;
+    }
+
+    let do_line_blend = if blend >= BLEND_DOMINANT {
+        true
+    }
+    else if (top_r(blend_info) != BLEND_NONE && !xbrz_eq(e, g)) || (bottom_l(blend_info) != BLEND_NONE && !xbrz_eq(e, c)) {
+        false
+    }
+    else {
+        // no full blending for L shapes, blend corner only
+        !(!xbrz_eq(e, i) && xbrz_eq(g, h) && xbrz_eq(h, i) && xbrz_eq(i, f) && xbrz_eq(f, c))
+    };
+
+    let px = if xbrz_dist(e, f) <= xbrz_dist(e, h) { f } else { h };
+    let mut px_at = |row: usize, col: usize, m: u32, n: u32| {
+        alpha_blend(&mut out[rotate_output(row, col, factor, rot)], px, m, n);
+    };
+    let last = factor - 1;
+
+    if do_line_blend {
+        let fg = xbrz_dist(f, g);
+        let hc = xbrz_dist(h, c);
+        let shallow = XBRZ_STEEP_DIRECTION_THRESHOLD * fg <= hc && e != g && d != g;
+        let steep = XBRZ_STEEP_DIRECTION_THRESHOLD * hc <= fg && e != c && b != c;
+
+        match (factor, shallow, steep) {
+            (2, true, true) => {
+                px_at(1, 0, 1, 4);
+                px_at(0, 1, 1, 4);
+                px_at(1, 1, 5, 6);
+            }
+            (2, true, false) => {
+                px_at(last, 0, 1, 4);
+                px_at(last, 1, 3, 4);
+            }
+            (2, false, true) => {
+                px_at(0, last, 1, 4);
+                px_at(1, last, 3, 4);
+            }
+            (2, false, false) => {
+                px_at(1, 1, 1, 2);
+            }
+            (_, true, true) => {
+                px_at(2, 0, 1, 4);
+                px_at(0, 2, 1, 4);
+                px_at(2, 1, 3, 4);
+                px_at(1, 2, 3, 4);
+                px_at(2, 2, 1, 1);
+            }
+            (_, true, false) => {
+                px_at(last, 0, 1, 4);
+                px_at(last - 1, 1, 1, 4);
+                px_at(last, 1, 3, 4);
+                px_at(last, 2, 1, 1);
+            }
+            (_, false, true) => {
+                px_at(0, last, 1, 4);
+                px_at(1, last - 1, 1, 4);
+                px_at(1, last, 3, 4);
+                px_at(2, last, 1, 1);
+            }
+            (_, false, false) => {
+                px_at(1, 2, 1, 8);
+                px_at(2, 1, 1, 8);
+                px_at(2, 2, 7, 8);
+            }
+        }
+    }
+    else if factor == 2 {
+        // 1 - pi/4
+        px_at(1, 1, 21, 100);
+    }
+    else {
+        px_at(2, 2, 45, 100);
+    }
+}
+
+fn xbrz(src: &[u32], width: usize, height: usize, dst: &mut [u32], factor: usize) {
+    let pixel = |x: isize, y: isize| -> u32 {
+        let x = x.max(0).min(width as isize - 1) as usize;
+        let y = y.max(0).min(height as isize - 1) as usize;
+        src[y * width + x]
+    };
+
+    // corner blend types for every source pixel
+    let mut blend_info = vec![0u8; width * height];
+    for y in -1..(height as isize) {
+        for x in -1..(width as isize) {
+            let mut k = [0u32; 16];
+            for (n, p) in k.iter_mut().enumerate() {
+                *p = pixel(x - 1 + (n % 4) as isize, y - 1 + (n / 4) as isize);
+            }
+
+            let (bf, bg, bj, bk) = xbrz_preprocess_corners(&k);
+            let mut set = |px: isize, py: isize, value: u8, shift: u8| {
+                if px >= 0 && py >= 0 && px < width as isize && py < height as isize {
+                    blend_info[py as usize * width + px as usize] |= value << shift;
+                }
+            };
+            set(x, y, bf, 4);
+            set(x + 1, y, bg, 6);
+            set(x, y + 1, bj, 2);
+            set(x + 1, y + 1, bk, 0);
+        }
+    }
+
+    let dst_width = width * factor;
+    let mut out = vec![0u32; factor * factor];
+    for y in 0..height {
+        for x in 0..width {
+            let mut ker = kernel3x3(src, width, height, x, y);
+            let mut info = blend_info[y * width + x];
+            for p in out.iter_mut() { *p = ker[4]; }
+
+            if info != 0 {
+                for rot in 0..4 {
+                    xbrz_blend_pixel(&ker, info, &mut out, factor, rot);
+                    let prev = ker;
+                    for (n, p) in ker.iter_mut().enumerate() { *p = prev[ROTATE_KERNEL_90[n]]; }
+                    info = rotate_blend_info(info);
+                }
+            }
+
+            for (n, p) in out.iter().enumerate() {
+                dst[(y * factor + n / factor) * dst_width + x * factor + n % factor] = *p;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCALERS: [Scaler; 5] = [Scaler::None, Scaler::Scale2x, Scaler::Scale3x, Scaler::Xbrz2x, Scaler::Xbrz3x];
+
+    // fixed 32x30 frame with diagonals, a circle, stairs and a checkerboard
+    fn test_frame() -> (Vec<u32>, usize, usize) {
+        let (w, h) = (32, 30);
+        let mut frame = vec![0x5C94FC; w * h];
+        for y in 0..h {
+            for x in 0..w {
+                let (dx, dy) = (x as i32 - 20, y as i32 - 10);
+                let c = if x == y || x + y == 29 { 0x000000 }
+                    else if dx * dx + dy * dy < 36 { 0xE45C10 }
+                    else if y > 22 && x < 16 && ((x / 2) + (y / 2)) % 2 == 0 { 0xFCFCFC }
+                    else if y > 20 && x > 20 && (x - 20) > (30 - y) { 0x00A800 }
+                    else { 0x5C94FC };
+                frame[y * w + x] = c;
+            }
+        }
+        (frame, w, h)
+    }
+
+    fn fnv1a(data: &[u32]) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for p in data.iter() {
+            for byte in p.to_le_bytes().iter() {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+        hash
+    }
+
+    fn run(scaler: Scaler, src: &[u32], w: usize, h: usize) -> Vec<u32> {
+        let f = scaler.factor();
+        let mut dst = vec![0; w * h * f * f];
+        scale(scaler, src, w, h, &mut dst);
+        dst
+    }
+
+    #[test]
+    fn test_flat_image_unchanged() {
+        let src = vec![0x123456; 8 * 8];
+        for s in SCALERS.iter() {
+            assert!(run(*s, &src, 8, 8).iter().all(|p| *p == 0x123456), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn test_scale2x_diagonal() {
+        // B D pixels along a diagonal fill in the corner
+        let (k, w) = (0x000000, 0xFFFFFF);
+        let src = [
+            w, k, w,
+            k, w, w,
+            w, w, w,
+        ];
+        let dst = run(Scaler::Scale2x, &src, 3, 3);
+        // center pixel top left corner becomes black
+        assert_eq!(dst[2 * 6 + 2], k);
+        assert_eq!(dst[2 * 6 + 3], w);
+        assert_eq!(dst[3 * 6 + 3], w);
+    }
+
+    #[test]
+    fn test_scalers_golden_frame() {
+        let (frame, w, h) = test_frame();
+        // fnv1a of the scaled frame, regenerate when a scaler is intentionally changed
+        let golden: [(Scaler, u64); 5] = [
+            (Scaler::None, 0x02AF8BE9699382F1),
+            (Scaler::Scale2x, 0x43168C7C149AC7C1),
+            (Scaler::Scale3x, 0x6495103551877429),
+            (Scaler::Xbrz2x, 0x32E900996511FE4C),
+            (Scaler::Xbrz3x, 0x75265EF77F397B95),
+        ];
+
+        for (s, hash) in golden.iter() {
+            let out = run(*s, &frame, w, h);
+            assert_eq!(fnv1a(&out), *hash, "{:?} {:#018X}", s, fnv1a(&out));
+        }
+    }
+}
@@ -0,0 +1,96 @@
+/*
+    PNG screenshots of a processed frame. The ROM CRC32 and frame number are stored in tEXt
+    chunks so a screenshot can be traced back to the game and point in time it came from.
+*/
+
+use crate::consoles::EmuError;
+use std::io::{Read, Write};
+
+pub const ROM_CRC_KEYWORD: &str = "RomCrc32";
+pub const FRAME_KEYWORD: &str = "Frame";
+
+// frame is width x height 0RGB
+pub fn encode_png<W: Write>(w: W, frame: &[u32], width: usize, height: usize, rom_crc: u32, frame_number: u64) -> Result<(), EmuError> {
+    if width == 0 || height == 0 || frame.len() < width * height {
+        return Err(EmuError::ScreenshotError);
+    }
+
+    let mut encoder = png::Encoder::new(w, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.add_text_chunk(ROM_CRC_KEYWORD.to_string(), format!("{:08X}", rom_crc)).map_err(|_| EmuError::ScreenshotError)?;
+    encoder.add_text_chunk(FRAME_KEYWORD.to_string(), frame_number.to_string()).map_err(|_| EmuError::ScreenshotError)?;
+
+    let mut data = Vec::with_capacity(width * height * 3);
+    for c in frame[..width * height].iter() {
+        data.extend_from_slice(&[(c >> 16) as u8, (c >> 8) as u8, *c as u8]);
+    }
+
+    let mut writer = encoder.write_header().map_err(|_| EmuError::ScreenshotError)?;
+    writer.write_image_data(&data).map_err(|_| EmuError::ScreenshotError)
+}
+
+// crc32 of the index buffer as u16 little endian, independent of the palette
+pub fn index_buffer_hash(buffer: &[u16]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    for index in buffer.iter() {
+        hasher.update(&index.to_le_bytes());
+    }
+    hasher.finalize()
+}
+
+// 8 bit RGB or RGBA png to 0RGB, returns the frame, width and height
+pub fn decode_png<R: Read>(r: R) -> Result<(Vec<u32>, usize, usize), EmuError> {
+    let decoder = png::Decoder::new(r);
+    let mut reader = decoder.read_info().map_err(|_| EmuError::ScreenshotError)?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).map_err(|_| EmuError::ScreenshotError)?;
+
+    let channels = match (info.color_type, info.bit_depth) {
+        (png::ColorType::Rgb, png::BitDepth::Eight) => 3,
+        (png::ColorType::Rgba, png::BitDepth::Eight) => 4,
+        _ => return Err(EmuError::ScreenshotError),
+    };
+
+    let (width, height) = (info.width as usize, info.height as usize);
+    let frame = data[..width * height * channels].chunks(channels)
+        .map(|c| ((c[0] as u32) << 16) | ((c[1] as u32) << 8) | c[2] as u32)
+        .collect();
+    Ok((frame, width, height))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_png_round_trip() {
+        let frame: Vec<u32> = (0..16 * 8).map(|i| (i as u32) * 0x010203).collect();
+        let mut bytes = Vec::new();
+        assert!(encode_png(&mut bytes, &frame, 16, 8, 0xDEADBEEF, 1234).is_ok());
+
+        let decoder = png::Decoder::new(&bytes[..]);
+        let mut reader = decoder.read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+        assert_eq!((info.width, info.height), (16, 8));
+        assert_eq!(info.color_type, png::ColorType::Rgb);
+        assert_eq!(&data[3 * 5..3 * 6], &[0x05, 0x0A, 0x0F]);
+
+        let text = &reader.info().uncompressed_latin1_text;
+        assert!(text.iter().any(|t| t.keyword == ROM_CRC_KEYWORD && t.text == "DEADBEEF"));
+        assert!(text.iter().any(|t| t.keyword == FRAME_KEYWORD && t.text == "1234"));
+
+        let (decoded, width, height) = decode_png(&bytes[..]).unwrap();
+        assert_eq!((width, height), (16, 8));
+        assert_eq!(decoded, frame.iter().map(|c| c & 0xFFFFFF).collect::<Vec<u32>>());
+
+        assert!(encode_png(&mut bytes, &frame, 32, 8, 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_index_buffer_hash() {
+        assert_eq!(index_buffer_hash(&[]), 0);
+        assert_eq!(index_buffer_hash(&[0x0201, 0x0403]), crc32fast::hash(&[1, 2, 3, 4]));
+    }
+}
@@ -0,0 +1,191 @@
+use nes::consoles::{Console, EmuError};
+use nes::consoles::nes_ntsc::NesNtsc;
+use nes::utils::cpu_trace_logger::CpuTraceFormat;
+use nes::utils::fm2::{Fm2Movie, MovieCommand};
+use nes::video::output::Overscan;
+use nes::video::recorder::VideoRecorder;
+use nes::video::screenshot::index_buffer_hash;
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::process;
+use std::time::Instant;
+
+const WIDTH: usize = 256;
+const HEIGHT: usize = 240;
+const DEFAULT_FRAMES: u64 = 600;
+
+const USAGE: &str = "usage: rustnes-cli <rom.nes> [options]
+    --frames <count>        frames to run, defaults to the movie length or 600
+    --movie <file.fm2>      joypad input movie
+    --cheats <file.cht>     cheat list, one Game Genie or AAAA:VV[:CC] code per line
+    --screenshot <file.png> screenshot of the final frame
+    --overscan              crop the NTSC overscan from the screenshot
+    --ram <file.bin>        dump of the 2K internal ram after the final frame
+    --hashes <file.txt>     crc32 of the index buffer for every frame
+    --trace <file.log>      cpu trace of every frame, very large
+    --trace-format <format> cycles (default) or nestest, one line per instruction
+    --record <file.y4m>     record every frame, .avi for uncompressed avi";
+
+#[derive(Default)]
+struct Options {
+    rom: String,
+    frames: Option<u64>,
+    movie: Option<String>,
+    cheats: Option<String>,
+    screenshot: Option<String>,
+    overscan: bool,
+    ram: Option<String>,
+    hashes: Option<String>,
+    trace: Option<String>,
+    trace_format: Option<CpuTraceFormat>,
+    record: Option<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut it = args.iter();
+
+    while let Some(arg) = it.next() {
+        let mut value = || it.next().cloned().ok_or(format!("{} requires a value", arg));
+        match arg.as_str() {
+            "--frames" => {
+                let frames = value()?;
+                options.frames = Some(frames.parse().map_err(|_| format!("invalid frame count {}", frames))?);
+            }
+            "--movie" => options.movie = Some(value()?),
+            "--cheats" => options.cheats = Some(value()?),
+            "--screenshot" => options.screenshot = Some(value()?),
+            "--overscan" => options.overscan = true,
+            "--ram" => options.ram = Some(value()?),
+            "--hashes" => options.hashes = Some(value()?),
+            "--trace" => options.trace = Some(value()?),
+            "--trace-format" => {
+                options.trace_format = match value()?.as_str() {
+                    "cycles" => Some(CpuTraceFormat::Cycles),
+                    "nestest" => Some(CpuTraceFormat::Nestest),
+                    format => return Err(format!("unknown trace format {}", format)),
+                };
+            }
+            "--record" => options.record = Some(value()?),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ if options.rom.is_empty() => options.rom = arg.clone(),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    if options.rom.is_empty() {
+        return Err("no rom given".to_string());
+    }
+
+    Ok(options)
+}
+
+fn create_writer(path: &str) -> Result<BufWriter<File>, String> {
+    File::create(path).map(BufWriter::new).map_err(|e| format!("unable to create {} - {}", path, e))
+}
+
+fn run(options: Options) -> Result<(), String> {
+    if !Path::new(&options.rom).is_file() {
+        return Err(format!("unable to open {}", options.rom));
+    }
+
+    let movie = match &options.movie {
+        Some(path) => Some(Fm2Movie::from_file(path).map_err(|_| format!("unable to load movie {}", path))?),
+        None => None,
+    };
+    let frames = options.frames.unwrap_or_else(|| movie.as_ref().map_or(DEFAULT_FRAMES, |m| m.frame_count() as u64));
+
+    let mut hashes = match &options.hashes { Some(path) => Some(create_writer(path)?), None => None };
+    let mut trace = match &options.trace { Some(path) => Some(create_writer(path)?), None => None };
+    let mut recorder = match &options.record {
+        Some(path) => Some(VideoRecorder::create(path, WIDTH, HEIGHT, None).map_err(|_| format!("unable to create {}", path))?),
+        None => None,
+    };
+
+    let mut nes = NesNtsc::new();
+    nes.load_rom(&options.rom);
+    if let Some(path) = &options.cheats {
+        nes.load_cheats(path).map_err(|e| match e {
+            EmuError::InvalidCheat(code) => format!("invalid cheat {} in {}", code, path),
+            _ => format!("unable to load cheats {}", path),
+        })?;
+    }
+    nes.set_cpu_trace(trace.is_some());
+    nes.set_cpu_trace_format(options.trace_format.unwrap_or(CpuTraceFormat::Cycles));
+
+    let mut fb: Vec<u32> = vec![0; WIDTH*HEIGHT];
+    let start_instant = Instant::now();
+
+    for frame in 0..frames {
+        if let Some(movie) = &movie {
+            let input = movie.frame(frame as usize);
+            if input.commands.contains(MovieCommand::HARD_RESET) { nes.power_on_console(); }
+            if input.commands.contains(MovieCommand::SOFT_RESET) { nes.restart_console(); }
+            nes.input_joypad1_state(input.joypad1);
+            nes.input_joypad2_state(input.joypad2);
+        }
+
+        let result = nes.execute_frame();
+
+        if let Some(w) = &mut trace {
+            writeln!(w, "==== frame {}", nes.get_frame_number()).map_err(|e| e.to_string())?;
+            nes.output_cpu_log(w);
+        }
+
+        // the trace up to the jam is written first, it's usually what's needed to find out why
+        match result {
+            Ok(_) => { }
+            Err(EmuError::CpuJammed { pc, opcode }) => return Err(format!("CPU jammed at ${:04X} (opcode ${:02X}) in frame {}", pc, opcode, nes.get_frame_number())),
+            Err(e) => return Err(format!("emulation error {:?} in frame {}", e, nes.get_frame_number())),
+        }
+
+        if let Some(w) = &mut hashes {
+            let hash = index_buffer_hash(nes.get_index_buffer());
+            writeln!(w, "{} {:08X}", nes.get_frame_number(), hash).map_err(|e| e.to_string())?;
+        }
+
+        if let Some(r) = &mut recorder {
+            match nes.output_pixel_buffer(&mut fb) {
+                Ok(_) => r.write_frame(&fb).map_err(|_| "unable to write recording".to_string())?,
+                Err(_) => return Err(format!("invalid pixel buffer at frame {}", nes.get_frame_number())),
+            }
+        }
+    }
+
+    if let Some(path) = &options.screenshot {
+        let overscan = if options.overscan { Overscan::ntsc() } else { Overscan::NONE };
+        let mut w = create_writer(path)?;
+        match nes.output_screenshot(&mut w, overscan) {
+            Ok(_) => { }
+            Err(EmuError::PixBufferError) => return Err(format!("invalid pixel buffer at frame {}", nes.get_frame_number())),
+            Err(_) => return Err(format!("unable to write {}", path)),
+        }
+    }
+
+    if let Some(path) = &options.ram {
+        let mut w = create_writer(path)?;
+        w.write_all(&nes.get_cpu_ram()).map_err(|e| e.to_string())?;
+    }
+
+    if let Some(r) = recorder {
+        r.finish().map_err(|_| "unable to finish recording".to_string())?;
+    }
+
+    println!("{} - {} frames in {:?}, rom crc {:08X}", options.rom, frames, Instant::now() - start_instant, nes.get_rom_crc());
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = parse_args(&args).unwrap_or_else(|e| {
+        eprintln!("{}\n{}", e, USAGE);
+        process::exit(2);
+    });
+
+    if let Err(e) = run(options) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
@@ -2,6 +2,17 @@ use nes::consoles::{Console, EmuError};
 use nes::consoles::nes_ntsc::NesNtsc;
 use nes::JoypadInput;
 use nes::utils::{frame_limiter, average_duration};
+use nes::video::ntsc::{NtscFilter, NtscSettings};
+use nes::video::scalers::{self, Scaler};
+use nes::video::output::{VideoOutput, Overscan, PixelAspect};
+use nes::video::recorder::VideoRecorder;
+use nes::debugger::breakpoints::BreakpointHit;
+
+mod ram_search_window;
+mod ppu_viewer_window;
+
+use ram_search_window::RamSearchWindow;
+use ppu_viewer_window::{PpuViewerWindow, PpuViewer};
 
 use ::minifb::{Menu, Key, Window, WindowOptions, Scale, ScaleMode, KeyRepeat};
 
@@ -19,20 +30,50 @@ const MENU_BEGIN_LOG: usize = 5;
 const MENU_END_LOG: usize = 6;
 const MENU_POWERON: usize = 8;
 const MENU_RESTART: usize = 9;
+const MENU_VIDEO_PALETTE: usize = 11;
+const MENU_VIDEO_NTSC: usize = 12;
+const MENU_SCALER_NONE: usize = 14;
+const MENU_SCALER_SCALE2X: usize = 15;
+const MENU_SCALER_SCALE3X: usize = 16;
+const MENU_SCALER_XBRZ2X: usize = 19;
+const MENU_SCALER_XBRZ3X: usize = 20;
+const MENU_OVERSCAN_NONE: usize = 22;
+const MENU_OVERSCAN_NTSC: usize = 23;
+const MENU_ASPECT_SQUARE: usize = 25;
+const MENU_ASPECT_NTSC: usize = 26;
+const MENU_ASPECT_PAL: usize = 27;
+const MENU_RAM_SEARCH: usize = 29;
+const MENU_PPU_NAMETABLES: usize = 30;
+const MENU_PPU_PATTERN_TABLES: usize = 31;
+const MENU_PPU_OAM: usize = 32;
+const MENU_PPU_PALETTE_RAM: usize = 33;
 
 enum EmuMode {
     Normal,
     SingleFrame,
 }
 
-pub fn normal_execute<C: Console>(nes: &mut C, jp1: JoypadInput, fb: &mut [u32]) -> Duration {
+enum VideoMode {
+    Palette,
+    Ntsc,
+}
+
+// also returns the breakpoint the frame stopped on, the next call carries on from there
+pub fn normal_execute<C: Console>(nes: &mut C, jp1: JoypadInput, fb: &mut [u32]) -> (Duration, Option<BreakpointHit>) {
     let start_instant = Instant::now();
     nes.input_joypad1_state(jp1);                 
-    nes.execute_frame();
-    let emu_res = nes.output_pixel_buffer(fb);
+    let emu_res = nes.execute_frame().and_then(|_| nes.output_pixel_buffer(fb));
+    let mut breakpoint = None;
 
     match emu_res {
         Ok(_) => { }
+        // shown in the window title until the console is reset
+        Err(EmuError::CpuJammed { .. }) => { }
+        // not an error, the partial frame is shown while paused
+        Err(EmuError::BreakpointHit(hit)) => {
+            let _ = nes.output_pixel_buffer(fb);
+            breakpoint = Some(hit);
+        }
         Err(emu_err) => {
             let cpu_log_file = File::create(format!("logs\\cpu_trace-frame-{}-error.log", nes.get_frame_number())).unwrap();
             let mut cpu_log_writer = BufWriter::new(cpu_log_file);  
@@ -44,10 +85,65 @@ pub fn normal_execute<C: Console>(nes: &mut C, jp1: JoypadInput, fb: &mut [u32])
         }
     }
 
-    Instant::now() - start_instant
+    (Instant::now() - start_instant, breakpoint)
+}
+
+pub fn save_screenshot<C: Console>(nes: &mut C, overscan: Overscan) {
+    let dir = Path::new("screenshots");
+    let path = dir.join(format!("{:08X}-frame-{}.png", nes.get_rom_crc(), nes.get_frame_number()));
+    let file = match std::fs::create_dir_all(dir).and_then(|_| File::create(&path)) {
+        Ok(file) => file,
+        Err(_) => {
+            println!("failed to save {}", path.display());
+            return;
+        }
+    };
+
+    let mut writer = BufWriter::new(file);
+    match nes.output_screenshot(&mut writer, overscan) {
+        Ok(_) => println!("saved {}", path.display()),
+        Err(_) => println!("failed to save {}", path.display()),
+    }
+}
+
+// runs without a window as fast as possible, writing every frame to a .y4m or .avi file
+pub fn record_headless(rom_path: &str, record_path: &str, frames: u64) {
+    let mut nes = NesNtsc::new();
+    nes.load_rom(rom_path);
+
+    let mut fb: Vec<u32> = vec![0; WIDTH*HEIGHT];
+    let mut recorder = VideoRecorder::create(record_path, WIDTH, HEIGHT, None).unwrap_or_else(|_| {
+        panic!("unable to create {}", record_path);
+    });
+
+    let start_instant = Instant::now();
+    while recorder.frame_count() < frames {
+        normal_execute(&mut nes, JoypadInput::new(), &mut fb);
+        if recorder.write_frame(&fb).is_err() {
+            panic!("unable to write frame {} to {}", nes.get_frame_number(), record_path);
+        }
+    }
+
+    if recorder.finish().is_err() {
+        panic!("unable to finish {}", record_path);
+    }
+    println!("recorded {} frames to {} in {:?}", frames, record_path, Instant::now() - start_instant);
 }
 
 fn main() {
+    // rustnes-dev --record <out.y4m|out.avi> [--frames <count>] [rom]
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(i) = args.iter().position(|a| a == "--record") {
+        let record_path = args.get(i + 1).expect("--record requires an output path");
+        let frames = args.iter().position(|a| a == "--frames")
+            .map(|i| args.get(i + 1).and_then(|n| n.parse().ok()).expect("--frames requires a frame count"))
+            .unwrap_or(600);
+        let rom_path = args.last().filter(|a| a.ends_with(".nes")).map(|a| a.as_str()).unwrap_or("test_roms\\games\\donkey_kong.nes");
+
+        record_headless(rom_path, record_path, frames);
+        return;
+    }
+
     //debug_run("test_roms\\nestest.nes");
     //debug_run("test_roms\\donkey_kong.nes");
     //debug_run("test_roms\\2-nmi_and_brk.nes");
@@ -84,6 +180,11 @@ fn main() {
     let mut mode_menu = Menu::new("Emulation Mode").unwrap();
     let mut debug_menu = Menu::new("Debug").unwrap();
     let mut console_menu = Menu::new("Console").unwrap();
+    let mut video_menu = Menu::new("Video").unwrap();
+    let mut scaler_menu = Menu::new("Scaler").unwrap();
+    let mut overscan_menu = Menu::new("Overscan").unwrap();
+    let mut aspect_menu = Menu::new("Pixel Aspect").unwrap();
+    let mut ppu_menu = Menu::new("PPU").unwrap();
 
     mode_menu.add_item("Normal", MENU_NORMAL)
         .shortcut(Key::F1, 0)
@@ -98,6 +199,9 @@ fn main() {
     debug_menu.add_item("End Log", MENU_END_LOG)
         .shortcut(Key::F8, 0)
         .build();
+    debug_menu.add_item("RAM Search", MENU_RAM_SEARCH)
+        .shortcut(Key::F9, 0)
+        .build();
 
     menu.add_sub_menu("Emu Mode", &mode_menu);
     //menu.add_separator();
@@ -108,20 +212,75 @@ fn main() {
     console_menu.add_item("Restart", MENU_RESTART)
         .build();
 
+    video_menu.add_item("RGB Palette", MENU_VIDEO_PALETTE)
+        .shortcut(Key::F3, 0)
+        .build();
+    video_menu.add_item("NTSC Filter", MENU_VIDEO_NTSC)
+        .shortcut(Key::F4, 0)
+        .build();
+
+    scaler_menu.add_item("None", MENU_SCALER_NONE)
+        .build();
+    scaler_menu.add_item("Scale2x", MENU_SCALER_SCALE2X)
+        .build();
+    scaler_menu.add_item("Scale3x", MENU_SCALER_SCALE3X)
+        .build();
+    scaler_menu.add_item("xBRZ 2x", MENU_SCALER_XBRZ2X)
+        .build();
+    scaler_menu.add_item("xBRZ 3x", MENU_SCALER_XBRZ3X)
+        .build();
+    video_menu.add_sub_menu("Scaler", &scaler_menu);
+
+    overscan_menu.add_item("Show All", MENU_OVERSCAN_NONE)
+        .build();
+    overscan_menu.add_item("Crop 8 Lines", MENU_OVERSCAN_NTSC)
+        .build();
+    video_menu.add_sub_menu("Overscan", &overscan_menu);
+
+    aspect_menu.add_item("Square", MENU_ASPECT_SQUARE)
+        .build();
+    aspect_menu.add_item("NTSC 8:7", MENU_ASPECT_NTSC)
+        .build();
+    aspect_menu.add_item("PAL", MENU_ASPECT_PAL)
+        .build();
+    video_menu.add_sub_menu("Pixel Aspect", &aspect_menu);
+
+    ppu_menu.add_item("Nametables", MENU_PPU_NAMETABLES)
+        .build();
+    ppu_menu.add_item("Pattern Tables", MENU_PPU_PATTERN_TABLES)
+        .build();
+    ppu_menu.add_item("OAM", MENU_PPU_OAM)
+        .build();
+    ppu_menu.add_item("Palette RAM", MENU_PPU_PALETTE_RAM)
+        .build();
+
     window.add_menu(&menu);
     window.add_menu(&console_menu);
+    window.add_menu(&video_menu);
+    window.add_menu(&ppu_menu);
 
     // =============================================
 
     let mut emu_mode = EmuMode::Normal;
+    let mut video_mode = VideoMode::Palette;
+    let mut scaler = Scaler::None;
+    let mut video_output = VideoOutput::default();
     let mut emu_pause = false;
+    let mut breakpoint: Option<BreakpointHit> = None;
+    let mut take_screenshot = false;
     let mut exec_frame = false;
     let mut enable_trace_log = false;
+    let mut ram_search: Option<RamSearchWindow> = None;
+    let mut ppu_viewers: Vec<PpuViewerWindow> = Vec::new();
 
     let mut average_duration = average_duration::AverageDuration::new();
     let mut frame_limiter = frame_limiter::FrameLimiter::new(60);
 
     let mut fb: Vec<u32> = vec![0; WIDTH*HEIGHT];  
+    let ntsc_filter = NtscFilter::new(NtscSettings::default());
+    let mut ntsc_fb: Vec<u32> = vec![0; ntsc_filter.output_width()*HEIGHT];
+    let mut scaled_fb: Vec<u32> = vec![0; WIDTH*HEIGHT*9];
+    let mut output_fb: Vec<u32> = Vec::new();
     let mut nes = NesNtsc::new();
     let mut jp1 = JoypadInput::new();
 
@@ -141,9 +300,26 @@ fn main() {
                 }
                 MENU_BEGIN_LOG => {
                     enable_trace_log = true;
+                    nes.set_cpu_trace(true);
                 }
                 MENU_END_LOG => {
                     enable_trace_log = false;
+                    nes.set_cpu_trace(false);
+                }
+                MENU_RAM_SEARCH => {
+                    ram_search = Some(RamSearchWindow::open(&mut nes));
+                }
+                MENU_PPU_NAMETABLES | MENU_PPU_PATTERN_TABLES | MENU_PPU_OAM | MENU_PPU_PALETTE_RAM => {
+                    let viewer = match menu_id {
+                        MENU_PPU_NAMETABLES => PpuViewer::Nametables,
+                        MENU_PPU_PATTERN_TABLES => PpuViewer::PatternTables,
+                        MENU_PPU_OAM => PpuViewer::Oam,
+                        _ => PpuViewer::PaletteRam,
+                    };
+                    // one window per viewer
+                    if !ppu_viewers.iter().any(|window| window.viewer() == viewer) {
+                        ppu_viewers.push(PpuViewerWindow::open(viewer));
+                    }
                 }
                 MENU_POWERON => {
                     nes.power_on_console();
@@ -151,6 +327,22 @@ fn main() {
                 MENU_RESTART => {
                     nes.restart_console();
                 }
+                MENU_VIDEO_PALETTE => {
+                    video_mode = VideoMode::Palette;
+                }
+                MENU_VIDEO_NTSC => {
+                    video_mode = VideoMode::Ntsc;
+                }
+                MENU_SCALER_NONE => { scaler = Scaler::None; }
+                MENU_SCALER_SCALE2X => { scaler = Scaler::Scale2x; }
+                MENU_SCALER_SCALE3X => { scaler = Scaler::Scale3x; }
+                MENU_SCALER_XBRZ2X => { scaler = Scaler::Xbrz2x; }
+                MENU_SCALER_XBRZ3X => { scaler = Scaler::Xbrz3x; }
+                MENU_OVERSCAN_NONE => { video_output.overscan = Overscan::NONE; }
+                MENU_OVERSCAN_NTSC => { video_output.overscan = Overscan::ntsc(); }
+                MENU_ASPECT_SQUARE => { video_output.aspect = PixelAspect::Square; }
+                MENU_ASPECT_NTSC => { video_output.aspect = PixelAspect::Ntsc; }
+                MENU_ASPECT_PAL => { video_output.aspect = PixelAspect::Pal; }
                 _ => (),
             }
         });
@@ -176,7 +368,11 @@ fn main() {
             for t in keys {
                 match t {
                     Key::Period => exec_frame = true,
-                    Key::P => emu_pause = !emu_pause,
+                    Key::P => {
+                        emu_pause = !emu_pause;
+                        breakpoint = None;
+                    }
+                    Key::F12 => take_screenshot = true,
                     _ => (),
                 }
             }
@@ -185,11 +381,15 @@ fn main() {
         if emu_pause == false {
             match emu_mode {
                 EmuMode::Normal => {
-                    average_duration.update(normal_execute(&mut nes, jp1, &mut fb));
+                    let (duration, hit) = normal_execute(&mut nes, jp1, &mut fb);
+                    average_duration.update(duration);
+                    breakpoint = hit;
                 }
                 EmuMode::SingleFrame => {
                     if exec_frame {
-                        average_duration.update(normal_execute(&mut nes, jp1, &mut fb));
+                        let (duration, hit) = normal_execute(&mut nes, jp1, &mut fb);
+                        average_duration.update(duration);
+                        breakpoint = hit;
                         // trace logs quickly grow huge, only really useful if going frame by frame
                         if enable_trace_log {
                             let cpu_log_file = File::create(format!("logs\\cpu_trace-frame-{}.log", nes.get_frame_number())).unwrap();
@@ -206,9 +406,49 @@ fn main() {
         }
 
         exec_frame = false;
-        window.update_with_buffer(&fb, WIDTH, HEIGHT).unwrap();
+        // pause into the debugger, P resumes from the breakpoint
+        if breakpoint.is_some() {
+            emu_pause = true;
+        }
+        if take_screenshot {
+            save_screenshot(&mut nes, video_output.overscan);
+            take_screenshot = false;
+        }
+
+        if let Some(window) = ram_search.as_mut() {
+            window.update(&mut nes);
+        }
+        if ram_search.as_ref().is_some_and(|window| !window.is_open()) {
+            ram_search = None;
+        }
+        for window in ppu_viewers.iter_mut() {
+            window.update(&mut nes);
+        }
+        ppu_viewers.retain(|window| window.is_open());
+
+        let (frame, frame_width, frame_height) = match video_mode {
+            VideoMode::Palette if scaler == Scaler::None => {
+                (&fb[..], WIDTH, HEIGHT)
+            }
+            VideoMode::Palette => {
+                let factor = scaler.factor();
+                scalers::scale(scaler, &fb, WIDTH, HEIGHT, &mut scaled_fb);
+                (&scaled_fb[..WIDTH*HEIGHT*factor*factor], WIDTH*factor, HEIGHT*factor)
+            }
+            VideoMode::Ntsc => {
+                ntsc_filter.filter_frame(nes.get_index_buffer(), nes.get_scanline_phases(), &mut ntsc_fb);
+                (&ntsc_fb[..], ntsc_filter.output_width(), HEIGHT)
+            }
+        };
 
-        window.set_title(format!("RUSTNES --- avg frame execution {} us", average_duration.get_average_duration().as_micros()).as_str());
+        let (output_width, output_height) = video_output.process(frame, frame_width, frame_height, &mut output_fb);
+        window.update_with_buffer(&output_fb, output_width, output_height).unwrap();
+
+        match (nes.get_cpu_jam(), &breakpoint) {
+            (Some(jam), _) => window.set_title(format!("RUSTNES --- CPU jammed at ${:04X}", jam.pc).as_str()),
+            (None, Some(hit)) => window.set_title(format!("RUSTNES --- paused on breakpoint {} at ${:04X}, PC ${:04X}", hit.id, hit.address, nes.get_cpu_pc()).as_str()),
+            (None, None) => window.set_title(format!("RUSTNES --- avg frame execution {} us", average_duration.get_average_duration().as_micros()).as_str()),
+        }
         frame_limiter.wait();
     }
 }
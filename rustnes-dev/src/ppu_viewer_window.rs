@@ -0,0 +1,125 @@
+use nes::consoles::nes_ntsc::NesNtsc;
+use nes::ppu_viewers;
+
+use ::minifb::{Key, Window, WindowOptions, Scale, KeyRepeat};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PpuViewer {
+    Nametables,
+    PatternTables,
+    Oam,
+    PaletteRam,
+}
+
+impl PpuViewer {
+    fn size(&self) -> (usize, usize) {
+        match self {
+            PpuViewer::Nametables => (ppu_viewers::NAMETABLES_WIDTH, ppu_viewers::NAMETABLES_HEIGHT),
+            PpuViewer::PatternTables => (ppu_viewers::PATTERN_TABLES_WIDTH, ppu_viewers::PATTERN_TABLES_HEIGHT),
+            PpuViewer::Oam => (ppu_viewers::OAM_WIDTH, ppu_viewers::OAM_HEIGHT),
+            PpuViewer::PaletteRam => (ppu_viewers::PALETTE_RAM_WIDTH, ppu_viewers::PALETTE_RAM_HEIGHT),
+        }
+    }
+
+    fn scale(&self) -> Scale {
+        match self {
+            PpuViewer::Nametables => Scale::X1,
+            _ => Scale::X2,
+        }
+    }
+}
+
+/*
+    PPU viewer window, redrawn once per emulated frame
+    Nametables - S toggles the scroll window outline
+    Pattern tables - P cycles through the 8 palettes
+    OAM - Enter lists the sprites
+*/
+pub struct PpuViewerWindow {
+    window: Window,
+    viewer: PpuViewer,
+    buffer: Vec<u32>,
+    scroll_overlay: bool,
+    palette_index: u8,
+}
+
+impl PpuViewerWindow {
+    pub fn open(viewer: PpuViewer) -> PpuViewerWindow {
+        let (width, height) = viewer.size();
+        let window_options = WindowOptions {
+            scale: viewer.scale(),
+            ..WindowOptions::default()
+        };
+        let window = Window::new("PPU Viewer", width, height, window_options).unwrap_or_else(|e| {
+            panic!("{}", e);
+        });
+
+        PpuViewerWindow {
+            window,
+            viewer,
+            buffer: vec![0; width * height],
+            scroll_overlay: true,
+            palette_index: 0,
+        }
+    }
+
+    pub fn viewer(&self) -> PpuViewer {
+        self.viewer
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.window.is_open() && !self.window.is_key_down(Key::Escape)
+    }
+
+    pub fn update(&mut self, nes: &mut NesNtsc) {
+        let keys = self.window.get_keys_pressed(KeyRepeat::No).unwrap_or_default();
+        for key in keys {
+            match key {
+                Key::S => { self.scroll_overlay = !self.scroll_overlay; }
+                Key::P => { self.palette_index = (self.palette_index + 1) & 0x07; }
+                Key::Enter if self.viewer == PpuViewer::Oam => { print_sprites(nes); }
+                _ => {}
+            }
+        }
+
+        let title = match self.viewer {
+            PpuViewer::Nametables => {
+                nes.render_nametables(&mut self.buffer, self.scroll_overlay);
+                format!("Nametables - scroll overlay {}", if self.scroll_overlay { "on" } else { "off" })
+            }
+            PpuViewer::PatternTables => {
+                nes.render_pattern_tables(&mut self.buffer, self.palette_index);
+                match self.palette_index {
+                    0..=3 => format!("Pattern Tables - background palette {}", self.palette_index),
+                    _ => format!("Pattern Tables - sprite palette {}", self.palette_index - 4),
+                }
+            }
+            PpuViewer::Oam => {
+                nes.render_oam(&mut self.buffer);
+                "OAM - Enter lists the sprites".to_string()
+            }
+            PpuViewer::PaletteRam => {
+                nes.render_palette_ram(&mut self.buffer);
+                "Palette RAM".to_string()
+            }
+        };
+
+        let (width, height) = self.viewer.size();
+        self.window.set_title(&title);
+        self.window.update_with_buffer(&self.buffer, width, height).unwrap();
+    }
+}
+
+fn print_sprites(nes: &mut NesNtsc) {
+    println!("  #   X   Y  Tile Pal Pri Flip");
+    for sprite in nes.get_oam_sprites() {
+        let flip = match (sprite.hflip, sprite.vflip) {
+            (true, true) => "HV",
+            (true, false) => "H",
+            (false, true) => "V",
+            (false, false) => "",
+        };
+        println!("{:>3} {:>3} {:>3}   ${:02X}  {}  {}  {}", sprite.index, sprite.x, sprite.y, sprite.tile,
+            sprite.palette, if sprite.behind_background { "B" } else { "F" }, flip);
+    }
+}
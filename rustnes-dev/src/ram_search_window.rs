@@ -0,0 +1,183 @@
+use nes::consoles::Console;
+use nes::debugger::memory::MemoryType;
+use nes::debugger::ram_search::{RamSearch, SearchFilter, Comparison, ValueSize};
+
+use ::minifb::{Key, Window, WindowOptions, Scale, KeyRepeat};
+
+const COLUMNS: usize = 64;
+const CELL: usize = 4;
+const LISTED_CANDIDATES: usize = 64;
+// sign and 5 digits, enough for any 16 bit value
+const MAX_VALUE_DIGITS: usize = 6;
+
+const COLOR_CANDIDATE: u32 = 0x00C040;
+const COLOR_CHANGED: u32 = 0xF0D000;
+const COLOR_CHANGED_CANDIDATE: u32 = 0xF04040;
+
+/*
+    RAM search window, one cell per byte with internal ram on top and prg ram below
+    Candidates are green, bytes that changed in the last frame yellow (red for candidates)
+
+    F5 start, E equal, N not equal, G greater, L less than the previous step
+    Typing digits enters a value, E / N / G / L then compare with it instead, - flips its sign
+    and Backspace deletes a digit
+    + / - changed by 1 while no value is entered, B / W for 8 / 16 bit, U toggles signed
+    Enter lists the candidates
+*/
+pub struct RamSearchWindow {
+    window: Window,
+    search: RamSearch,
+    buffer: Vec<u32>,
+    last_frame: Vec<u8>,
+    prg_ram_size: usize,
+    value: String,
+}
+
+impl RamSearchWindow {
+    pub fn open<C: Console>(console: &mut C) -> RamSearchWindow {
+        let prg_ram_size = console.memory_size(MemoryType::PrgRam);
+        let rows = (0x800 + COLUMNS + prg_ram_size) / COLUMNS;
+        let (width, height) = (COLUMNS * CELL, rows * CELL);
+
+        let window_options = WindowOptions {
+            scale: Scale::X2,
+            ..WindowOptions::default()
+        };
+        let window = Window::new("RAM Search - F5 to start", width, height, window_options).unwrap_or_else(|e| {
+            panic!("{}", e);
+        });
+
+        let mut search = RamSearch::new(ValueSize::Bits8, false);
+        search.start(console);
+        RamSearchWindow {
+            window,
+            search,
+            buffer: vec![0; width * height],
+            last_frame: Vec::new(),
+            prg_ram_size,
+            value: String::new(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.window.is_open() && !self.window.is_key_down(Key::Escape)
+    }
+
+    // once per emulated frame
+    pub fn update<C: Console>(&mut self, console: &mut C) {
+        let mut mode = (self.search.size(), self.search.signed());
+        let keys = self.window.get_keys_pressed(KeyRepeat::No).unwrap_or_default();
+        for key in keys {
+            if let Some(digit) = key_digit(key) {
+                if self.value.len() < MAX_VALUE_DIGITS {
+                    self.value.push(digit);
+                }
+                continue;
+            }
+
+            let filter = match key {
+                Key::F5 => { self.search.start(console); None }
+                Key::E => Some(self.comparison_filter(Comparison::Equal)),
+                Key::N => Some(self.comparison_filter(Comparison::NotEqual)),
+                Key::G => Some(self.comparison_filter(Comparison::Greater)),
+                Key::L => Some(self.comparison_filter(Comparison::Less)),
+                Key::Backspace => { self.value.pop(); None }
+                Key::Minus | Key::NumPadMinus if !self.value.is_empty() => { self.flip_value_sign(); None }
+                Key::Equal | Key::NumPadPlus => Some(SearchFilter::ChangedBy(1)),
+                Key::Minus | Key::NumPadMinus => Some(SearchFilter::ChangedBy(-1)),
+                Key::B => { mode.0 = ValueSize::Bits8; None }
+                Key::W => { mode.0 = ValueSize::Bits16; None }
+                Key::U => { mode.1 = !mode.1; None }
+                Key::Enter => { self.print_candidates(console); None }
+                _ => None,
+            };
+
+            if let Some(filter) = filter {
+                self.search.filter(console, filter);
+            }
+        }
+        if mode != (self.search.size(), self.search.signed()) {
+            self.search.set_mode(mode.0, mode.1);
+        }
+
+        self.draw(console);
+        let mut title = format!("RAM Search - {} candidates - {} bit {}", self.search.candidate_count(),
+            if mode.0 == ValueSize::Bits8 { 8 } else { 16 }, if mode.1 { "signed" } else { "unsigned" });
+        if !self.value.is_empty() {
+            title.push_str(&format!(" - value {}", self.value));
+        }
+        self.window.set_title(&title);
+    }
+
+    // compares with the entered value if there is one and clears it, otherwise with the previous step
+    fn comparison_filter(&mut self, comparison: Comparison) -> SearchFilter {
+        match std::mem::take(&mut self.value).parse::<i64>() {
+            Ok(value) => SearchFilter::Value(comparison, value),
+            Err(_) => SearchFilter::Previous(comparison),
+        }
+    }
+
+    fn flip_value_sign(&mut self) {
+        match self.value.strip_prefix('-') {
+            Some(digits) => { self.value = digits.to_string(); }
+            None => { self.value.insert(0, '-'); }
+        }
+    }
+
+    fn draw<C: Console>(&mut self, console: &mut C) {
+        let width = COLUMNS * CELL;
+        let cpu_ram = (0..0x800).map(|i| (MemoryType::CpuRam, i, i));
+        // a blank row between the two
+        let prg_ram = (0..self.prg_ram_size).map(|i| (MemoryType::PrgRam, i, 0x800 + COLUMNS + i));
+
+        let mut frame = Vec::with_capacity(self.last_frame.len());
+        for (memory, offset, cell) in cpu_ram.chain(prg_ram) {
+            let value = console.peek_memory(memory, offset).unwrap_or(0);
+            let changed = self.last_frame.get(frame.len()).is_some_and(|&last| last != value);
+            frame.push(value);
+
+            let color = match (self.search.is_candidate(memory, offset), changed) {
+                (true, true) => COLOR_CHANGED_CANDIDATE,
+                (true, false) => COLOR_CANDIDATE,
+                (false, true) => COLOR_CHANGED,
+                // dim grey by value
+                (false, false) => 0x101010 * (1 + (value as u32 >> 5)),
+            };
+
+            let (x, y) = ((cell % COLUMNS) * CELL, (cell / COLUMNS) * CELL);
+            for row in 0..CELL - 1 {
+                let start = (y + row) * width + x;
+                self.buffer[start..start + CELL - 1].iter_mut().for_each(|p| *p = color);
+            }
+        }
+
+        self.last_frame = frame;
+        self.window.update_with_buffer(&self.buffer, width, self.buffer.len() / width).unwrap();
+    }
+
+    fn print_candidates<C: Console>(&self, console: &mut C) {
+        let candidates = self.search.candidates(console);
+        println!("{} candidates", candidates.len());
+        for candidate in candidates.iter().take(LISTED_CANDIDATES) {
+            let history: Vec<String> = candidate.history.iter().map(|v| v.to_string()).collect();
+            println!("${:04X}  {:>6}  {}", candidate.cpu_address(), candidate.value, history.join(" "));
+        }
+    }
+}
+
+fn key_digit(key: Key) -> Option<char> {
+    let digit = match key {
+        Key::Key0 | Key::NumPad0 => 0,
+        Key::Key1 | Key::NumPad1 => 1,
+        Key::Key2 | Key::NumPad2 => 2,
+        Key::Key3 | Key::NumPad3 => 3,
+        Key::Key4 | Key::NumPad4 => 4,
+        Key::Key5 | Key::NumPad5 => 5,
+        Key::Key6 | Key::NumPad6 => 6,
+        Key::Key7 | Key::NumPad7 => 7,
+        Key::Key8 | Key::NumPad8 => 8,
+        Key::Key9 | Key::NumPad9 => 9,
+        _ => return None,
+    };
+    std::char::from_digit(digit, 10)
+}
@@ -0,0 +1,6 @@
+#!/bin/sh
+# Fetches the reference nestest.log next to nestest.nes for test_nestest_log
+#   cargo test -p nes nestest_log -- --ignored
+set -e
+cd "$(dirname "$0")"
+curl -fsSL -o nestest.log https://raw.githubusercontent.com/christopherpow/nes-test-roms/master/other/nestest.log